@@ -0,0 +1,270 @@
+use crate::libs::cli::{
+    build_header_config, delimiter_arg, get_delimiter, header_args_with_columns,
+};
+use crate::libs::distribution::chi2_independence;
+use crate::libs::io::map_io_err;
+use crate::libs::number::{format_float, format_p_value};
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::header::Header;
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::{Row, TsvRow};
+use clap::*;
+use indexmap::IndexSet;
+use std::collections::HashMap;
+use std::io::Write;
+
+pub fn make_subcommand() -> Command {
+    Command::new("crosstab")
+        .about("Two-way contingency tables with margins and a chi-square test")
+        .after_help(include_str!("../../docs/help/crosstab.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Input TSV file(s) to process (default: stdin)"),
+        )
+        .arg(
+            Arg::new("rows")
+                .long("rows")
+                .short('r')
+                .num_args(1)
+                .required(true)
+                .help("Field whose values label the rows"),
+        )
+        .arg(
+            Arg::new("cols")
+                .long("cols")
+                .short('c')
+                .num_args(1)
+                .required(true)
+                .help("Field whose values label the columns"),
+        )
+        .arg(
+            Arg::new("percent")
+                .long("percent")
+                .num_args(1)
+                .value_parser(["row", "col", "total"])
+                .help("Write percentages of the row, column or grand total"),
+        )
+        .arg(
+            Arg::new("chi2")
+                .long("chi2")
+                .action(ArgAction::SetTrue)
+                .help("Append a chi-square test of independence"),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .action(ArgAction::SetTrue)
+                .help("Sort row and column labels (default: first appearance)"),
+        )
+        .arg(
+            Arg::new("float-precision")
+                .long("float-precision")
+                .short('p')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("2")
+                .help("Precision for percentages and the chi-square statistic"),
+        )
+        .args(header_args_with_columns())
+        .arg(delimiter_arg())
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+}
+
+type Labels<'a> = Vec<&'a [u8]>;
+
+/// Counts of each (row label, column label) pair, labels in first-seen order.
+#[derive(Default)]
+struct Contingency {
+    rows: IndexSet<Vec<u8>>,
+    cols: IndexSet<Vec<u8>>,
+    counts: HashMap<(usize, usize), u64>,
+}
+
+impl Contingency {
+    fn add(&mut self, row: &[u8], col: &[u8]) {
+        let (i, _) = self.rows.insert_full(row.to_vec());
+        let (j, _) = self.cols.insert_full(col.to_vec());
+        *self.counts.entry((i, j)).or_insert(0) += 1;
+    }
+
+    /// The observed counts, rows and columns in output order, with their labels.
+    fn table(&self, sort: bool) -> (Labels<'_>, Labels<'_>, Vec<Vec<u64>>) {
+        let order = |labels: &IndexSet<Vec<u8>>| {
+            let mut idxs: Vec<usize> = (0..labels.len()).collect();
+            if sort {
+                idxs.sort_by(|a, b| labels[*a].cmp(&labels[*b]));
+            }
+            idxs
+        };
+        let row_order = order(&self.rows);
+        let col_order = order(&self.cols);
+
+        let observed = row_order
+            .iter()
+            .map(|i| {
+                col_order
+                    .iter()
+                    .map(|j| self.counts.get(&(*i, *j)).copied().unwrap_or(0))
+                    .collect()
+            })
+            .collect();
+        (
+            row_order.iter().map(|i| self.rows[*i].as_slice()).collect(),
+            col_order.iter().map(|j| self.cols[*j].as_slice()).collect(),
+            observed,
+        )
+    }
+}
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+    let delimiter_char = opt_delimiter as char;
+
+    let rows_spec = args.get_one::<String>("rows").unwrap();
+    let cols_spec = args.get_one::<String>("cols").unwrap();
+    let percent = args.get_one::<String>("percent").map(|s| s.as_str());
+    let chi2 = args.get_flag("chi2");
+    let sort = args.get_flag("sort");
+    let precision = args.get_one::<usize>("float-precision").copied();
+
+    // 1-based indices of the row and column fields, and the row field name
+    let mut fields: Option<(usize, usize, String)> = None;
+    let mut table = Contingency::default();
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut column_names_bytes: Option<Vec<u8>> = None;
+        let mut column_names: Option<Vec<String>> = None;
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(map_io_err)?;
+            let Some(header_info) = header_result else {
+                continue; // Empty file
+            };
+            column_names_bytes = header_info.column_names_line.clone();
+            column_names =
+                Header::from_info(header_info, delimiter_char).column_names_list();
+        }
+
+        if fields.is_none() {
+            let resolver = FieldResolver::new(column_names_bytes, delimiter_char);
+            let row_idx = resolver
+                .resolve_single(rows_spec, "--rows")
+                .map_err(|e| anyhow::anyhow!(e))?;
+            let col_idx = resolver
+                .resolve_single(cols_spec, "--cols")
+                .map_err(|e| anyhow::anyhow!(e))?;
+            let row_name = column_names
+                .and_then(|names| names.get(row_idx - 1).cloned())
+                .unwrap_or_else(|| format!("field{}", row_idx));
+            fields = Some((row_idx, col_idx, row_name));
+        }
+        let (row_idx, col_idx, _) = fields.as_ref().unwrap();
+
+        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+            table.add(
+                row.get_bytes(*row_idx).unwrap_or(b""),
+                row.get_bytes(*col_idx).unwrap_or(b""),
+            );
+            Ok(())
+        })?;
+    }
+
+    let Some((_, _, row_name)) = fields else {
+        writer.flush()?;
+        return Ok(());
+    };
+    let (row_labels, col_labels, observed) = table.table(sort);
+
+    let row_totals: Vec<u64> = observed.iter().map(|row| row.iter().sum()).collect();
+    let col_totals: Vec<u64> = (0..col_labels.len())
+        .map(|j| observed.iter().map(|row| row[j]).sum())
+        .collect();
+    let total: u64 = row_totals.iter().sum();
+
+    // Formats a cell of row total `row_total` and column total `col_total`
+    let cell = |count: u64, row_total: u64, col_total: u64| -> String {
+        let base = match percent {
+            None => return count.to_string(),
+            Some("row") => row_total,
+            Some("col") => col_total,
+            _ => total,
+        };
+        format_float(count as f64 * 100.0 / base as f64, precision)
+    };
+
+    writer.write_all(row_name.as_bytes())?;
+    for label in &col_labels {
+        writer.write_all(&[opt_delimiter])?;
+        writer.write_all(label)?;
+    }
+    writer.write_all(&[opt_delimiter])?;
+    writer.write_all(b"total\n")?;
+
+    for ((label, counts), row_total) in row_labels.iter().zip(&observed).zip(&row_totals)
+    {
+        writer.write_all(label)?;
+        for (count, col_total) in counts.iter().zip(&col_totals) {
+            write!(
+                writer,
+                "{}{}",
+                delimiter_char,
+                cell(*count, *row_total, *col_total)
+            )?;
+        }
+        writeln!(
+            writer,
+            "{}{}",
+            delimiter_char,
+            cell(*row_total, *row_total, total)
+        )?;
+    }
+
+    writer.write_all(b"total")?;
+    for col_total in &col_totals {
+        write!(
+            writer,
+            "{}{}",
+            delimiter_char,
+            cell(*col_total, total, *col_total)
+        )?;
+    }
+    writeln!(writer, "{}{}", delimiter_char, cell(total, total, total))?;
+
+    if chi2 {
+        let (statistic, df, p_value) = chi2_independence(&observed);
+        writeln!(writer)?;
+        writeln!(writer, "chi2{0}df{0}p_value", delimiter_char)?;
+        writeln!(
+            writer,
+            "{1}{0}{2}{0}{3}",
+            delimiter_char,
+            format_float(statistic, precision),
+            df,
+            format_p_value(p_value)
+        )?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
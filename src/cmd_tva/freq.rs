@@ -0,0 +1,220 @@
+use crate::libs::cli::{
+    build_header_config, delimiter_arg, get_delimiter, header_args_with_columns,
+};
+use crate::libs::io::map_io_err;
+use crate::libs::number::format_float;
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::header::Header;
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::{Row, TsvRow};
+use clap::*;
+use indexmap::IndexMap;
+use std::io::Write;
+
+pub fn make_subcommand() -> Command {
+    Command::new("freq")
+        .about("Frequency tables with counts, percentages and top-k")
+        .after_help(include_str!("../../docs/help/freq.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Input TSV file(s) to process (default: stdin)"),
+        )
+        .arg(
+            Arg::new("fields")
+                .long("fields")
+                .short('f')
+                .num_args(1)
+                .required(true)
+                .help("Fields to count; each field gets its own table"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .short('n')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .help("Keep only the N most frequent values of each field"),
+        )
+        .arg(
+            Arg::new("other")
+                .long("other")
+                .action(ArgAction::SetTrue)
+                .requires("limit")
+                .help("Sum the values beyond --limit into a single row"),
+        )
+        .arg(
+            Arg::new("other-label")
+                .long("other-label")
+                .num_args(1)
+                .default_value("(other)")
+                .requires("other")
+                .help("Value written in the --other row"),
+        )
+        .arg(
+            Arg::new("float-precision")
+                .long("float-precision")
+                .short('p')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("2")
+                .help("Precision for the percentages"),
+        )
+        .args(header_args_with_columns())
+        .arg(delimiter_arg())
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+}
+
+/// Field name, 1-based index and value counts in first-seen order.
+type FreqTable = (String, usize, IndexMap<Vec<u8>, u64>);
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+    let delimiter_char = opt_delimiter as char;
+
+    let fields_spec = args.get_one::<String>("fields").unwrap();
+    let limit = args.get_one::<usize>("limit").copied();
+    let other = args.get_flag("other");
+    let other_label = args.get_one::<String>("other-label").unwrap();
+    let precision = args.get_one::<usize>("float-precision").copied();
+
+    let mut tables: Option<Vec<FreqTable>> = None;
+    let mut total: u64 = 0;
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut column_names_bytes: Option<Vec<u8>> = None;
+        let mut column_names: Option<Vec<String>> = None;
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(map_io_err)?;
+            let Some(header_info) = header_result else {
+                continue; // Empty file
+            };
+            column_names_bytes = header_info.column_names_line.clone();
+            column_names =
+                Header::from_info(header_info, delimiter_char).column_names_list();
+        }
+
+        if tables.is_none() {
+            let resolver = FieldResolver::new(column_names_bytes, delimiter_char);
+            let indices = resolver
+                .resolve(fields_spec)
+                .map_err(|e| anyhow::anyhow!(e))?;
+            tables = Some(
+                indices
+                    .into_iter()
+                    .map(|idx| {
+                        let name = column_names
+                            .as_ref()
+                            .and_then(|names| names.get(idx - 1).cloned())
+                            .unwrap_or_else(|| format!("field{}", idx));
+                        (name, idx, IndexMap::new())
+                    })
+                    .collect(),
+            );
+        }
+        let tables = tables.as_mut().unwrap();
+
+        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+            total += 1;
+            for (_, idx, counts) in tables.iter_mut() {
+                let value = row.get_bytes(*idx).unwrap_or(b"");
+                match counts.get_mut(value) {
+                    Some(count) => *count += 1,
+                    None => {
+                        counts.insert(value.to_vec(), 1);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    writeln!(
+        writer,
+        "field{0}value{0}count{0}percent{0}cum_percent",
+        delimiter_char
+    )?;
+
+    let percent =
+        |count: u64| format_float(count as f64 * 100.0 / total as f64, precision);
+    for (name, _, counts) in tables.unwrap_or_default() {
+        let mut entries: Vec<(Vec<u8>, u64)> = counts.into_iter().collect();
+        // Stable sort: ties keep the order in which values were first seen
+        entries.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        let kept = limit.unwrap_or(entries.len()).min(entries.len());
+        let mut cumulative: u64 = 0;
+        for (value, count) in &entries[..kept] {
+            cumulative += count;
+            write_entry(
+                &mut writer,
+                opt_delimiter,
+                &name,
+                value,
+                *count,
+                &percent,
+                cumulative,
+            )?;
+        }
+
+        if other && kept < entries.len() {
+            let rest: u64 = entries[kept..].iter().map(|(_, count)| count).sum();
+            cumulative += rest;
+            write_entry(
+                &mut writer,
+                opt_delimiter,
+                &name,
+                other_label.as_bytes(),
+                rest,
+                &percent,
+                cumulative,
+            )?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_entry<W: Write + ?Sized>(
+    writer: &mut W,
+    delimiter: u8,
+    name: &str,
+    value: &[u8],
+    count: u64,
+    percent: &dyn Fn(u64) -> String,
+    cumulative: u64,
+) -> std::io::Result<()> {
+    writer.write_all(name.as_bytes())?;
+    writer.write_all(&[delimiter])?;
+    writer.write_all(value)?;
+    writeln!(
+        writer,
+        "{0}{1}{0}{2}{0}{3}",
+        delimiter as char,
+        count,
+        percent(count),
+        percent(cumulative)
+    )
+}
@@ -0,0 +1,506 @@
+use crate::libs::cli::{
+    build_header_config, delimiter_arg, get_delimiter, header_args_with_columns,
+};
+use crate::libs::io::map_io_err;
+use crate::libs::number::{fast_parse_f64, format_float};
+use crate::libs::plot::bar::{render_bar, BarScale};
+use crate::libs::plot::freedman_diaconis_bins;
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::{Row, TsvRow};
+use chrono::{Datelike, Months, NaiveDate};
+use clap::*;
+use indexmap::IndexMap;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Upper bound on the number of bins picked by the Freedman-Diaconis rule.
+const MAX_AUTO_BINS: usize = 50;
+
+pub fn make_subcommand() -> Command {
+    Command::new("hist")
+        .about("Horizontal bar charts and histograms printed as plain text")
+        .after_help(include_str!("../../docs/help/hist.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Input TSV file(s) to process (default: stdin)"),
+        )
+        .arg(
+            Arg::new("label")
+                .long("label")
+                .short('l')
+                .num_args(1)
+                .help("Field whose values label the bars"),
+        )
+        .arg(
+            Arg::new("count")
+                .long("count")
+                .short('c')
+                .num_args(1)
+                .requires("label")
+                .help("Field holding the length of each bar (default: count rows)"),
+        )
+        .arg(
+            Arg::new("bins")
+                .long("bins")
+                .short('b')
+                .num_args(1)
+                .conflicts_with_all(["label", "dates"])
+                .help("Numeric field to bin into a histogram"),
+        )
+        .group(
+            ArgGroup::new("source")
+                .args(["label", "bins"])
+                .required(true),
+        )
+        .arg(
+            Arg::new("num-bins")
+                .long("num-bins")
+                .short('n')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .requires("bins")
+                .help("Number of bins (default: Freedman-Diaconis rule)"),
+        )
+        .arg(
+            Arg::new("group")
+                .long("group")
+                .short('g')
+                .num_args(1)
+                .help("Field whose values split the input into separate charts"),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .num_args(1)
+                .value_parser(["count", "label"])
+                .help(
+                    "Sort bars by decreasing count or by label (default: input order)",
+                ),
+        )
+        .arg(
+            Arg::new("log")
+                .long("log")
+                .action(ArgAction::SetTrue)
+                .help("Use a logarithmic scale for bar lengths"),
+        )
+        .arg(
+            Arg::new("compress-gaps")
+                .long("compress-gaps")
+                .action(ArgAction::SetTrue)
+                .help("Collapse runs of zero-count bars into a single line"),
+        )
+        .arg(
+            Arg::new("dates")
+                .long("dates")
+                .action(ArgAction::SetTrue)
+                .help("Labels are dates; add the missing ones with a count of zero"),
+        )
+        .arg(
+            Arg::new("width")
+                .long("width")
+                .short('w')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("40")
+                .help("Width of the longest bar, in characters"),
+        )
+        .arg(
+            Arg::new("float-precision")
+                .long("float-precision")
+                .short('p')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("2")
+                .help("Precision for counts, percentages and bin edges"),
+        )
+        .args(header_args_with_columns())
+        .arg(delimiter_arg())
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+}
+
+/// 1-based indices of the fields feeding the charts.
+struct Fields {
+    group: Option<usize>,
+    label: Option<usize>,
+    count: Option<usize>,
+    bins: Option<usize>,
+}
+
+/// Bars of one chart, as (label, count).
+type Bars = Vec<(String, f64)>;
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+    let delimiter_char = opt_delimiter as char;
+
+    let num_bins = args.get_one::<usize>("num-bins").copied();
+    if num_bins == Some(0) {
+        anyhow::bail!("--num-bins must be at least 1");
+    }
+    let sort = args.get_one::<String>("sort").map(|s| s.as_str());
+    let dates = args.get_flag("dates");
+    let precision = args.get_one::<usize>("float-precision").copied();
+    let chart = Chart {
+        scale: if args.get_flag("log") {
+            BarScale::Log
+        } else {
+            BarScale::Linear
+        },
+        width: *args.get_one::<usize>("width").unwrap(),
+        compress_gaps: args.get_flag("compress-gaps"),
+        precision,
+    };
+
+    let mut fields: Option<Fields> = None;
+    // Per group: summed counts by label, or the values to bin
+    let mut counts: IndexMap<Vec<u8>, IndexMap<Vec<u8>, f64>> = IndexMap::new();
+    let mut samples: IndexMap<Vec<u8>, Vec<f64>> = IndexMap::new();
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut column_names_bytes: Option<Vec<u8>> = None;
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(map_io_err)?;
+            let Some(header_info) = header_result else {
+                continue; // Empty file
+            };
+            column_names_bytes = header_info.column_names_line;
+        }
+
+        if fields.is_none() {
+            let resolver = FieldResolver::new(column_names_bytes, delimiter_char);
+            let resolve = |name: &str, option: &str| {
+                args.get_one::<String>(name)
+                    .map(|spec| resolver.resolve_single(spec, option))
+                    .transpose()
+                    .map_err(|e| anyhow::anyhow!(e))
+            };
+            fields = Some(Fields {
+                group: resolve("group", "--group")?,
+                label: resolve("label", "--label")?,
+                count: resolve("count", "--count")?,
+                bins: resolve("bins", "--bins")?,
+            });
+        }
+        let fields = fields.as_ref().unwrap();
+
+        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+            let group = fields
+                .group
+                .and_then(|idx| row.get_bytes(idx))
+                .unwrap_or(b"");
+
+            if let Some(idx) = fields.bins {
+                let values = samples.entry(group.to_vec()).or_default();
+                // NaN and infinite values have no bin
+                if let Some(value) = row
+                    .get_bytes(idx)
+                    .and_then(fast_parse_f64)
+                    .filter(|v| v.is_finite())
+                {
+                    values.push(value);
+                }
+                return Ok(());
+            }
+
+            let label = row.get_bytes(fields.label.unwrap()).unwrap_or(b"");
+            let count = match fields.count {
+                Some(idx) => {
+                    let bytes = row.get_bytes(idx).unwrap_or(b"");
+                    if bytes.trim_ascii().is_empty() {
+                        0.0
+                    } else {
+                        // Negative counts would also skew the percentages
+                        fast_parse_f64(bytes)
+                            .filter(|v| v.is_finite() && *v >= 0.0)
+                            .ok_or_else(|| {
+                                std::io::Error::new(
+                                    std::io::ErrorKind::InvalidData,
+                                    format!(
+                                        "invalid count `{}`",
+                                        String::from_utf8_lossy(bytes)
+                                    ),
+                                )
+                            })?
+                    }
+                }
+                None => 1.0,
+            };
+            *counts
+                .entry(group.to_vec())
+                .or_default()
+                .entry(label.to_vec())
+                .or_insert(0.0) += count;
+            Ok(())
+        })?;
+    }
+
+    let mut charts: Vec<(Vec<u8>, Bars)> = Vec::new();
+    for (group, labels) in counts {
+        let mut bars: Bars = labels
+            .into_iter()
+            .map(|(label, count)| (String::from_utf8_lossy(&label).into_owned(), count))
+            .collect();
+        if dates {
+            bars = fill_dates(bars)?;
+        }
+        charts.push((group, bars));
+    }
+    for (group, values) in samples {
+        charts.push((group, bin_values(&values, num_bins, precision)));
+    }
+
+    let grouped = fields.is_some_and(|f| f.group.is_some());
+    for (i, (group, mut bars)) in charts.into_iter().enumerate() {
+        match sort {
+            Some("count") => bars.sort_by(|a, b| b.1.total_cmp(&a.1)),
+            Some("label") => bars.sort_by(|a, b| a.0.cmp(&b.0)),
+            _ => {}
+        }
+        if grouped {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            writer.write_all(&group)?;
+            writeln!(writer)?;
+        }
+        chart.write(&mut writer, &bars)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// A line of a chart: a bar, or a run of zero-count bars left out.
+enum Line<'a> {
+    Bar(&'a str, f64),
+    Gap(usize),
+}
+
+/// How bars are drawn.
+struct Chart {
+    scale: BarScale,
+    width: usize,
+    compress_gaps: bool,
+    precision: Option<usize>,
+}
+
+impl Chart {
+    /// Writes one line per bar: the label, the count, the percentage of the
+    /// chart total and the bar.
+    fn write(
+        &self,
+        writer: &mut dyn Write,
+        bars: &[(String, f64)],
+    ) -> std::io::Result<()> {
+        let max = bars.iter().fold(0.0f64, |max, bar| max.max(bar.1));
+        let total: f64 = bars.iter().map(|bar| bar.1).sum();
+
+        // Bars to draw, with runs of zeros replaced by their length
+        let mut lines: Vec<Line> = Vec::new();
+        let mut start = 0;
+        while start < bars.len() {
+            let zeros = bars[start..].iter().take_while(|bar| bar.1 == 0.0).count();
+            if self.compress_gaps && zeros > 1 {
+                lines.push(Line::Gap(zeros));
+                start += zeros;
+            } else {
+                lines.push(Line::Bar(&bars[start].0, bars[start].1));
+                start += 1;
+            }
+        }
+
+        let cells: Vec<(String, String)> = bars
+            .iter()
+            .map(|(_, count)| {
+                (
+                    format_float(*count, self.precision),
+                    format!("{}%", format_float(count * 100.0 / total, self.precision)),
+                )
+            })
+            .collect();
+        let label_width = lines
+            .iter()
+            .map(|line| match line {
+                Line::Bar(label, _) => label.chars().count(),
+                Line::Gap(_) => 1,
+            })
+            .max()
+            .unwrap_or(0);
+        let count_width = cells.iter().map(|c| c.0.len()).max().unwrap_or(0);
+        let percent_width = cells.iter().map(|c| c.1.len()).max().unwrap_or(0);
+
+        let mut idx = 0;
+        for line in lines {
+            match line {
+                Line::Bar(label, count) => {
+                    let (count_str, percent_str) = &cells[idx];
+                    writeln!(
+                        writer,
+                        "{:<lw$}  {:>cw$}  {:>pw$} │{}",
+                        label,
+                        count_str,
+                        percent_str,
+                        render_bar(self.scale.ratio(count, max), self.width),
+                        lw = label_width,
+                        cw = count_width,
+                        pw = percent_width,
+                    )?;
+                    idx += 1;
+                }
+                Line::Gap(zeros) => {
+                    writeln!(
+                        writer,
+                        "{:<lw$}  {:>cw$}  {:>pw$} │({} empty)",
+                        "…",
+                        "",
+                        "",
+                        zeros,
+                        lw = label_width,
+                        cw = count_width,
+                        pw = percent_width,
+                    )?;
+                    idx += zeros;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits `values` into equal-width bins between their minimum and maximum.
+/// The last bin includes the maximum.
+fn bin_values(
+    values: &[f64],
+    num_bins: Option<usize>,
+    precision: Option<usize>,
+) -> Bars {
+    let Some(min) = values.iter().copied().reduce(f64::min) else {
+        return Vec::new();
+    };
+    let max = values.iter().copied().fold(min, f64::max);
+    if min == max {
+        let edge = format_float(min, precision);
+        return vec![(format!("[{}, {}]", edge, edge), values.len() as f64)];
+    }
+
+    let num_bins = num_bins
+        .unwrap_or_else(|| freedman_diaconis_bins(values).clamp(1, MAX_AUTO_BINS));
+    let width = (max - min) / num_bins as f64;
+    let mut counts = vec![0.0; num_bins];
+    for value in values {
+        let idx = ((value - min) / width).floor() as usize;
+        counts[idx.min(num_bins - 1)] += 1.0;
+    }
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let lower = format_float(min + i as f64 * width, precision);
+            let label = if i + 1 == num_bins {
+                format!("[{}, {}]", lower, format_float(max, precision))
+            } else {
+                let upper = format_float(min + (i + 1) as f64 * width, precision);
+                format!("[{}, {})", lower, upper)
+            };
+            (label, count)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateUnit {
+    Day,
+    Month,
+    Year,
+}
+
+impl DateUnit {
+    fn parse(label: &str) -> Option<(DateUnit, NaiveDate)> {
+        if let Ok(date) = NaiveDate::parse_from_str(label, "%Y-%m-%d") {
+            return Some((DateUnit::Day, date));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(&format!("{}-01", label), "%Y-%m-%d")
+        {
+            return Some((DateUnit::Month, date));
+        }
+        if label.len() == 4 && label.bytes().all(|b| b.is_ascii_digit()) {
+            let date = NaiveDate::from_ymd_opt(label.parse().ok()?, 1, 1)?;
+            return Some((DateUnit::Year, date));
+        }
+        None
+    }
+
+    fn next(&self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            DateUnit::Day => date.succ_opt(),
+            DateUnit::Month => date.checked_add_months(Months::new(1)),
+            DateUnit::Year => date.checked_add_months(Months::new(12)),
+        }
+    }
+
+    fn format(&self, date: NaiveDate) -> String {
+        match self {
+            DateUnit::Day => date.format("%Y-%m-%d").to_string(),
+            DateUnit::Month => format!("{:04}-{:02}", date.year(), date.month()),
+            DateUnit::Year => format!("{:04}", date.year()),
+        }
+    }
+}
+
+/// Orders date labels chronologically and adds the missing days, months or
+/// years between the first and the last one, with a count of zero.
+fn fill_dates(bars: Bars) -> anyhow::Result<Bars> {
+    let mut unit: Option<DateUnit> = None;
+    let mut by_date: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+    for (label, count) in &bars {
+        let Some((label_unit, date)) = DateUnit::parse(label) else {
+            anyhow::bail!("--dates: `{}` is not a date", label);
+        };
+        if unit.is_some_and(|unit| unit != label_unit) {
+            anyhow::bail!("--dates: `{}` does not match the other dates", label);
+        }
+        unit = Some(label_unit);
+        *by_date.entry(date).or_insert(0.0) += count;
+    }
+    let (Some(unit), Some(&last)) = (unit, by_date.keys().last()) else {
+        return Ok(bars);
+    };
+
+    let mut filled = Vec::new();
+    let mut date = *by_date.keys().next().unwrap();
+    while date <= last {
+        filled.push((
+            unit.format(date),
+            by_date.get(&date).copied().unwrap_or(0.0),
+        ));
+        match unit.next(date) {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+    Ok(filled)
+}
@@ -1,13 +1,19 @@
 use crate::libs::cli::{build_header_config, get_delimiter, header_args_with_columns};
+use crate::libs::fuzzy::{self, Similarity};
+use crate::libs::interval::IntervalTree;
+use crate::libs::number::{format_float, parse_timestamp};
+use crate::libs::sort::{KeyField, KeyOptions, SortKey, SortSpec, SortedReader};
 use crate::libs::tsv::fields::FieldResolver;
-use crate::libs::tsv::header::{write_header, Header};
-use crate::libs::tsv::key::{KeyBuffer, KeyExtractor};
+use crate::libs::tsv::header::{write_header, Header, HeaderConfig};
+use crate::libs::tsv::key::{KeyBuffer, KeyExtractor, ParsedKey};
 use crate::libs::tsv::reader::TsvReader;
-use crate::libs::tsv::record::TsvRow;
+use crate::libs::tsv::record::{TsvRecord, TsvRow};
+use crate::libs::tsv::select::SelectPlan;
 use ahash::RandomState;
 use clap::*;
-use std::collections::HashMap;
-use std::io::Write;
+use indexmap::IndexMap;
+use smallvec::{smallvec, SmallVec};
+use std::io::{Read, Write};
 use std::ops::Range;
 
 pub fn make_subcommand() -> Command {
@@ -64,6 +70,31 @@ pub fn make_subcommand() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Exclude matching records (anti-join)"),
         )
+        .arg(
+            Arg::new("how")
+                .long("how")
+                .num_args(1)
+                .value_parser(["inner", "left", "right", "full"])
+                .conflicts_with_all(["exclude", "write-all"])
+                .help("Relational join type; writes the columns of both files"),
+        )
+        .arg(
+            Arg::new("fill")
+                .long("fill")
+                .num_args(1)
+                .default_value("")
+                .allow_hyphen_values(true)
+                .requires("how")
+                .help("With --how, value for the columns of the missing side"),
+        )
+        .arg(
+            Arg::new("suffixes")
+                .long("suffixes")
+                .num_args(1)
+                .default_value(".x,.y")
+                .requires("how")
+                .help("With --how, suffixes for data and filter columns with the same name"),
+        )
         .args(header_args_with_columns())
         .arg(
             Arg::new("delimiter")
@@ -87,6 +118,114 @@ pub fn make_subcommand() -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Allow duplicate keys in the filter file (last entry wins)"),
         )
+        .arg(
+            Arg::new("all-matches")
+                .long("all-matches")
+                .short('m')
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["allow-duplicate-keys", "exclude"])
+                .help("Keep every filter record per key; write a data record once per match"),
+        )
+        .arg(
+            Arg::new("relationship")
+                .long("relationship")
+                .num_args(1)
+                .value_parser(["one-to-one", "one-to-many", "many-to-one", "many-to-many"])
+                .conflicts_with_all(["allow-duplicate-keys", "exclude"])
+                .help("Expected data-to-filter relationship of matches; fail on violation"),
+        )
+        .arg(
+            Arg::new("sorted")
+                .long("sorted")
+                .action(ArgAction::SetTrue)
+                .help("Both inputs are sorted by the key; stream them in a merge join"),
+        )
+        .arg(
+            Arg::new("interval")
+                .long("interval")
+                .num_args(1)
+                .conflicts_with_all([
+                    "key-fields",
+                    "data-fields",
+                    "sorted",
+                    "relationship",
+                    "allow-duplicate-keys",
+                ])
+                .help("Filter file chrom,start,end fields; join records with overlapping intervals"),
+        )
+        .arg(
+            Arg::new("data-interval")
+                .long("data-interval")
+                .num_args(1)
+                .requires("interval")
+                .help("Data stream chrom,start,end fields (default: --interval)"),
+        )
+        .arg(
+            Arg::new("half-open")
+                .long("half-open")
+                .action(ArgAction::SetTrue)
+                .requires("interval")
+                .help("Intervals are 0-based and half-open, as in BED (default: 1-based, closed)"),
+        )
+        .arg(
+            Arg::new("asof")
+                .long("asof")
+                .num_args(1)
+                .conflicts_with_all([
+                    "interval",
+                    "sorted",
+                    "how",
+                    "relationship",
+                    "all-matches",
+                    "allow-duplicate-keys",
+                ])
+                .help("Filter file field to match as of: the greatest value not exceeding the data value"),
+        )
+        .arg(
+            Arg::new("data-asof")
+                .long("data-asof")
+                .num_args(1)
+                .requires("asof")
+                .help("Data stream as-of field (default: --asof)"),
+        )
+        .arg(
+            Arg::new("tolerance")
+                .long("tolerance")
+                .num_args(1)
+                .requires("asof")
+                .help("Maximum distance of an as-of match; seconds for timestamps (suffixes: s, m, h, d)"),
+        )
+        .arg(
+            Arg::new("fuzzy")
+                .long("fuzzy")
+                .num_args(1)
+                .value_parser(["normalized", "levenshtein", "jaro-winkler"])
+                .conflicts_with_all([
+                    "interval",
+                    "asof",
+                    "sorted",
+                    "how",
+                    "relationship",
+                    "all-matches",
+                ])
+                .help("Match normalized keys, by similarity; appends the score"),
+        )
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .num_args(1)
+                .default_value("0.8")
+                .value_parser(value_parser!(f64))
+                .requires("fuzzy")
+                .help("With --fuzzy, minimum similarity of a match, from 0 to 1"),
+        )
+        .arg(
+            Arg::new("require-match")
+                .long("require-match")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("exclude")
+                .help("Fail if a data record has no match in the filter file"),
+        )
         .arg(
             Arg::new("line-buffered")
                 .long("line-buffered")
@@ -105,10 +244,12 @@ pub fn make_subcommand() -> Command {
 
 /// Parse field specification for join keys using FieldResolver.
 /// Returns (is_whole_line, field_indices).
-/// Note: Key fields are sorted to ensure consistent key matching.
+/// Note: Key fields are sorted to ensure consistent key matching, unless
+/// `keep_order` is set (sorted joins compare keys in the given field order).
 fn parse_join_field_spec(
     spec_opt: Option<&str>,
     resolver: &FieldResolver,
+    keep_order: bool,
 ) -> anyhow::Result<(bool, Option<Vec<usize>>)> {
     let spec = spec_opt.unwrap_or("0");
     let trimmed = spec.trim();
@@ -118,9 +259,11 @@ fn parse_join_field_spec(
     let mut indices = resolver
         .resolve(trimmed)
         .map_err(|e| anyhow::anyhow!("{}", e))?;
-    // Sort and deduplicate key fields for consistent key matching
-    indices.sort_unstable();
-    indices.dedup();
+    if !keep_order {
+        // Sort and deduplicate key fields for consistent key matching
+        indices.sort_unstable();
+        indices.dedup();
+    }
     Ok((false, Some(indices)))
 }
 
@@ -225,285 +368,1746 @@ fn build_write_all_fill(
     Some(s)
 }
 
-pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
-    let mut writer =
-        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+/// Join type selected with `--how`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum JoinHow {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
 
-    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
-        Some(values) => values.cloned().collect(),
-        None => vec!["stdin".to_string()],
-    };
+impl JoinHow {
+    fn from_arg(s: &str) -> Self {
+        match s {
+            "left" => JoinHow::Left,
+            "right" => JoinHow::Right,
+            "full" => JoinHow::Full,
+            _ => JoinHow::Inner,
+        }
+    }
 
-    let filter_file = args.get_one::<String>("filter-file").unwrap().to_string();
-    let key_fields_spec = args.get_one::<String>("key-fields").map(|s| s.as_str());
-    let data_fields_spec = args.get_one::<String>("data-fields").map(|s| s.as_str());
-    let append_fields_spec = args.get_one::<String>("append-fields").map(|s| s.as_str());
-    let write_all_value = args.get_one::<String>("write-all").map(|s| s.as_str());
+    /// Whether data records without a match are written.
+    fn keeps_unmatched_data(self) -> bool {
+        matches!(self, JoinHow::Left | JoinHow::Full)
+    }
 
-    // Build HeaderConfig from arguments
-    let header_config =
-        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    /// Whether filter records without a match are written.
+    fn keeps_unmatched_filter(self) -> bool {
+        matches!(self, JoinHow::Right | JoinHow::Full)
+    }
+}
 
-    let allow_duplicate_keys = args.get_flag("allow-duplicate-keys");
-    let line_buffered = args.get_flag("line-buffered");
-    let exclude = args.get_flag("exclude");
+/// Expected relationship between data records (left) and filter records (right).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Relationship {
+    OneToOne,
+    OneToMany,
+    ManyToOne,
+    ManyToMany,
+}
 
-    let opt_delimiter = get_delimiter(args, "delimiter")?;
-    let delimiter_char = opt_delimiter as char;
+impl Relationship {
+    fn from_arg(s: &str) -> Self {
+        match s {
+            "one-to-one" => Relationship::OneToOne,
+            "one-to-many" => Relationship::OneToMany,
+            "many-to-one" => Relationship::ManyToOne,
+            _ => Relationship::ManyToMany,
+        }
+    }
 
-    // Validate argument combinations
-    if exclude && append_fields_spec.is_some() {
-        anyhow::bail!("--exclude cannot be used with --append-fields");
+    fn name(self) -> &'static str {
+        match self {
+            Relationship::OneToOne => "one-to-one",
+            Relationship::OneToMany => "one-to-many",
+            Relationship::ManyToOne => "many-to-one",
+            Relationship::ManyToMany => "many-to-many",
+        }
     }
-    if exclude && write_all_value.is_some() {
-        anyhow::bail!("--write-all cannot be used with --exclude");
+
+    /// Whether a filter record may be matched by at most one data record.
+    fn unique_data(self) -> bool {
+        matches!(self, Relationship::OneToOne | Relationship::OneToMany)
     }
-    if write_all_value.is_some() && append_fields_spec.is_none() {
-        anyhow::bail!("--write-all requires --append-fields");
+
+    /// Whether a data record may match at most one filter record.
+    fn unique_filter(self) -> bool {
+        matches!(self, Relationship::OneToOne | Relationship::ManyToOne)
     }
-    if filter_file == "-" && infiles.len() == 1 && infiles[0] == "stdin" {
-        anyhow::bail!("data file is required when filter-file is '-'");
+}
+
+/// Validates a data record's match against `--relationship` and `--require-match`,
+/// then marks the entry as matched.
+fn check_match(
+    entry: Option<&mut FilterEntry>,
+    key: &[u8],
+    relationship: Option<Relationship>,
+    require_match: bool,
+    source: &str,
+    line: u64,
+) -> std::io::Result<()> {
+    let invalid =
+        |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+
+    let Some(entry) = entry else {
+        if require_match {
+            return Err(invalid(format!(
+                "--require-match: key `{}` at {} line {} has no match in the filter file",
+                String::from_utf8_lossy(key),
+                source,
+                line
+            )));
+        }
+        return Ok(());
+    };
+
+    if let Some(rel) = relationship {
+        if rel.unique_filter() && entry.values.len() > 1 {
+            return Err(invalid(format!(
+                "--relationship {} violated by the filter file: key `{}` at {} line {} matches {} filter records",
+                rel.name(),
+                String::from_utf8_lossy(key),
+                source,
+                line,
+                entry.values.len()
+            )));
+        }
+        if rel.unique_data() && entry.matched {
+            return Err(invalid(format!(
+                "--relationship {} violated by the data: key `{}` at {} line {} was already matched by an earlier data record",
+                rel.name(),
+                String::from_utf8_lossy(key),
+                source,
+                line
+            )));
+        }
     }
+    entry.matched = true;
+    Ok(())
+}
 
-    let prefix = args
-        .get_one::<String>("prefix")
-        .map(|s| s.as_str())
-        .unwrap_or("");
-    if !header_config.enabled && !prefix.is_empty() {
-        anyhow::bail!("--prefix requires --header");
+/// The filter file records sharing a join key.
+struct FilterEntry {
+    /// Values appended to matching data records, delimiter separated, one per
+    /// filter record in file order. Holds a single record unless `--all-matches`.
+    values: SmallVec<[Vec<u8>; 1]>,
+    /// Set once a data record has matched this entry.
+    matched: bool,
+}
+
+/// Extracts all non-key fields of a filter row, for `--how` without `--append-fields`.
+/// Returns the values and the number of fields.
+fn extract_non_key_values(
+    row: &TsvRow<'_, '_>,
+    delimiter: u8,
+    key_indices: Option<&[usize]>,
+) -> (Vec<u8>, usize) {
+    let Some(key_indices) = key_indices else {
+        // The whole line is the key
+        return (Vec::new(), 0);
+    };
+
+    let mut values = Vec::with_capacity(row.line.len());
+    let mut count = 0;
+    let mut start = 0;
+    for (i, &end) in row.ends.iter().enumerate() {
+        if !key_indices.contains(&(i + 1)) {
+            if count > 0 {
+                values.push(delimiter);
+            }
+            values.extend_from_slice(&row.line[start..end]);
+            count += 1;
+        }
+        start = end + 1;
     }
+    (values, count)
+}
 
-    // ============================================================
-    // Phase 1: Process Filter File
-    // ============================================================
-    let mut filter_reader = TsvReader::new(crate::libs::io::raw_reader(&filter_file)?);
+/// Builds the column names line of a `--how` join: data columns followed by
+/// appended filter columns. Names present on both sides get the respective suffix.
+fn build_joined_header(
+    data_names: &[String],
+    append_names: &[String],
+    suffixes: (&str, &str),
+    delimiter: char,
+) -> Vec<u8> {
+    let data_set: std::collections::HashSet<&str> =
+        data_names.iter().map(|s| s.as_str()).collect();
+    let append_set: std::collections::HashSet<&str> =
+        append_names.iter().map(|s| s.as_str()).collect();
 
-    // Read filter file header if enabled
-    let mut filter_header_bytes: Option<Vec<u8>> = None;
-    if header_config.enabled {
-        if let Some(header_info) = filter_reader.read_header_mode(header_config.mode)? {
-            filter_header_bytes = header_info.column_names_line.clone();
+    let mut names: Vec<String> =
+        Vec::with_capacity(data_names.len() + append_names.len());
+    for name in data_names {
+        if append_set.contains(name.as_str()) {
+            names.push(format!("{}{}", name, suffixes.0));
+        } else {
+            names.push(name.clone());
+        }
+    }
+    for name in append_names {
+        if data_set.contains(name.as_str()) {
+            names.push(format!("{}{}", name, suffixes.1));
+        } else {
+            names.push(name.clone());
         }
     }
+    names.join(&delimiter.to_string()).into_bytes()
+}
 
-    // Create FieldResolver for filter file
-    let filter_resolver =
-        FieldResolver::new(filter_header_bytes.clone(), delimiter_char);
+/// Layout of data records, used to place keys of unmatched filter records.
+struct DataLayout {
+    /// Data key fields (1-based), or `None` for whole-line keys.
+    key_indices: Option<Vec<usize>>,
+    /// Number of data fields.
+    width: usize,
+}
 
-    // Parse filter file field specifications
-    let (filter_key_whole_line, filter_key_indices) =
-        parse_join_field_spec(key_fields_spec, &filter_resolver)?;
-    let append_indices = parse_append_field_spec(append_fields_spec, &filter_resolver)?;
-    let append_count = append_indices.as_ref().map(|v| v.len()).unwrap_or(0);
+/// Writes the filter records of a key that matched no data record (right and
+/// full joins).
+///
+/// The data columns are filled with `fill`, except the key columns, which take
+/// the filter record's key values.
+fn write_unmatched_filter<W: Write>(
+    writer: &mut W,
+    key: &[u8],
+    entry: &FilterEntry,
+    layout: &DataLayout,
+    fill: &[u8],
+    append_count: usize,
+    delimiter: u8,
+) -> std::io::Result<()> {
+    let mut data_part = Vec::new();
+    match layout.key_indices.as_deref() {
+        None => data_part.extend_from_slice(key),
+        Some(indices) => {
+            let width = indices.iter().copied().max().unwrap_or(0).max(layout.width);
+            let mut key_parts = key.split(|&b| b == delimiter);
+            let mut fields: Vec<&[u8]> = vec![fill; width];
+            for &idx in indices {
+                fields[idx - 1] = key_parts.next().unwrap_or(&[]);
+            }
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    data_part.push(delimiter);
+                }
+                data_part.extend_from_slice(field);
+            }
+        }
+    }
+    for values in &entry.values {
+        writer.write_all(&data_part)?;
+        if append_count > 0 {
+            writer.write_all(&[delimiter])?;
+            writer.write_all(values)?;
+        }
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
 
-    // Build append header suffix for output
-    let append_header_suffix = build_append_header_suffix(
-        append_indices.as_ref(),
-        &filter_resolver,
-        delimiter_char,
-        prefix,
-    );
-    let write_all_fill =
-        build_write_all_fill(write_all_value, append_count, opt_delimiter);
+/// Turns filter rows into join keys and appended values.
+struct FilterLoader {
+    key_extractor: KeyExtractor,
+    key_indices: Option<Vec<usize>>,
+    append_plan: Option<SelectPlan>,
+    /// Append all non-key fields (`--how` without `--append-fields`).
+    append_all: bool,
+    ranges_buf: Vec<Range<usize>>,
+    /// Number of appended fields, unless `widest_append` is set.
+    append_count: usize,
+    /// Without a filter header, the widest filter record decides the number
+    /// of fields appended by `--how`.
+    widest_append: bool,
+    max_append_count: usize,
+    all_matches: bool,
+    allow_duplicate_keys: bool,
+    delimiter: u8,
+}
 
-    // Initialize key extractor and append plan
-    let mut filter_key_extractor =
-        KeyExtractor::new(filter_key_indices.clone(), false, true);
-    let append_plan = append_indices
-        .as_ref()
-        .map(|idxs| crate::libs::tsv::select::SelectPlan::new(idxs));
+impl FilterLoader {
+    /// Returns the number of appended fields, once filter records are loaded.
+    fn append_count(&self) -> usize {
+        if self.widest_append {
+            self.max_append_count
+        } else {
+            self.append_count
+        }
+    }
 
-    // Build filter hash map: Key -> Appended Values
-    let mut filter_map: HashMap<KeyBuffer, Vec<u8>, RandomState> =
-        HashMap::with_hasher(RandomState::new());
-    let mut ranges_buf: Vec<Range<usize>> = Vec::new();
+    fn key<'a>(&mut self, row: &TsvRow<'a, '_>) -> std::io::Result<ParsedKey<'a>> {
+        self.key_extractor
+            .extract_from_row(row, self.delimiter)
+            .map_err(|idx| key_index_error(row, idx))
+    }
 
-    filter_reader.for_each_row(opt_delimiter, |row| {
-        if row.line.is_empty() {
+    fn values(&mut self, row: &TsvRow<'_, '_>) -> std::io::Result<Vec<u8>> {
+        if let Some(ref plan) = self.append_plan {
+            self.ranges_buf.clear();
+            extract_values(row, self.delimiter, plan, &mut self.ranges_buf).map_err(
+                |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
+            )
+        } else if self.append_all {
+            let (values, count) =
+                extract_non_key_values(row, self.delimiter, self.key_indices.as_deref());
+            self.max_append_count = self.max_append_count.max(count);
+            Ok(values)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Adds the values of another filter record with an existing key.
+    fn add_duplicate(
+        &self,
+        entry: &mut FilterEntry,
+        values: Vec<u8>,
+    ) -> std::io::Result<()> {
+        if self.all_matches {
+            entry.values.push(values);
             return Ok(());
         }
+        if !self.allow_duplicate_keys && entry.values[0] != values {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "duplicate key with different append values found in filter file",
+            ));
+        }
+        if self.allow_duplicate_keys {
+            entry.values[0] = values;
+        }
+        Ok(())
+    }
+}
 
-        let key = match filter_key_extractor.extract_from_row(row, opt_delimiter) {
-            Ok(k) => k,
-            Err(idx) => {
-                let n = row.ends.len();
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!(
-                        "line has {} fields, but key index {} is out of range",
-                        n, idx
-                    ),
-                ));
+fn key_index_error(row: &TsvRow<'_, '_>, idx: usize) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "line has {} fields, but key index {} is out of range",
+            row.ends.len(),
+            idx
+        ),
+    )
+}
+
+/// Writes data records according to the join mode.
+struct JoinWriter {
+    how: Option<JoinHow>,
+    exclude: bool,
+    /// Appended to unmatched data records with `--write-all`.
+    write_all_fill: Option<Vec<u8>>,
+    /// Appended to unmatched data records with `--how left|full`.
+    how_fill: Vec<u8>,
+    append_count: usize,
+    line_buffered: bool,
+    delimiter: u8,
+}
+
+impl JoinWriter {
+    fn new(opts: &JoinOptions, append_count: usize) -> Self {
+        Self {
+            how: opts.how,
+            exclude: opts.exclude,
+            // A fuzzy join appends the score as well
+            write_all_fill: build_write_all_fill(
+                opts.write_all,
+                append_count + usize::from(opts.fuzzy.is_some()),
+                opts.delimiter,
+            ),
+            how_fill: build_write_all_fill(
+                Some(opts.fill),
+                append_count,
+                opts.delimiter,
+            )
+            .unwrap_or_default(),
+            append_count,
+            line_buffered: opts.line_buffered,
+            delimiter: opts.delimiter,
+        }
+    }
+
+    /// Writes a data record given its matching filter records, if any.
+    fn write_data<W: Write>(
+        &self,
+        writer: &mut W,
+        line: &[u8],
+        matched: Option<&mut FilterEntry>,
+    ) -> std::io::Result<()> {
+        if let Some(how) = self.how {
+            match matched {
+                Some(entry) => {
+                    entry.matched = true;
+                    for values in &entry.values {
+                        writer.write_all(line)?;
+                        if self.append_count > 0 {
+                            writer.write_all(&[self.delimiter])?;
+                            writer.write_all(values)?;
+                        }
+                        writer.write_all(b"\n")?;
+                    }
+                }
+                None if how.keeps_unmatched_data() => {
+                    writer.write_all(line)?;
+                    writer.write_all(&self.how_fill)?;
+                    writer.write_all(b"\n")?;
+                }
+                None => return Ok(()),
+            }
+        } else if self.exclude {
+            if matched.is_some() {
+                return Ok(());
+            }
+            writer.write_all(line)?;
+            writer.write_all(b"\n")?;
+        } else if let Some(entry) = matched {
+            for values in &entry.values {
+                writer.write_all(line)?;
+                if !values.is_empty() {
+                    writer.write_all(&[self.delimiter])?;
+                    writer.write_all(values)?;
+                }
+                writer.write_all(b"\n")?;
+            }
+        } else if let Some(ref fill) = self.write_all_fill {
+            writer.write_all(line)?;
+            writer.write_all(fill)?;
+            writer.write_all(b"\n")?;
+        } else {
+            return Ok(());
+        }
+
+        if self.line_buffered {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl JoinWriter {
+    /// Writes a data record of a fuzzy join, followed by the similarity score.
+    fn write_scored<W: Write>(
+        &self,
+        writer: &mut W,
+        line: &[u8],
+        matched: Option<(&FilterEntry, f64)>,
+    ) -> std::io::Result<()> {
+        match matched {
+            Some(_) if self.exclude => return Ok(()),
+            Some((entry, score)) => {
+                writer.write_all(line)?;
+                if !entry.values[0].is_empty() {
+                    writer.write_all(&[self.delimiter])?;
+                    writer.write_all(&entry.values[0])?;
+                }
+                writer.write_all(&[self.delimiter])?;
+                writer.write_all(format_float(score, Some(4)).as_bytes())?;
             }
+            None if self.exclude => writer.write_all(line)?,
+            None => match self.write_all_fill {
+                Some(ref fill) => {
+                    writer.write_all(line)?;
+                    writer.write_all(fill)?;
+                }
+                None => return Ok(()),
+            },
+        }
+        writer.write_all(b"\n")?;
+
+        if self.line_buffered {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Consecutive filter records with the same key, in a sorted join.
+struct FilterGroup {
+    sort_key: SortKey,
+    key: KeyBuffer,
+    entry: FilterEntry,
+}
+
+/// Streams a sorted filter file as groups of records with equal keys.
+struct SortedFilter<R> {
+    reader: SortedReader<R>,
+    spec: SortSpec,
+    pending: Option<(SortKey, TsvRecord)>,
+    delimiter: u8,
+}
+
+impl<R: Read> SortedFilter<R> {
+    fn next_record(&mut self) -> std::io::Result<Option<(SortKey, TsvRecord)>> {
+        if let Some(pending) = self.pending.take() {
+            return Ok(Some(pending));
+        }
+        // Empty lines are skipped, as in the hash join
+        while let Some((sort_key, record)) =
+            self.reader.next_record(&self.spec, self.delimiter)?
+        {
+            if !record.as_line().is_empty() {
+                return Ok(Some((sort_key, record)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the first record without consuming it.
+    fn peek(&mut self) -> std::io::Result<Option<&TsvRecord>> {
+        if self.pending.is_none() {
+            self.pending = self.next_record()?;
+        }
+        Ok(self.pending.as_ref().map(|(_, record)| record))
+    }
+
+    fn next_group(
+        &mut self,
+        loader: &mut FilterLoader,
+    ) -> std::io::Result<Option<FilterGroup>> {
+        let Some((sort_key, record)) = self.next_record()? else {
+            return Ok(None);
+        };
+        let row = record.as_row();
+        let key = loader.key(&row)?.into_owned();
+        let values = loader.values(&row)?;
+        let mut group = FilterGroup {
+            sort_key,
+            key,
+            entry: FilterEntry {
+                values: smallvec![values],
+                matched: false,
+            },
         };
 
-        let values = if let Some(ref plan) = append_plan {
-            ranges_buf.clear();
-            match extract_values(row, opt_delimiter, plan, &mut ranges_buf) {
-                Ok(v) => v,
-                Err(e) => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        e.to_string(),
-                    ));
+        while let Some((sort_key, record)) = self.next_record()? {
+            if sort_key != group.sort_key {
+                self.pending = Some((sort_key, record));
+                break;
+            }
+            let values = loader.values(&record.as_row())?;
+            loader.add_duplicate(&mut group.entry, values)?;
+        }
+        Ok(Some(group))
+    }
+}
+
+/// Returns the field with the given 1-based index, borrowing from the row's line.
+fn row_field<'a>(row: &TsvRow<'a, '_>, idx: usize) -> std::io::Result<&'a [u8]> {
+    let i = idx - 1;
+    match row.ends.get(i) {
+        Some(&end) => {
+            let start = if i == 0 { 0 } else { row.ends[i - 1] + 1 };
+            Ok(&row.line[start..end])
+        }
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "line has {} fields, but index {} is out of range",
+                row.ends.len(),
+                idx
+            ),
+        )),
+    }
+}
+
+/// Chromosome, start and end fields (1-based) of interval records.
+#[derive(Clone, Copy, Debug)]
+struct IntervalFields {
+    chrom: usize,
+    start: usize,
+    end: usize,
+}
+
+impl IntervalFields {
+    fn parse(spec: &str, resolver: &FieldResolver) -> anyhow::Result<Self> {
+        let indices = resolver
+            .resolve(spec.trim())
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        match indices[..] {
+            [chrom, start, end] => Ok(Self { chrom, start, end }),
+            _ => anyhow::bail!(
+                "interval fields must be three fields (chrom,start,end), got `{}`",
+                spec
+            ),
+        }
+    }
+
+    /// Returns the chromosome and the half-open coordinates of a row.
+    fn extract<'a>(
+        &self,
+        row: &TsvRow<'a, '_>,
+        half_open: bool,
+        source: &str,
+        line: u64,
+    ) -> std::io::Result<(&'a [u8], i64, i64)> {
+        let invalid =
+            |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+        let coord = |idx: usize, what: &str| -> std::io::Result<i64> {
+            let bytes = row_field(row, idx)?;
+            std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.trim().parse::<i64>().ok())
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "invalid interval {} `{}` at {} line {}",
+                        what,
+                        String::from_utf8_lossy(bytes),
+                        source,
+                        line
+                    ))
+                })
+        };
+
+        let chrom = row_field(row, self.chrom)?;
+        let start = coord(self.start, "start")?;
+        let mut end = coord(self.end, "end")?;
+        if !half_open {
+            // Closed intervals include their end
+            end += 1;
+        }
+        if end < start || (!half_open && end == start) {
+            return Err(invalid(format!(
+                "interval end is before its start at {} line {}",
+                source, line
+            )));
+        }
+        if end == start {
+            // A zero-length interval, such as a BED insertion, is the point
+            // at its start
+            end += 1;
+        }
+        Ok((chrom, start, end))
+    }
+}
+
+/// Parses the values of `--asof` fields: numbers, or ISO 8601 dates and
+/// timestamps, which are converted to seconds since the epoch.
+#[derive(Default)]
+struct AsofParser {
+    /// Whether the values are timestamps, decided by the first value.
+    timestamps: Option<bool>,
+    /// `--tolerance` with a time unit, only valid for timestamps.
+    unit_tolerance: Option<String>,
+}
+
+impl AsofParser {
+    fn parse(&mut self, bytes: &[u8], source: &str, line: u64) -> std::io::Result<f64> {
+        let invalid =
+            |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+        let text = std::str::from_utf8(bytes).unwrap_or_default().trim();
+
+        let (timestamp, value) = match text.parse::<f64>() {
+            Ok(v) if v.is_finite() => (false, v),
+            _ => match parse_timestamp(text) {
+                Some(v) => (true, v),
+                None => {
+                    return Err(invalid(format!(
+                        "invalid as-of value `{}` at {} line {}",
+                        String::from_utf8_lossy(bytes),
+                        source,
+                        line
+                    )))
                 }
+            },
+        };
+
+        if *self.timestamps.get_or_insert(timestamp) != timestamp {
+            return Err(invalid(format!(
+                "as-of values mix numbers and timestamps at {} line {}",
+                source, line
+            )));
+        }
+        if let (false, Some(tolerance)) = (timestamp, &self.unit_tolerance) {
+            return Err(invalid(format!(
+                "--tolerance `{}` has a time unit, but the as-of values are numbers \
+                 at {} line {}",
+                tolerance, source, line
+            )));
+        }
+        Ok(value)
+    }
+}
+
+/// Parses `--tolerance`: a non-negative number, optionally followed by a
+/// time unit (`s`, `m`, `h` or `d`), returned in seconds. Also returns whether
+/// a unit was given, which only timestamps accept.
+fn parse_tolerance(s: &str) -> anyhow::Result<(f64, bool)> {
+    let s = s.trim();
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], Some(1.0)),
+        Some((i, 'm')) => (&s[..i], Some(60.0)),
+        Some((i, 'h')) => (&s[..i], Some(3600.0)),
+        Some((i, 'd')) => (&s[..i], Some(86400.0)),
+        _ => (s, None),
+    };
+    match number.parse::<f64>() {
+        Ok(v) if v.is_finite() && v >= 0.0 => {
+            Ok((v * scale.unwrap_or(1.0), scale.is_some()))
+        }
+        _ => anyhow::bail!("invalid --tolerance `{}`", s),
+    }
+}
+
+/// Filter records of an as-of join, sorted by as-of value within each
+/// partition key.
+struct AsofIndex {
+    partitions: std::collections::HashMap<KeyBuffer, Vec<(f64, usize)>, RandomState>,
+    entries: Vec<FilterEntry>,
+}
+
+impl AsofIndex {
+    fn new() -> Self {
+        Self {
+            partitions: std::collections::HashMap::with_hasher(RandomState::new()),
+            entries: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, key: KeyBuffer, value: f64, values: Vec<u8>) {
+        self.partitions
+            .entry(key)
+            .or_default()
+            .push((value, self.entries.len()));
+        self.entries.push(FilterEntry {
+            values: smallvec![values],
+            matched: false,
+        });
+    }
+
+    /// Sorts each partition. The sort is stable, so among equal values the
+    /// last filter record is found.
+    fn finish(&mut self) {
+        for list in self.partitions.values_mut() {
+            list.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+    }
+
+    /// Finds the filter record with the greatest value not exceeding `value`,
+    /// at most `tolerance` below it.
+    fn lookup(
+        &mut self,
+        key: &[u8],
+        value: f64,
+        tolerance: Option<f64>,
+    ) -> Option<&mut FilterEntry> {
+        let list = self.partitions.get(key)?;
+        let pos = list.partition_point(|&(v, _)| v <= value);
+        let (found, idx) = *list.get(pos.checked_sub(1)?)?;
+        if tolerance.is_some_and(|t| value - found > t) {
+            return None;
+        }
+        Some(&mut self.entries[idx])
+    }
+}
+
+/// Builds a sort specification comparing the given key fields lexically.
+fn join_sort_spec(indices: Option<&[usize]>) -> SortSpec {
+    SortSpec {
+        keys: indices
+            .unwrap_or_default()
+            .iter()
+            .map(|&index| KeyField {
+                index,
+                options: KeyOptions::default(),
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Command line options of a join, validated.
+struct JoinOptions<'a> {
+    infiles: Vec<String>,
+    filter_file: &'a str,
+    key_fields: Option<&'a str>,
+    data_fields: Option<&'a str>,
+    append_fields: Option<&'a str>,
+    write_all: Option<&'a str>,
+    header_config: HeaderConfig,
+    prefix: &'a str,
+    delimiter: u8,
+    allow_duplicate_keys: bool,
+    relationship: Option<Relationship>,
+    require_match: bool,
+    all_matches: bool,
+    line_buffered: bool,
+    exclude: bool,
+    sorted: bool,
+    interval: Option<&'a str>,
+    data_interval: Option<&'a str>,
+    half_open: bool,
+    fuzzy: Option<Similarity>,
+    threshold: f64,
+    asof: Option<&'a str>,
+    data_asof: Option<&'a str>,
+    /// `--tolerance` as given, in seconds, and whether it has a time unit.
+    tolerance: Option<(&'a str, f64, bool)>,
+    how: Option<JoinHow>,
+    fill: &'a str,
+    suffixes: (String, String),
+}
+
+impl<'a> JoinOptions<'a> {
+    fn from_args(args: &'a ArgMatches) -> anyhow::Result<Self> {
+        let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+            Some(values) => values.cloned().collect(),
+            None => vec!["stdin".to_string()],
+        };
+
+        let filter_file = args.get_one::<String>("filter-file").unwrap().as_str();
+        let key_fields = args.get_one::<String>("key-fields").map(|s| s.as_str());
+        let data_fields = args.get_one::<String>("data-fields").map(|s| s.as_str());
+        let append_fields = args.get_one::<String>("append-fields").map(|s| s.as_str());
+        let write_all = args.get_one::<String>("write-all").map(|s| s.as_str());
+
+        // Build HeaderConfig from arguments
+        let header_config =
+            build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+
+        let relationship = args
+            .get_one::<String>("relationship")
+            .map(|s| Relationship::from_arg(s));
+        let threshold = *args.get_one::<f64>("threshold").unwrap();
+        if !(0.0..=1.0).contains(&threshold) {
+            anyhow::bail!("--threshold must be between 0 and 1");
+        }
+        let asof = args.get_one::<String>("asof").map(|s| s.as_str());
+        let tolerance = match args.get_one::<String>("tolerance") {
+            Some(arg) => {
+                let (seconds, has_unit) = parse_tolerance(arg)?;
+                Some((arg.as_str(), seconds, has_unit))
+            }
+            None => None,
+        };
+        // As-of joins are partitioned by the key fields, if any are given
+        if asof.is_some() && data_fields.is_some() && key_fields.is_none() {
+            anyhow::bail!("--data-fields with --asof requires --key-fields");
+        }
+        let suffixes = {
+            let value = args.get_one::<String>("suffixes").unwrap();
+            match value.split_once(',') {
+                Some((x, y)) => (x.to_string(), y.to_string()),
+                None => anyhow::bail!("--suffixes must be two comma-separated values"),
+            }
+        };
+
+        let delimiter = get_delimiter(args, "delimiter")?;
+
+        let opts = Self {
+            infiles,
+            filter_file,
+            key_fields,
+            data_fields,
+            append_fields,
+            write_all,
+            header_config,
+            prefix: args
+                .get_one::<String>("prefix")
+                .map(|s| s.as_str())
+                .unwrap_or(""),
+            delimiter,
+            allow_duplicate_keys: args.get_flag("allow-duplicate-keys"),
+            relationship,
+            require_match: args.get_flag("require-match"),
+            // Relationships are checked against all matches, so keep every filter record
+            all_matches: args.get_flag("all-matches") || relationship.is_some(),
+            line_buffered: args.get_flag("line-buffered"),
+            exclude: args.get_flag("exclude"),
+            sorted: args.get_flag("sorted"),
+            interval: args.get_one::<String>("interval").map(|s| s.as_str()),
+            data_interval: args.get_one::<String>("data-interval").map(|s| s.as_str()),
+            half_open: args.get_flag("half-open"),
+            fuzzy: args
+                .get_one::<String>("fuzzy")
+                .map(|s| Similarity::from_arg(s)),
+            threshold,
+            asof,
+            data_asof: args.get_one::<String>("data-asof").map(|s| s.as_str()),
+            tolerance,
+            how: args.get_one::<String>("how").map(|s| JoinHow::from_arg(s)),
+            fill: args.get_one::<String>("fill").unwrap().as_str(),
+            suffixes,
+        };
+
+        // Validate argument combinations
+        if opts.exclude && opts.append_fields.is_some() {
+            anyhow::bail!("--exclude cannot be used with --append-fields");
+        }
+        if opts.exclude && opts.write_all.is_some() {
+            anyhow::bail!("--write-all cannot be used with --exclude");
+        }
+        // A fuzzy join always appends the score
+        if opts.write_all.is_some()
+            && opts.append_fields.is_none()
+            && opts.fuzzy.is_none()
+        {
+            anyhow::bail!("--write-all requires --append-fields");
+        }
+        if opts.filter_file == "-"
+            && opts.infiles.len() == 1
+            && opts.infiles[0] == "stdin"
+        {
+            anyhow::bail!("data file is required when filter-file is '-'");
+        }
+        if opts.sorted && opts.infiles.len() > 1 {
+            anyhow::bail!("--sorted takes a single data input");
+        }
+        if !opts.header_config.enabled && !opts.prefix.is_empty() {
+            anyhow::bail!("--prefix requires --header");
+        }
+        Ok(opts)
+    }
+
+    fn keeps_unmatched_filter(&self) -> bool {
+        self.how.is_some_and(|h| h.keeps_unmatched_filter())
+    }
+}
+
+/// The filter file after its header, with the key and appended fields
+/// resolved against it.
+struct FilterFile {
+    reader: TsvReader<Box<dyn Read>>,
+    header_lines: u64,
+    resolver: FieldResolver,
+    key_whole_line: bool,
+    key_indices: Option<Vec<usize>>,
+    loader: FilterLoader,
+    /// With `--how`, names of the appended columns.
+    append_names: Vec<String>,
+    /// Without `--how`, appended to the data header.
+    append_header_suffix: Option<String>,
+}
+
+impl FilterFile {
+    fn open(opts: &JoinOptions) -> anyhow::Result<Self> {
+        let delimiter_char = opts.delimiter as char;
+        let mut reader = TsvReader::new(crate::libs::io::raw_reader(opts.filter_file)?);
+
+        // Read filter file header if enabled
+        let mut header_bytes: Option<Vec<u8>> = None;
+        let mut header_lines = 0;
+        if opts.header_config.enabled {
+            if let Some(header_info) =
+                reader.read_header_mode(opts.header_config.mode)?
+            {
+                header_lines = header_info.line_count() as u64;
+                header_bytes = header_info.column_names_line.clone();
             }
+        }
+        let resolver = FieldResolver::new(header_bytes, delimiter_char);
+
+        // Interval joins have no key fields; all filter fields can be appended.
+        let (key_whole_line, key_indices) = if opts.interval.is_some() {
+            (false, Some(Vec::new()))
         } else {
-            Vec::new()
+            parse_join_field_spec(opts.key_fields, &resolver, opts.sorted)?
         };
+        let append_indices = parse_append_field_spec(opts.append_fields, &resolver)?;
+        let mut append_count = append_indices.as_ref().map(|v| v.len()).unwrap_or(0);
 
-        if let Some(existing) = filter_map.get_mut(key.as_ref()) {
-            if !allow_duplicate_keys && *existing != values {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "duplicate key with different append values found in filter file",
-                ));
+        // With --how, all non-key filter fields are appended unless -a is given
+        let append_all = opts.how.is_some() && append_indices.is_none();
+        let mut append_names: Vec<String> = Vec::new();
+        if opts.how.is_some() {
+            let column_names = resolver.column_names().unwrap_or_default();
+            let names: Vec<String> = match append_indices.as_ref() {
+                Some(idxs) => idxs
+                    .iter()
+                    .map(|idx| column_names.get(idx - 1).cloned().unwrap_or_default())
+                    .collect(),
+                None if key_whole_line => Vec::new(),
+                None => column_names
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| {
+                        !key_indices
+                            .as_ref()
+                            .is_some_and(|keys| keys.contains(&(i + 1)))
+                    })
+                    .map(|(_, name)| name.clone())
+                    .collect(),
+            };
+            if append_all {
+                append_count = names.len();
             }
-            if allow_duplicate_keys {
-                *existing = values;
+            append_names = names
+                .into_iter()
+                .map(|name| format!("{}{}", opts.prefix, name))
+                .collect();
+        }
+
+        // Build append header suffix for output
+        let mut append_header_suffix = build_append_header_suffix(
+            append_indices.as_ref(),
+            &resolver,
+            delimiter_char,
+            opts.prefix,
+        );
+        if opts.fuzzy.is_some() && !opts.exclude && resolver.has_header() {
+            let suffix = append_header_suffix.get_or_insert_with(String::new);
+            suffix.push(delimiter_char);
+            suffix.push_str("score");
+        }
+
+        let loader = FilterLoader {
+            key_extractor: KeyExtractor::new(key_indices.clone(), false, true),
+            key_indices: key_indices.clone(),
+            append_plan: append_indices.as_ref().map(|idxs| SelectPlan::new(idxs)),
+            append_all,
+            ranges_buf: Vec::new(),
+            append_count,
+            widest_append: append_all && !resolver.has_header(),
+            max_append_count: 0,
+            all_matches: opts.all_matches,
+            allow_duplicate_keys: opts.allow_duplicate_keys,
+            delimiter: opts.delimiter,
+        };
+
+        Ok(Self {
+            reader,
+            header_lines,
+            resolver,
+            key_whole_line,
+            key_indices,
+            loader,
+            append_names,
+            append_header_suffix,
+        })
+    }
+}
+
+/// Key fields of the data inputs, resolved against each data header.
+struct DataKeys<'a> {
+    opts: &'a JoinOptions<'a>,
+    /// Number of filter key fields, or `None` for whole-line keys.
+    filter_key_count: Option<usize>,
+    extractor: Option<KeyExtractor>,
+    /// Key fields of the first data input, for unmatched filter records.
+    layout: Option<Option<Vec<usize>>>,
+}
+
+impl<'a> DataKeys<'a> {
+    fn new(opts: &'a JoinOptions<'a>, filter: &FilterFile) -> Self {
+        Self {
+            opts,
+            filter_key_count: (!filter.key_whole_line)
+                .then(|| filter.key_indices.as_ref().map(|v| v.len()).unwrap_or(0)),
+            extractor: None,
+            layout: None,
+        }
+    }
+
+    /// Resolves the key fields of a data input. `input` names the file in
+    /// errors when the fields are resolved against its header.
+    fn resolve(
+        &mut self,
+        resolver: &FieldResolver,
+        input: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let spec = self.opts.data_fields.or(self.opts.key_fields);
+        let (whole_line, indices) =
+            parse_join_field_spec(spec, resolver, self.opts.sorted)?;
+
+        // Validate key lengths match
+        if let (Some(filter_count), false) = (self.filter_key_count, whole_line) {
+            if filter_count != indices.as_ref().map(|v| v.len()).unwrap_or(0) {
+                match input {
+                    Some(name) => anyhow::bail!(
+                        "different number of key-fields and data-fields in file {}",
+                        name
+                    ),
+                    None => {
+                        anyhow::bail!("different number of key-fields and data-fields")
+                    }
+                }
             }
+        }
+
+        if self.layout.is_none() {
+            self.layout = Some(indices.clone());
+        }
+        self.extractor = Some(KeyExtractor::new(indices, false, true));
+        Ok(())
+    }
+
+    /// Returns the key extractor of the current data input. Without data
+    /// column names, the key fields are resolved once, for all inputs.
+    fn extractor(&mut self) -> anyhow::Result<&mut KeyExtractor> {
+        if self.extractor.is_none() {
+            let resolver = FieldResolver::new(None, self.opts.delimiter as char);
+            self.resolve(&resolver, None)?;
+        }
+        Ok(self
+            .extractor
+            .as_mut()
+            .expect("data key extractor should be initialized"))
+    }
+}
+
+/// The output of a join: the header of the first data input, extended with
+/// the filter columns, and the records.
+struct JoinOutput<'a> {
+    writer: Box<dyn Write>,
+    opts: &'a JoinOptions<'a>,
+    append_names: Vec<String>,
+    append_header_suffix: Option<String>,
+    header_written: bool,
+    /// Number of fields of the widest data record, for unmatched filter records.
+    data_width: usize,
+}
+
+impl<'a> JoinOutput<'a> {
+    fn new(
+        writer: Box<dyn Write>,
+        opts: &'a JoinOptions<'a>,
+        filter: &FilterFile,
+    ) -> Self {
+        Self {
+            writer,
+            opts,
+            append_names: filter.append_names.clone(),
+            append_header_suffix: filter.append_header_suffix.clone(),
+            header_written: false,
+            data_width: 0,
+        }
+    }
+
+    /// Reads the header of a data input, if enabled, and resolves the data
+    /// fields against its column names with `resolve`. The output header is
+    /// written for the first input.
+    ///
+    /// Returns the number of header lines, or `None` for an empty input.
+    fn read_header<R: Read>(
+        &mut self,
+        reader: &mut TsvReader<R>,
+        mut resolve: impl FnMut(&FieldResolver) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Option<u64>> {
+        if !self.opts.header_config.enabled {
+            return Ok(Some(0));
+        }
+        let Some(header_info) = reader.read_header_mode(self.opts.header_config.mode)?
+        else {
+            return Ok(None); // Empty file
+        };
+        let line_count = header_info.line_count() as u64;
+        let delimiter_char = self.opts.delimiter as char;
+
+        // Parse data file header for field resolution (for each file, to handle different column orders)
+        if let Some(ref column_names) = header_info.column_names_line {
+            resolve(&FieldResolver::new(
+                Some(column_names.clone()),
+                delimiter_char,
+            ))?;
+        }
+
+        // Write header only for the first file (only if there are column names)
+        if self.header_written {
+            return Ok(Some(line_count));
+        }
+        self.header_written = true;
+        if header_info
+            .column_names_line
+            .as_ref()
+            .is_none_or(|names| names.is_empty())
+        {
+            return Ok(Some(line_count));
+        }
+
+        // Convert to Header and write with optional suffix
+        let mut header = Header::from_info(header_info, delimiter_char);
+        if self.opts.how.is_some() {
+            let data_names = header.column_names_list().unwrap_or_default();
+            self.data_width = data_names.len();
+            let joined = build_joined_header(
+                &data_names,
+                &self.append_names,
+                (&self.opts.suffixes.0, &self.opts.suffixes.1),
+                delimiter_char,
+            );
+            let lines = std::mem::take(&mut header.lines);
+            header = Header::from_column_names(joined, delimiter_char);
+            header.lines = lines;
+            write_header(&mut self.writer, &header, None)?;
         } else {
-            filter_map.insert(key.into_owned(), values);
+            let suffix = self.append_header_suffix.as_deref().map(|s| s.as_bytes());
+            write_header(&mut self.writer, &header, suffix)?;
+        }
+        if self.opts.line_buffered {
+            self.writer.flush()?;
+        }
+        Ok(Some(line_count))
+    }
+
+    /// Writes the filter records of a key, unless a data record matched them
+    /// (right and full joins).
+    fn write_unmatched(
+        &mut self,
+        key: &[u8],
+        entry: &FilterEntry,
+        layout: &DataLayout,
+        append_count: usize,
+    ) -> std::io::Result<()> {
+        if entry.matched {
+            return Ok(());
+        }
+        write_unmatched_filter(
+            &mut self.writer,
+            key,
+            entry,
+            layout,
+            self.opts.fill.as_bytes(),
+            append_count,
+            self.opts.delimiter,
+        )?;
+        if self.opts.line_buffered {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let writer = crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+    let opts = JoinOptions::from_args(args)?;
+    let filter = FilterFile::open(&opts)?;
+
+    if let Some(spec) = opts.asof {
+        join_asof(&opts, filter, spec, writer)
+    } else if let Some(spec) = opts.interval {
+        join_interval(&opts, filter, spec, writer)
+    } else if opts.sorted {
+        join_sorted(&opts, filter, writer)
+    } else if let Some(similarity) = opts.fuzzy {
+        join_fuzzy(&opts, filter, similarity, writer)
+    } else {
+        join_hash(&opts, filter, writer)
+    }
+}
+
+/// Joins data records with the filter records of the same key, held in a
+/// hash map.
+fn join_hash(
+    opts: &JoinOptions,
+    mut filter: FilterFile,
+    writer: Box<dyn Write>,
+) -> anyhow::Result<()> {
+    // Build filter hash map: Key -> Appended Values, in filter file order
+    let mut filter_map: IndexMap<KeyBuffer, FilterEntry, RandomState> =
+        IndexMap::with_hasher(RandomState::new());
+    filter.reader.for_each_row(opts.delimiter, |row| {
+        if row.line.is_empty() {
+            return Ok(());
+        }
+
+        let key = filter.loader.key(row)?;
+        let values = filter.loader.values(row)?;
+        if let Some(existing) = filter_map.get_mut(key.as_ref()) {
+            filter.loader.add_duplicate(existing, values)?;
+        } else {
+            filter_map.insert(
+                key.into_owned(),
+                FilterEntry {
+                    values: smallvec![values],
+                    matched: false,
+                },
+            );
         }
         Ok(())
     })?;
 
-    // ============================================================
-    // Phase 2: Process Data Files
-    // ============================================================
-    let mut header_written = false;
-    let mut data_key_extractor: Option<KeyExtractor> = None;
+    let append_count = filter.loader.append_count();
+    let join_writer = JoinWriter::new(opts, append_count);
+    let mut output = JoinOutput::new(writer, opts, &filter);
+    let mut keys = DataKeys::new(opts, &filter);
 
-    for input in crate::libs::io::raw_input_sources(&infiles)? {
+    for input in crate::libs::io::raw_input_sources(&opts.infiles)? {
         let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let Some(mut line_no) = output.read_header(&mut reader, |resolver| {
+            keys.resolve(resolver, Some(&input.name))
+        })?
+        else {
+            continue;
+        };
+        let extractor = keys.extractor()?;
 
-        // Process header if enabled
-        if header_config.enabled {
-            let header_result = reader.read_header_mode(header_config.mode)?;
-
-            if let Some(header_info) = header_result {
-                // Parse data file header for field resolution (for each file, to handle different column orders)
-                if let Some(ref column_names) = header_info.column_names_line {
-                    let data_resolver =
-                        FieldResolver::new(Some(column_names.clone()), delimiter_char);
-                    let effective_data_spec = data_fields_spec.or(key_fields_spec);
-
-                    let (data_key_whole_line, indices) =
-                        parse_join_field_spec(effective_data_spec, &data_resolver)?;
-
-                    // Validate key lengths match
-                    if !filter_key_whole_line && !data_key_whole_line {
-                        let fk_len =
-                            filter_key_indices.as_ref().map(|v| v.len()).unwrap_or(0);
-                        let dk_len = indices.as_ref().map(|v| v.len()).unwrap_or(0);
-                        if fk_len != dk_len {
-                            anyhow::bail!(
-                                "different number of key-fields and data-fields in file {}",
-                                input.name
-                            );
-                        }
-                    }
+        reader.for_each_row(opts.delimiter, |row: &TsvRow| {
+            line_no += 1;
+            if row.line.is_empty() {
+                return Ok(());
+            }
 
-                    data_key_extractor = Some(KeyExtractor::new(indices, false, true));
-                }
+            let key = extractor
+                .extract_from_row(row, opts.delimiter)
+                .map_err(|idx| key_index_error(row, idx))?;
+            output.data_width = output.data_width.max(row.ends.len());
 
-                // Write header only for the first file (only if there are column names)
-                if !header_written {
-                    if let Some(ref column_names) = header_info.column_names_line {
-                        if !column_names.is_empty() {
-                            // Convert to Header and write with optional suffix
-                            let header = Header::from_info(header_info, delimiter_char);
-                            let suffix =
-                                append_header_suffix.as_deref().map(|s| s.as_bytes());
-                            write_header(&mut writer, &header, suffix)?;
-                            if line_buffered {
-                                writer.flush()?;
-                            }
-                        }
-                    }
-                    header_written = true;
-                }
-            } else {
-                continue; // Empty file
+            let mut matched = filter_map.get_mut(key.as_ref());
+            if opts.relationship.is_some() || opts.require_match {
+                check_match(
+                    matched.as_deref_mut(),
+                    key.as_ref(),
+                    opts.relationship,
+                    opts.require_match,
+                    &input.name,
+                    line_no,
+                )?;
             }
+            join_writer.write_data(&mut output.writer, row.line, matched)
+        })?;
+    }
+
+    if opts.keeps_unmatched_filter() {
+        // Without any data record, key fields are laid out as in the filter file
+        let layout = DataLayout {
+            key_indices: keys.layout.unwrap_or(filter.key_indices),
+            width: output.data_width,
+        };
+        for (key, entry) in filter_map.iter() {
+            output.write_unmatched(key, entry, &layout, append_count)?;
         }
+    }
+    Ok(())
+}
 
-        // Initialize extractor for headerless files if not set
-        if data_key_extractor.is_none() {
-            let effective_data_spec = data_fields_spec.or(key_fields_spec);
-            let data_resolver = FieldResolver::new(None, delimiter_char);
-            let (data_key_whole_line, indices) =
-                parse_join_field_spec(effective_data_spec, &data_resolver)?;
+/// Joins data records with filter records by normalized keys and, unless
+/// `--fuzzy normalized`, by their similarity.
+fn join_fuzzy(
+    opts: &JoinOptions,
+    mut filter: FilterFile,
+    similarity: Similarity,
+    writer: Box<dyn Write>,
+) -> anyhow::Result<()> {
+    // Filter records by normalized key, with the original key of each, for errors
+    let mut filter_map: IndexMap<KeyBuffer, FilterEntry, RandomState> =
+        IndexMap::with_hasher(RandomState::new());
+    let mut originals: Vec<Vec<u8>> = Vec::new();
+    filter.reader.for_each_row(opts.delimiter, |row| {
+        if row.line.is_empty() {
+            return Ok(());
+        }
 
-            // Validate key lengths match
-            if !filter_key_whole_line && !data_key_whole_line {
-                let fk_len = filter_key_indices.as_ref().map(|v| v.len()).unwrap_or(0);
-                let dk_len = indices.as_ref().map(|v| v.len()).unwrap_or(0);
-                if fk_len != dk_len {
-                    anyhow::bail!("different number of key-fields and data-fields");
-                }
-            }
+        let original = filter.loader.key(row)?;
+        let values = filter.loader.values(row)?;
+        let key = KeyBuffer::from_slice(
+            fuzzy::normalize(&String::from_utf8_lossy(original.as_ref())).as_bytes(),
+        );
 
-            data_key_extractor = Some(KeyExtractor::new(indices, false, true));
+        if let Some((i, _, existing)) = filter_map.get_full_mut(key.as_slice()) {
+            filter.loader.add_duplicate(existing, values).map_err(|e| {
+                // Name both keys when only normalization made them equal
+                if originals[i] == original.as_ref() {
+                    return e;
+                }
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "filter keys `{}` and `{}` both normalize to `{}` \
+                         with --fuzzy, but have different append values",
+                        String::from_utf8_lossy(&originals[i]),
+                        String::from_utf8_lossy(original.as_ref()),
+                        String::from_utf8_lossy(&key)
+                    ),
+                )
+            })?;
+        } else {
+            originals.push(original.as_ref().to_vec());
+            filter_map.insert(
+                key,
+                FilterEntry {
+                    values: smallvec![values],
+                    matched: false,
+                },
+            );
         }
+        Ok(())
+    })?;
 
-        // Get a reference to the extractor (unwrap is safe here)
-        let extractor = data_key_extractor
-            .as_mut()
-            .expect("data_key_extractor should be initialized");
+    // Normalized filter keys, compared by similarity when not found as such
+    let fuzzy_keys: Vec<Vec<char>> = match similarity {
+        Similarity::Normalized => Vec::new(),
+        _ => filter_map
+            .keys()
+            .map(|key| String::from_utf8_lossy(key).chars().collect())
+            .collect(),
+    };
+    let join_writer = JoinWriter::new(opts, filter.loader.append_count());
+    let mut output = JoinOutput::new(writer, opts, &filter);
+    let mut keys = DataKeys::new(opts, &filter);
+
+    for input in crate::libs::io::raw_input_sources(&opts.infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let Some(mut line_no) = output.read_header(&mut reader, |resolver| {
+            keys.resolve(resolver, Some(&input.name))
+        })?
+        else {
+            continue;
+        };
+        let extractor = keys.extractor()?;
 
-        // Process data records
-        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+        reader.for_each_row(opts.delimiter, |row: &TsvRow| {
+            line_no += 1;
             if row.line.is_empty() {
                 return Ok(());
             }
 
-            let key = match extractor.extract_from_row(row, opt_delimiter) {
-                Ok(k) => k,
-                Err(idx) => {
-                    let n = row.ends.len();
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        format!(
-                            "line has {} fields, but key index {} is out of range",
-                            n, idx
-                        ),
-                    ));
+            let key = extractor
+                .extract_from_row(row, opts.delimiter)
+                .map_err(|idx| key_index_error(row, idx))?;
+            let normalized = fuzzy::normalize(&String::from_utf8_lossy(key.as_ref()));
+            let matched = match filter_map.get_index_of(normalized.as_bytes()) {
+                Some(i) => Some((i, 1.0)),
+                None if fuzzy_keys.is_empty() => None,
+                None => {
+                    let query: Vec<char> = normalized.chars().collect();
+                    similarity.best_match(&query, &fuzzy_keys, opts.threshold)
                 }
             };
+            if matched.is_none() && opts.require_match {
+                check_match(None, key.as_ref(), None, true, &input.name, line_no)?;
+            }
+            let matched = matched.map(|(i, score)| (&filter_map[i], score));
+            join_writer.write_scored(&mut output.writer, row.line, matched)
+        })?;
+    }
+    Ok(())
+}
+
+/// Merge join of a filter file and a data input, both sorted by the key.
+/// Only the filter records of the current key are held in memory.
+fn join_sorted(
+    opts: &JoinOptions,
+    filter: FilterFile,
+    writer: Box<dyn Write>,
+) -> anyhow::Result<()> {
+    let mut output = JoinOutput::new(writer, opts, &filter);
+    let mut keys = DataKeys::new(opts, &filter);
+    let filter_key_indices = filter.key_indices;
+    let mut loader = filter.loader;
+    let mut sorted_filter = SortedFilter {
+        reader: SortedReader::new(opts.filter_file, filter.reader, filter.header_lines),
+        spec: join_sort_spec(filter_key_indices.as_deref()),
+        pending: None,
+        delimiter: opts.delimiter,
+    };
+    // Without a filter header, the first filter record decides the appended width
+    if let Some(record) = sorted_filter.peek()? {
+        loader.values(&record.as_row())?;
+    }
+    let append_count = loader.append_count();
+    let join_writer = JoinWriter::new(opts, append_count);
+    let keeps_unmatched_filter = opts.keeps_unmatched_filter();
 
-            let matched = filter_map.get(key.as_ref());
+    // Filter records of the current key
+    let mut current_group: Option<FilterGroup> = None;
+    for input in crate::libs::io::raw_input_sources(&opts.infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let Some(line_no) = output.read_header(&mut reader, |resolver| {
+            keys.resolve(resolver, Some(&input.name))
+        })?
+        else {
+            continue;
+        };
+        let extractor = keys.extractor()?;
 
-            if exclude {
-                if matched.is_none() {
-                    writer.write_all(row.line)?;
-                    writer.write_all(b"\n")?;
-                    if line_buffered {
-                        writer.flush()?;
-                    }
-                }
-            } else if let Some(values) = matched {
-                writer.write_all(row.line)?;
-                if !values.is_empty() {
-                    writer.write_all(&[opt_delimiter])?;
-                    writer.write_all(values)?;
-                }
-                writer.write_all(b"\n")?;
-                if line_buffered {
-                    writer.flush()?;
+        // Advance the filter file to the key of each data record
+        let mut layout = DataLayout {
+            key_indices: extractor.indices.clone(),
+            width: output.data_width,
+        };
+        let data_spec = join_sort_spec(layout.key_indices.as_deref());
+        let mut data = SortedReader::new(&input.name, reader, line_no);
+        current_group = sorted_filter.next_group(&mut loader)?;
+
+        while let Some((data_sort_key, record)) =
+            data.next_record(&data_spec, opts.delimiter)?
+        {
+            if record.as_line().is_empty() {
+                continue;
+            }
+            let row = record.as_row();
+            let key = extractor
+                .extract_from_row(&row, opts.delimiter)
+                .map_err(|idx| key_index_error(&row, idx))?;
+            output.data_width = output.data_width.max(row.ends.len());
+
+            while let Some(g) = current_group.as_ref() {
+                if g.sort_key >= data_sort_key {
+                    break;
                 }
-            } else if let Some(ref fill) = write_all_fill {
-                writer.write_all(row.line)?;
-                writer.write_all(fill)?;
-                writer.write_all(b"\n")?;
-                if line_buffered {
-                    writer.flush()?;
+                if keeps_unmatched_filter {
+                    layout.width = output.data_width;
+                    output.write_unmatched(&g.key, &g.entry, &layout, append_count)?;
                 }
+                current_group = sorted_filter.next_group(&mut loader)?;
             }
 
+            let mut matched = current_group
+                .as_mut()
+                .filter(|g| g.sort_key == data_sort_key)
+                .map(|g| &mut g.entry);
+            if opts.relationship.is_some() || opts.require_match {
+                check_match(
+                    matched.as_deref_mut(),
+                    key.as_ref(),
+                    opts.relationship,
+                    opts.require_match,
+                    &input.name,
+                    data.line(),
+                )?;
+            }
+            join_writer.write_data(&mut output.writer, row.line, matched)?;
+        }
+    }
+
+    if keeps_unmatched_filter {
+        // Without any data record, key fields are laid out as in the filter file
+        let layout = DataLayout {
+            key_indices: keys.layout.unwrap_or(filter_key_indices),
+            width: output.data_width,
+        };
+        // The rest of the filter file, after the last data record
+        let mut group = match current_group.take() {
+            Some(g) => Some(g),
+            None => sorted_filter.next_group(&mut loader)?,
+        };
+        while let Some(g) = group {
+            output.write_unmatched(&g.key, &g.entry, &layout, append_count)?;
+            group = sorted_filter.next_group(&mut loader)?;
+        }
+    }
+    Ok(())
+}
+
+/// Joins data records with the filter records whose intervals overlap theirs.
+fn join_interval(
+    opts: &JoinOptions,
+    mut filter: FilterFile,
+    spec: &str,
+    writer: Box<dyn Write>,
+) -> anyhow::Result<()> {
+    let fields = IntervalFields::parse(spec, &filter.resolver)?;
+
+    // Filter records, indexed by chromosome
+    let mut entries: Vec<FilterEntry> = Vec::new();
+    let mut index: std::collections::HashMap<Vec<u8>, IntervalTree<usize>, RandomState> =
+        std::collections::HashMap::with_hasher(RandomState::new());
+    let mut line_no = filter.header_lines;
+    filter.reader.for_each_row(opts.delimiter, |row| {
+        line_no += 1;
+        if row.line.is_empty() {
+            return Ok(());
+        }
+
+        let (chrom, start, end) =
+            fields.extract(row, opts.half_open, opts.filter_file, line_no)?;
+        let values = filter.loader.values(row)?;
+        index
+            .entry(chrom.to_vec())
+            .or_default()
+            .push(start, end, entries.len());
+        entries.push(FilterEntry {
+            values: smallvec![values],
+            matched: false,
+        });
+        Ok(())
+    })?;
+    for tree in index.values_mut() {
+        tree.index();
+    }
+
+    let append_count = filter.loader.append_count();
+    let join_writer = JoinWriter::new(opts, append_count);
+    let mut output = JoinOutput::new(writer, opts, &filter);
+
+    // Interval fields of the data, resolved against each data header
+    let data_spec = opts.data_interval.unwrap_or(spec);
+    let mut data_fields: Option<IntervalFields> = None;
+    let mut hits: Vec<usize> = Vec::new();
+    for input in crate::libs::io::raw_input_sources(&opts.infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let Some(mut line_no) = output.read_header(&mut reader, |resolver| {
+            data_fields = Some(IntervalFields::parse(data_spec, resolver)?);
+            Ok(())
+        })?
+        else {
+            continue;
+        };
+        let fields = match data_fields {
+            Some(fields) => fields,
+            None => {
+                let resolver = FieldResolver::new(None, opts.delimiter as char);
+                *data_fields.insert(IntervalFields::parse(data_spec, &resolver)?)
+            }
+        };
+
+        // Write the data record once per overlapping filter record
+        reader.for_each_row(opts.delimiter, |row: &TsvRow| {
+            line_no += 1;
+            if row.line.is_empty() {
+                return Ok(());
+            }
+
+            let (chrom, start, end) =
+                fields.extract(row, opts.half_open, &input.name, line_no)?;
+            output.data_width = output.data_width.max(row.ends.len());
+
+            hits.clear();
+            if let Some(tree) = index.get(chrom) {
+                tree.overlaps(start, end, |_, _, &i| hits.push(i));
+            }
+            // Matches are written in filter file order
+            hits.sort_unstable();
+
+            if hits.is_empty() {
+                if opts.require_match {
+                    let label = format!(
+                        "{}:{}-{}",
+                        String::from_utf8_lossy(chrom),
+                        start,
+                        if opts.half_open { end } else { end - 1 }
+                    );
+                    check_match(
+                        None,
+                        label.as_bytes(),
+                        None,
+                        true,
+                        &input.name,
+                        line_no,
+                    )?;
+                }
+                return join_writer.write_data(&mut output.writer, row.line, None);
+            }
+            for &i in &hits {
+                join_writer.write_data(
+                    &mut output.writer,
+                    row.line,
+                    Some(&mut entries[i]),
+                )?;
+            }
             Ok(())
         })?;
     }
 
+    if opts.keeps_unmatched_filter() {
+        // Unmatched filter records of an interval join have no data key columns
+        let layout = DataLayout {
+            key_indices: Some(Vec::new()),
+            width: output.data_width,
+        };
+        for entry in &entries {
+            output.write_unmatched(b"", entry, &layout, append_count)?;
+        }
+    }
+    Ok(())
+}
+
+/// Joins each data record with the filter record of the greatest as-of value
+/// not exceeding its own, among those with the same key.
+fn join_asof(
+    opts: &JoinOptions,
+    mut filter: FilterFile,
+    spec: &str,
+    writer: Box<dyn Write>,
+) -> anyhow::Result<()> {
+    let asof_field = filter
+        .resolver
+        .resolve_single(spec, "--asof")
+        .map_err(|e| anyhow::anyhow!(e))?;
+    // As-of joins are partitioned by the key fields, if any are given
+    let partitioned = opts.key_fields.is_some();
+    let mut parser = AsofParser {
+        unit_tolerance: opts
+            .tolerance
+            .filter(|&(_, _, has_unit)| has_unit)
+            .map(|(arg, _, _)| arg.to_string()),
+        ..Default::default()
+    };
+    let tolerance = opts.tolerance.map(|(_, seconds, _)| seconds);
+
+    // Filter records, sorted by their as-of value
+    let mut index = AsofIndex::new();
+    let mut line_no = filter.header_lines;
+    filter.reader.for_each_row(opts.delimiter, |row| {
+        line_no += 1;
+        if row.line.is_empty() {
+            return Ok(());
+        }
+
+        let key = if partitioned {
+            filter.loader.key(row)?.into_owned()
+        } else {
+            KeyBuffer::new()
+        };
+        let value =
+            parser.parse(row_field(row, asof_field)?, opts.filter_file, line_no)?;
+        let values = filter.loader.values(row)?;
+        index.push(key, value, values);
+        Ok(())
+    })?;
+    index.finish();
+
+    let join_writer = JoinWriter::new(opts, filter.loader.append_count());
+    let mut output = JoinOutput::new(writer, opts, &filter);
+    let mut keys = DataKeys::new(opts, &filter);
+
+    // As-of field of the data, resolved against each data header
+    let data_spec = opts.data_asof.unwrap_or(spec);
+    let mut data_asof: Option<usize> = None;
+    for input in crate::libs::io::raw_input_sources(&opts.infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let Some(mut line_no) = output.read_header(&mut reader, |resolver| {
+            data_asof = Some(
+                resolver
+                    .resolve_single(data_spec, "--data-asof")
+                    .map_err(|e| anyhow::anyhow!(e))?,
+            );
+            keys.resolve(resolver, Some(&input.name))
+        })?
+        else {
+            continue;
+        };
+        let extractor = keys.extractor()?;
+        let asof_field = match data_asof {
+            Some(field) => field,
+            None => {
+                let resolver = FieldResolver::new(None, opts.delimiter as char);
+                *data_asof.insert(
+                    resolver
+                        .resolve_single(data_spec, "--data-asof")
+                        .map_err(|e| anyhow::anyhow!(e))?,
+                )
+            }
+        };
+
+        // Match the latest filter record at or before the data value
+        reader.for_each_row(opts.delimiter, |row: &TsvRow| {
+            line_no += 1;
+            if row.line.is_empty() {
+                return Ok(());
+            }
+
+            let key = if partitioned {
+                extractor
+                    .extract_from_row(row, opts.delimiter)
+                    .map_err(|idx| key_index_error(row, idx))?
+            } else {
+                ParsedKey::Ref(&[])
+            };
+            let raw = row_field(row, asof_field)?;
+            let value = parser.parse(raw, &input.name, line_no)?;
+
+            let matched = index.lookup(key.as_ref(), value, tolerance);
+            if matched.is_none() && opts.require_match {
+                let mut label = key.as_ref().to_vec();
+                if !label.is_empty() {
+                    label.push(b' ');
+                }
+                label.extend_from_slice(raw);
+                check_match(None, &label, None, true, &input.name, line_no)?;
+            }
+            join_writer.write_data(&mut output.writer, row.line, matched)
+        })?;
+    }
     Ok(())
 }
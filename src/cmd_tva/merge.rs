@@ -0,0 +1,91 @@
+use crate::cmd_tva::sort::{build_sort_spec, key_args};
+use crate::libs::cli::{build_header_config, get_delimiter, header_args};
+use crate::libs::sort::{merge_sorted, SortedReader};
+use crate::libs::tsv::header::{write_header, Header};
+use crate::libs::tsv::reader::TsvReader;
+use clap::*;
+use std::io::Write;
+
+pub fn make_subcommand() -> Command {
+    Command::new("merge")
+        .about("Merges TSV/CSV files that are already sorted by the same keys")
+        .after_help(include_str!("../../docs/help/merge.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Sorted input file(s) to merge (default: stdin)"),
+        )
+        .args(key_args())
+        .args(header_args())
+        .arg(
+            Arg::new("delimiter")
+                .long("delimiter")
+                .short('t')
+                .num_args(1)
+                .default_value("\t")
+                .help("Field delimiter character (default: TAB)"),
+        )
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+}
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+
+    // Build HeaderConfig from arguments
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+
+    // All inputs are open at once; read each header up front
+    let mut header: Option<Header> = None;
+    let mut sources = Vec::with_capacity(infiles.len());
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 64 * 1024);
+        let mut header_lines = 0;
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(|e| anyhow::anyhow!(e))?;
+
+            if let Some(h_info) = header_result {
+                let h = Header::from_info(h_info, opt_delimiter as char);
+                header_lines = h.line_count() as u64;
+                // Store header from the first file only
+                if header.is_none() {
+                    header = Some(h);
+                }
+            }
+        }
+
+        sources.push(SortedReader::new(&input.name, reader, header_lines));
+    }
+
+    let spec = build_sort_spec(args, header.as_ref(), opt_delimiter)?;
+
+    // Write header (only from the first file)
+    if let Some(ref h) = header {
+        write_header(&mut writer, h, None)?;
+    }
+
+    merge_sorted(sources, &spec, opt_delimiter, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
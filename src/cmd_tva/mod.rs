@@ -2,15 +2,19 @@ pub mod append;
 pub mod bin;
 pub mod blank;
 pub mod check;
+pub mod crosstab;
 pub mod expr;
 pub mod extend;
 pub mod fill;
 pub mod filter;
+pub mod freq;
 pub mod r#from;
 pub mod header;
+pub mod hist;
 pub mod join;
 pub mod keep_header;
 pub mod longer;
+pub mod merge;
 pub mod mutate;
 pub mod nl;
 pub mod plot;
@@ -21,7 +25,10 @@ pub mod slice;
 pub mod sort;
 pub mod split;
 pub mod stats;
+pub mod summary;
+pub mod test;
 pub mod to;
 pub mod transpose;
 pub mod uniq;
 pub mod wider;
+pub mod window;
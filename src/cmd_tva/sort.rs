@@ -1,11 +1,16 @@
 use crate::libs::cli::{build_header_config, get_delimiter, header_args};
+use crate::libs::number::parse_size;
+use crate::libs::sort::{
+    Comparison, ExternalSorter, KeyOptions, SortSpec, SortedReader, TopSelector,
+};
+use crate::libs::tsv::fields::FieldResolver;
 use crate::libs::tsv::header::{write_header, Header};
-use crate::libs::tsv::key::{KeyBuffer, KeyExtractor};
+use crate::libs::tsv::key::KeyExtractor;
+use crate::libs::tsv::reader::TsvReader;
 use crate::libs::tsv::record::{TsvRecord, TsvRow};
 use clap::*;
-use intspan::IntSpan;
-use std::cmp::Ordering;
 use std::io::Write;
+use std::path::PathBuf;
 
 pub fn make_subcommand() -> Command {
     Command::new("sort")
@@ -17,26 +22,43 @@ pub fn make_subcommand() -> Command {
                 .index(1)
                 .help("Input file(s) to sort (default: stdin)"),
         )
+        .args(key_args())
         .arg(
-            Arg::new("key")
-                .long("key")
-                .short('k')
+            Arg::new("buffer-size")
+                .long("buffer-size")
+                .short('S')
                 .num_args(1)
-                .help("Field list (1-based) to use as sort key, e.g. 2 or 2,4-5"),
+                .help("Memory limit for in-memory runs, e.g. 512M or 2G; larger inputs spill to disk"),
         )
         .arg(
-            Arg::new("numeric")
-                .long("numeric")
-                .short('n')
-                .action(ArgAction::SetTrue)
-                .help("Compare key fields numerically"),
+            Arg::new("temp-dir")
+                .long("temp-dir")
+                .short('T')
+                .num_args(1)
+                .help("Directory for temporary run files (default: system temp dir)"),
+        )
+        .arg(
+            Arg::new("top")
+                .long("top")
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .conflicts_with("buffer-size")
+                .help("Output only the first N records in sort order, using O(N) memory"),
+        )
+        .arg(
+            Arg::new("group-by")
+                .long("group-by")
+                .num_args(1)
+                .requires("top")
+                .help("With --top, keep the first N records of each group (field list)"),
         )
         .arg(
-            Arg::new("reverse")
-                .long("reverse")
-                .short('r')
+            Arg::new("check")
+                .long("check")
+                .short('c')
                 .action(ArgAction::SetTrue)
-                .help("Reverse the sort order"),
+                .conflicts_with_all(["top", "buffer-size", "temp-dir"])
+                .help("Check that each input is sorted; fail at the first out-of-order line"),
         )
         .args(header_args())
         .arg(
@@ -57,31 +79,77 @@ pub fn make_subcommand() -> Command {
         )
 }
 
+/// Returns the key and comparison arguments shared by `sort` and `merge`.
+pub fn key_args() -> Vec<Arg> {
+    vec![
+        Arg::new("key")
+            .long("key")
+            .short('k')
+            .num_args(1)
+            .action(ArgAction::Append)
+            .help("Sort key FIELDS[:MODS], e.g. 2, 2,4-5, or score:nr; repeatable"),
+        Arg::new("numeric")
+            .long("numeric")
+            .short('n')
+            .action(ArgAction::SetTrue)
+            .group("comparison")
+            .help("Compare key fields numerically"),
+        Arg::new("general-numeric")
+            .long("general-numeric")
+            .short('g')
+            .action(ArgAction::SetTrue)
+            .group("comparison")
+            .help("Compare as general numbers (inf/nan aware, non-numbers first)"),
+        Arg::new("natural")
+            .long("natural")
+            .action(ArgAction::SetTrue)
+            .group("comparison")
+            .help("Natural (version) order, e.g. chr2 < chr10"),
+        Arg::new("human-numeric")
+            .long("human-numeric")
+            .action(ArgAction::SetTrue)
+            .group("comparison")
+            .help("Compare human-readable sizes, e.g. 10K < 3G"),
+        Arg::new("ignore-case")
+            .long("ignore-case")
+            .short('i')
+            .action(ArgAction::SetTrue)
+            .group("comparison")
+            .help("Compare lexically, ignoring ASCII case"),
+        Arg::new("reverse")
+            .long("reverse")
+            .short('r')
+            .action(ArgAction::SetTrue)
+            .help("Reverse the sort order"),
+    ]
+}
+
+/// Where records go: a full (possibly external) sort, or a bounded top-N selection.
+enum Sink {
+    Sort(ExternalSorter),
+    Top(TopSelector),
+}
+
 pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
     let infiles: Vec<String> = match args.get_many::<String>("infiles") {
         Some(values) => values.cloned().collect(),
         None => vec!["stdin".to_string()],
     };
 
-    let numeric = args.get_flag("numeric");
-    let reverse = args.get_flag("reverse");
-
     let opt_delimiter = get_delimiter(args, "delimiter")?;
 
-    let key_indices: Vec<usize> = if let Some(spec) = args.get_one::<String>("key") {
-        match parse_key_indices(spec) {
-            Ok(v) => v,
-            Err(msg) => {
-                return Err(anyhow::anyhow!(
-                    "invalid key specification `{}`: {}",
-                    spec,
-                    msg
-                ));
-            }
-        }
-    } else {
-        Vec::new()
+    if args.get_flag("check") {
+        return check_sorted(args, &infiles, opt_delimiter);
+    }
+
+    let buffer_size = match args.get_one::<String>("buffer-size") {
+        Some(s) => parse_size(s).map_err(|e| anyhow::anyhow!(e))?,
+        None => usize::MAX,
     };
+    if buffer_size == 0 {
+        return Err(anyhow::anyhow!("--buffer-size must be positive"));
+    }
+    let temp_dir = args.get_one::<String>("temp-dir").map(PathBuf::from);
 
     let mut writer =
         crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
@@ -90,13 +158,13 @@ pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
     let header_config =
         build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
 
-    let mut rows: Vec<TsvRecord> = Vec::new();
+    let top: Option<usize> = args.get_one::<usize>("top").copied();
+
     let mut header: Option<Header> = None;
-    let mut header_written = false;
+    let mut sink: Option<Sink> = None;
 
     for input in crate::libs::io::raw_input_sources(&infiles)? {
-        let mut reader =
-            crate::libs::tsv::reader::TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
 
         // If header is enabled, read header according to the configured mode
         if header_config.enabled {
@@ -112,143 +180,148 @@ pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
             }
         }
 
-        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
-            if row.line.is_empty() {
-                rows.push(TsvRecord::new());
-            } else {
-                rows.push(TsvRecord::from_row(row));
+        // Keys are resolved once the first header is known, so names work
+        if sink.is_none() {
+            let spec = build_sort_spec(args, header.as_ref(), opt_delimiter)?;
+            sink = Some(match top {
+                Some(n) => {
+                    let group_by =
+                        build_group_extractor(args, header.as_ref(), opt_delimiter)?;
+                    Sink::Top(TopSelector::new(spec, n, group_by))
+                }
+                None => Sink::Sort(
+                    ExternalSorter::new(spec, buffer_size, opt_delimiter)
+                        .with_temp_dir(temp_dir.clone()),
+                ),
+            });
+        }
+
+        match sink.as_mut().unwrap() {
+            Sink::Sort(sorter) => {
+                reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+                    if row.line.is_empty() {
+                        sorter.push(TsvRecord::new())
+                    } else {
+                        sorter.push(TsvRecord::from_row(row))
+                    }
+                })?;
             }
-            Ok(())
-        })?;
+            Sink::Top(selector) => {
+                reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+                    selector.push_row(row, opt_delimiter)
+                })?;
+            }
+        }
     }
 
     // Write header (only from the first file)
     if let Some(ref h) = header {
         write_header(&mut writer, h, None)?;
-        header_written = true;
     }
 
-    if rows.is_empty() {
-        if !header_written {
-            writer.flush()?;
-        }
-        return Ok(());
+    match sink {
+        Some(Sink::Sort(sorter)) => sorter.finish(&mut writer)?,
+        Some(Sink::Top(selector)) => selector.finish(&mut writer)?,
+        None => {}
     }
+    writer.flush()?;
 
-    let indices: Vec<usize> = if key_indices.is_empty() {
-        let max_fields = rows.iter().map(|r| r.len()).max().unwrap_or(0);
-        (1..=max_fields).collect() // 1-based indices for all fields
-    } else {
-        key_indices.iter().map(|&i| i + 1).collect() // Convert 0-based to 1-based
-    };
+    Ok(())
+}
 
-    if numeric {
-        // Pre-compute numeric keys
-        let mut keyed_rows: Vec<(Vec<f64>, TsvRecord)> = Vec::with_capacity(rows.len());
-        for record in rows {
-            let mut key = Vec::with_capacity(indices.len());
-            for &idx in &indices {
-                // idx is 1-based
-                let field = record.get(idx - 1).unwrap_or(b""); // get is 0-based
-                                                                // Optimization: parse directly from bytes without full utf8 check if possible,
-                                                                // but standard parse requires &str.
-                                                                // from_utf8_lossy might allocate, from_utf8 is better.
-                let s = std::str::from_utf8(field).unwrap_or("");
-                let n: f64 = s.trim().parse().unwrap_or(0.0);
-                key.push(n);
-            }
-            keyed_rows.push((key, record));
-        }
+/// Streams each input and fails at the first record that sorts before its predecessor.
+fn check_sorted(
+    args: &ArgMatches,
+    infiles: &[String],
+    delimiter: u8,
+) -> anyhow::Result<()> {
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
 
-        keyed_rows.sort_by(|(ka, _), (kb, _)| {
-            let mut ord = Ordering::Equal;
-            for (na, nb) in ka.iter().zip(kb.iter()) {
-                ord = na.partial_cmp(nb).unwrap_or(Ordering::Equal);
-                if ord != Ordering::Equal {
-                    break;
-                }
-            }
-            if reverse {
-                ord.reverse()
-            } else {
-                ord
-            }
-        });
-
-        for (_, record) in keyed_rows {
-            if record.is_empty() {
-                writer.write_all(b"\n")?;
-            } else {
-                writer.write_all(record.as_line())?;
-                writer.write_all(b"\n")?;
+    let mut spec: Option<SortSpec> = None;
+
+    for input in crate::libs::io::raw_input_sources(infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut header: Option<Header> = None;
+
+        if header_config.enabled {
+            if let Some(h_info) = reader
+                .read_header_mode(header_config.mode)
+                .map_err(|e| anyhow::anyhow!(e))?
+            {
+                header = Some(Header::from_info(h_info, delimiter as char));
             }
         }
-    } else {
-        // Pre-compute byte keys using KeyExtractor
-        // Note: Sort behavior for multiple keys:
-        // KeyExtractor concatenates keys with delimiter.
-        // "A\tB" vs "A\tC".
-        // If delimiter < any content char, this is equivalent to tuple comparison.
-        // Tab is \t (9). Visible chars >= 32.
-        // So this optimization is valid for standard text.
-
-        // KeyExtractor now expects 1-based indices.
-        let mut extractor = KeyExtractor::new(Some(indices), false, false); // strict=false to handle missing fields gracefully
-
-        // We use KeyBuffer (SmallVec) to store keys.
-        let mut keyed_rows: Vec<(KeyBuffer, TsvRecord)> = Vec::with_capacity(rows.len());
-
-        for record in rows {
-            let key_res = extractor.extract_from_record(&record, opt_delimiter);
-            let key = match key_res {
-                Ok(k) => k.into_owned(),
-                Err(_) => KeyBuffer::new(), // Should not happen with strict=false
-            };
-            keyed_rows.push((key, record));
-        }
+        let header_lines = header.as_ref().map_or(0, |h| h.line_count()) as u64;
 
-        if reverse {
-            keyed_rows.sort_by(|(ka, _), (kb, _)| kb.cmp(ka));
-        } else {
-            keyed_rows.sort_by(|(ka, _), (kb, _)| ka.cmp(kb));
+        // Keys are resolved against the first file's header, as for a full sort
+        if spec.is_none() {
+            spec = Some(build_sort_spec(args, header.as_ref(), delimiter)?);
         }
+        let spec = spec.as_ref().unwrap();
 
-        for (_, record) in keyed_rows {
-            if record.is_empty() {
-                writer.write_all(b"\n")?;
-            } else {
-                writer.write_all(record.as_line())?;
-                writer.write_all(b"\n")?;
-            }
-        }
+        let mut sorted = SortedReader::new(&input.name, reader, header_lines);
+        while sorted.next_record(spec, delimiter)?.is_some() {}
     }
 
     Ok(())
 }
 
-fn parse_key_indices(spec: &str) -> Result<Vec<usize>, String> {
-    if spec.trim().is_empty() {
-        return Ok(Vec::new());
-    }
+/// Builds the sort specification from `--key` and the global comparison flags.
+///
+/// Field names in keys are resolved against the column names of `header`.
+pub fn build_sort_spec(
+    args: &ArgMatches,
+    header: Option<&Header>,
+    delimiter: u8,
+) -> anyhow::Result<SortSpec> {
+    let comparison = if args.get_flag("numeric") {
+        Comparison::Numeric
+    } else if args.get_flag("general-numeric") {
+        Comparison::GeneralNumeric
+    } else if args.get_flag("natural") {
+        Comparison::Natural
+    } else if args.get_flag("human-numeric") {
+        Comparison::HumanSize
+    } else if args.get_flag("ignore-case") {
+        Comparison::IgnoreCase
+    } else {
+        Comparison::Lexical
+    };
+    let default = KeyOptions {
+        comparison,
+        reverse: args.get_flag("reverse"),
+    };
 
-    let mut indices: Vec<usize> = Vec::new();
-    let mut seen: std::collections::HashSet<i32> = std::collections::HashSet::new();
+    let keys: Vec<String> = args
+        .get_many::<String>("key")
+        .map(|v| v.cloned().collect())
+        .unwrap_or_default();
 
-    for part in spec.split(',') {
-        let part = part.trim();
-        if part.is_empty() {
-            return Err(format!("empty key list element in `{}`", spec));
-        }
-        let span = IntSpan::from(part);
-        for e in span.elements() {
-            if e <= 0 {
-                return Err(format!("field index must be >= 1 in `{}`", spec));
-            }
-            if seen.insert(e) {
-                indices.push((e - 1) as usize);
-            }
-        }
-    }
+    let resolver = FieldResolver::new(
+        header.and_then(|h| h.column_names.clone()),
+        delimiter as char,
+    );
+    SortSpec::from_key_args(&keys, default, &resolver)
+        .map_err(|msg| anyhow::anyhow!("invalid key specification: {}", msg))
+}
 
-    Ok(indices)
+/// Builds the `--group-by` key extractor for `--top`, if requested.
+fn build_group_extractor(
+    args: &ArgMatches,
+    header: Option<&Header>,
+    delimiter: u8,
+) -> anyhow::Result<Option<KeyExtractor>> {
+    let Some(spec) = args.get_one::<String>("group-by") else {
+        return Ok(None);
+    };
+    let resolver = FieldResolver::new(
+        header.and_then(|h| h.column_names.clone()),
+        delimiter as char,
+    );
+    let indices = resolver
+        .resolve(spec)
+        .map_err(|msg| anyhow::anyhow!("invalid --group-by: {}", msg))?;
+    // Missing fields group as empty, matching how sort treats them
+    Ok(Some(KeyExtractor::new(Some(indices), false, false)))
 }
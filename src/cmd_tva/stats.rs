@@ -1,14 +1,21 @@
+use crate::libs::aggregation::partial::{self, PartialMeta};
+use crate::libs::aggregation::tdigest::DEFAULT_COMPRESSION;
+use crate::libs::aggregation::transform::TransformKind;
 use crate::libs::aggregation::{
-    Aggregator, OpKind, Operation, StatsConfig, StatsProcessor,
+    hll, Aggregator, OpKind, Operation, StatsConfig, StatsProcessor,
 };
 use crate::libs::cli::{build_header_config, get_delimiter, header_args_with_columns};
 use crate::libs::io::map_io_err;
+use crate::libs::number::fast_parse_f64;
 use crate::libs::tsv::fields::FieldResolver;
 use crate::libs::tsv::header::Header;
 use crate::libs::tsv::key::{KeyBuffer, KeyExtractor};
 use crate::libs::tsv::reader::TsvReader;
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use crate::libs::tsv::record::{Row, TsvRow};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use indexmap::map::Entry;
 use indexmap::IndexMap;
+use std::io::BufRead;
 
 pub fn make_subcommand() -> Command {
     let mut cmd = Command::new("stats")
@@ -75,6 +82,11 @@ pub fn make_subcommand() -> Command {
         "unique-count",
         "Count the number of unique values"
     );
+    add_op_arg!(
+        cmd,
+        "approx-nunique",
+        "Estimate the number of unique values with HyperLogLog"
+    );
     add_op_arg!(cmd, "mode", "Get the most frequent value (mode)");
     add_op_arg!(cmd, "geomean", "Calculate geometric mean of fields");
     add_op_arg!(cmd, "harmmean", "Calculate harmonic mean of fields");
@@ -99,6 +111,29 @@ pub fn make_subcommand() -> Command {
         "Calculate coefficient of variation (stdev/mean) of fields"
     );
     add_op_arg!(cmd, "range", "Calculate range (max-min) of fields");
+    add_op_arg!(cmd, "skewness", "Calculate sample skewness of fields");
+    add_op_arg!(
+        cmd,
+        "kurtosis",
+        "Calculate sample excess kurtosis of fields"
+    );
+    add_op_arg!(
+        cmd,
+        "covariance",
+        "cov",
+        "Calculate sample covariance of two fields"
+    );
+    add_op_arg!(
+        cmd,
+        "pearson",
+        "corr",
+        "Calculate Pearson correlation of two fields"
+    );
+    add_op_arg!(
+        cmd,
+        "spearman",
+        "Calculate Spearman rank correlation of two fields"
+    );
     add_op_arg!(
         cmd,
         "quantile",
@@ -124,6 +159,26 @@ pub fn make_subcommand() -> Command {
         "not-missing-count",
         "Number of filled (non-empty) fields"
     );
+    add_op_arg!(cmd, "zscore", "Z-score of each row within its group");
+    add_op_arg!(
+        cmd,
+        "dense-rank",
+        "Rank of each row within its group, without gaps"
+    );
+    add_op_arg!(
+        cmd,
+        "ordinal-rank",
+        "Rank of each row within its group, ties in row order"
+    );
+    add_op_arg!(
+        cmd,
+        "percent-rank",
+        "Relative rank (rank-1)/(n-1) of each row within its group"
+    );
+    add_op_arg!(cmd, "share", "Fraction of the group sum of each row");
+    for name in TRANSFORM_ARGS.iter().map(|(name, _)| name) {
+        cmd = cmd.mut_arg(*name, |arg| arg.requires("transform"));
+    }
 
     cmd = cmd
         .arg(
@@ -169,6 +224,91 @@ pub fn make_subcommand() -> Command {
                 .num_args(1)
                 .help("Replace missing values (nan) with a string"),
         )
+        .arg(
+            Arg::new("approx-quantiles")
+                .long("approx-quantiles")
+                .action(ArgAction::SetTrue)
+                .help("Estimate median, quartiles, IQR and quantiles with a t-digest"),
+        )
+        .arg(
+            Arg::new("compression")
+                .long("compression")
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .requires("approx-quantiles")
+                .help("t-digest compression; higher is more accurate (default: 100)"),
+        )
+        .arg(
+            Arg::new("hll-precision")
+                .long("hll-precision")
+                .num_args(1)
+                .value_parser(value_parser!(u8))
+                .help("HyperLogLog precision for --approx-nunique, 4-18 (default: 14)"),
+        )
+        .arg(
+            Arg::new("rollup")
+                .long("rollup")
+                .action(ArgAction::SetTrue)
+                .requires("group-by")
+                .conflicts_with_all(["cube", "sorted", "partial", "merge-partials"])
+                .help("Add subtotal rows for each prefix of the --group-by fields"),
+        )
+        .arg(
+            Arg::new("cube")
+                .long("cube")
+                .action(ArgAction::SetTrue)
+                .requires("group-by")
+                .conflicts_with_all(["sorted", "partial", "merge-partials"])
+                .help(
+                    "Add subtotal rows for every combination of the --group-by fields",
+                ),
+        )
+        .arg(
+            Arg::new("placeholder")
+                .long("placeholder")
+                .num_args(1)
+                .help(
+                "Key value of collapsed fields in --rollup/--cube rows (default: (all))",
+            ),
+        )
+        .arg(
+            Arg::new("sorted")
+                .long("sorted")
+                .action(ArgAction::SetTrue)
+                .requires("group-by")
+                .conflicts_with("merge-partials")
+                .help(
+                    "Input is sorted by --group-by; write each group as soon as it ends",
+                ),
+        )
+        .arg(
+            Arg::new("transform")
+                .long("transform")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "rollup",
+                    "cube",
+                    "sorted",
+                    "partial",
+                    "merge-partials",
+                    "write-header",
+                ])
+                .help("Append the results of its group to every input row"),
+        )
+        .arg(
+            Arg::new("partial")
+                .long("partial")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("merge-partials")
+                .help("Write mergeable partial aggregates instead of results"),
+        )
+        .arg(
+            Arg::new("merge-partials")
+                .long("merge-partials")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["group-by", "approx-quantiles", "hll-precision"])
+                .help("Combine partial aggregates written by --partial into results"),
+        )
         .arg(
             Arg::new("infiles")
                 .num_args(0..)
@@ -179,6 +319,23 @@ pub fn make_subcommand() -> Command {
     cmd
 }
 
+/// Row-wise operations of `--transform`, by argument name.
+const TRANSFORM_ARGS: [(&str, TransformKind); 5] = [
+    ("zscore", TransformKind::ZScore),
+    ("dense-rank", TransformKind::DenseRank),
+    ("ordinal-rank", TransformKind::OrdinalRank),
+    ("percent-rank", TransformKind::PercentRank),
+    ("share", TransformKind::Share),
+];
+
+/// Processor, its operations, group key extractor and output headers
+type Setup = (
+    StatsProcessor,
+    Vec<Operation>,
+    Option<KeyExtractor>,
+    Vec<String>,
+);
+
 struct OpConfig {
     kind: OpKind,
     spec: Option<String>,
@@ -235,6 +392,7 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
     parse_op!("first", OpKind::First);
     parse_op!("last", OpKind::Last);
     parse_op!("nunique", OpKind::NUnique);
+    parse_op!("approx-nunique", OpKind::ApproxNUnique);
     parse_op!("mode", OpKind::Mode);
     parse_op!("mode-count", OpKind::ModeCount);
     parse_op!("missing-count", OpKind::MissingCount);
@@ -249,6 +407,12 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
     parse_op!("values", OpKind::Collapse);
     parse_op!("unique-values", OpKind::Unique);
     parse_op!("rand", OpKind::Rand);
+    parse_op!("skewness", OpKind::Skewness);
+    parse_op!("kurtosis", OpKind::Kurtosis);
+    // The second field is filled in once the field list is resolved
+    parse_op!("covariance", OpKind::Covariance(0));
+    parse_op!("pearson", OpKind::Pearson(0));
+    parse_op!("spearman", OpKind::Spearman(0));
 
     if let Some(indices) = matches.indices_of("quantile") {
         for (i, val) in indices.zip(matches.get_many::<String>("quantile").unwrap()) {
@@ -301,6 +465,17 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
 
     op_configs.sort_by_key(|c| c.arg_index);
 
+    // Row-wise operations are appended after the group results
+    let mut transform_configs: Vec<(usize, TransformKind, String)> = Vec::new();
+    for (name, kind) in TRANSFORM_ARGS {
+        if let Some(indices) = matches.indices_of(name) {
+            for (i, val) in indices.zip(matches.get_many::<String>(name).unwrap()) {
+                transform_configs.push((i, kind, val.clone()));
+            }
+        }
+    }
+    transform_configs.sort_by_key(|(i, _, _)| *i);
+
     let mut config = StatsConfig::default();
     if let Some(p) = matches.get_one::<String>("replace-missing") {
         config.missing_val = Some(p.clone());
@@ -319,16 +494,60 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
         }
     }
     config.exclude_missing = matches.get_flag("exclude-missing");
+    if matches.get_flag("approx-quantiles") {
+        let compression = matches
+            .get_one::<usize>("compression")
+            .copied()
+            .unwrap_or(DEFAULT_COMPRESSION);
+        if compression == 0 {
+            return Err(anyhow::anyhow!("--compression must be positive"));
+        }
+        config.approx_quantiles = Some(compression);
+    }
+    if let Some(&precision) = matches.get_one::<u8>("hll-precision") {
+        if !(hll::MIN_PRECISION..=hll::MAX_PRECISION).contains(&precision) {
+            return Err(anyhow::anyhow!(
+                "--hll-precision must be between {} and {}",
+                hll::MIN_PRECISION,
+                hll::MAX_PRECISION
+            ));
+        }
+        config.hll_precision = precision;
+    }
 
     let infiles: Vec<String> = match matches.get_many::<String>("infiles") {
         Some(values) => values.cloned().collect(),
         None => vec!["stdin".to_string()],
     };
 
+    if matches.get_flag("merge-partials") {
+        if !op_configs.is_empty() {
+            return Err(anyhow::anyhow!(
+                "--merge-partials takes its operations from the partial files"
+            ));
+        }
+        return merge_partials(&infiles, config);
+    }
+
     // Build HeaderConfig from arguments
     let header_config =
         build_header_config(matches, true).map_err(|e| anyhow::anyhow!(e))?;
 
+    let partial = matches.get_flag("partial");
+    let sorted = matches.get_flag("sorted");
+    let transform = matches.get_flag("transform");
+    let sets_mode = if matches.get_flag("rollup") {
+        Some(SetsMode::Rollup)
+    } else if matches.get_flag("cube") {
+        Some(SetsMode::Cube)
+    } else {
+        None
+    };
+    let placeholder = matches.get_one::<String>("placeholder");
+    if placeholder.is_some() && sets_mode.is_none() {
+        return Err(anyhow::anyhow!("--placeholder requires --rollup or --cube"));
+    }
+    let placeholder = placeholder.map_or("(all)", |s| s.as_str());
     let write_header = matches.get_flag("write-header");
     let group_by_spec = matches.get_one::<String>("group-by").cloned();
     let replace_missing = matches.get_one::<String>("replace-missing").cloned();
@@ -337,16 +556,17 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
     let mut processor: Option<StatsProcessor> = None;
     let mut aggregator: Option<Aggregator> = None;
     let mut groups: IndexMap<KeyBuffer, Aggregator> = IndexMap::new();
+    let mut streaming = StreamingGroups::default();
+    let mut grouping_sets: Option<GroupingSets> = None;
+    let mut set_groups: IndexMap<(u64, KeyBuffer), Aggregator> = IndexMap::new();
     let mut group_extractor: Option<KeyExtractor> = None;
     let mut use_grouping = false;
+    let mut rows_seen = false;
+    let mut transform_rows = TransformRows::default();
 
     // Helper to setup processor
     // Takes header bytes instead of Header reference for FieldResolver compatibility
-    let setup_processor = |header_bytes: Option<&[u8]>| -> anyhow::Result<(
-        StatsProcessor,
-        Option<KeyExtractor>,
-        Vec<String>,
-    )> {
+    let setup_processor = |header_bytes: Option<&[u8]>| -> anyhow::Result<Setup> {
         let mut ops = Vec::new();
         let mut output_headers = Vec::new();
 
@@ -390,6 +610,48 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
                     .resolve(field_spec)
                     .map_err(|e| anyhow::anyhow!("Error parsing field list: {}", e))?;
 
+                if config.kind.is_bivariate() {
+                    let op_name = match config.kind {
+                        OpKind::Covariance(_) => "covariance",
+                        OpKind::Pearson(_) => "pearson",
+                        _ => "spearman",
+                    };
+                    let [idx1, idx2] = indices[..] else {
+                        return Err(anyhow::anyhow!(
+                            "--{} needs exactly two fields, got {}",
+                            op_name,
+                            indices.len()
+                        ));
+                    };
+                    // The second field travels in the kind itself
+                    let kind = match config.kind {
+                        OpKind::Covariance(_) => OpKind::Covariance(idx2 - 1),
+                        OpKind::Pearson(_) => OpKind::Pearson(idx2 - 1),
+                        _ => OpKind::Spearman(idx2 - 1),
+                    };
+                    ops.push(Operation {
+                        kind,
+                        field_idx: Some(idx1 - 1),
+                    });
+
+                    let column_name = |idx: usize| {
+                        header_opt
+                            .as_ref()
+                            .and_then(|h| h.column_names_list())
+                            .and_then(|names| names.get(idx - 1).cloned())
+                            .unwrap_or_else(|| format!("field{}", idx))
+                    };
+                    output_headers.push(custom_header_override.unwrap_or_else(|| {
+                        format!(
+                            "{}_{}_{}",
+                            column_name(idx1),
+                            column_name(idx2),
+                            op_name
+                        )
+                    }));
+                    continue;
+                }
+
                 if custom_header_override.is_some() && indices.len() > 1 {
                     return Err(anyhow::anyhow!(
                         "custom header is not allowed with multiple fields"
@@ -404,33 +666,8 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
                     });
 
                     let suffix = match config.kind {
-                        OpKind::Sum => "_sum".to_string(),
-                        OpKind::Mean => "_mean".to_string(),
-                        OpKind::Min => "_min".to_string(),
-                        OpKind::Max => "_max".to_string(),
-                        OpKind::Median => "_median".to_string(),
-                        OpKind::Stdev => "_stdev".to_string(),
-                        OpKind::Variance => "_variance".to_string(),
-                        OpKind::Mad => "_mad".to_string(),
-                        OpKind::First => "_first".to_string(),
-                        OpKind::Last => "_last".to_string(),
-                        OpKind::NUnique => "_nunique".to_string(),
-                        OpKind::Mode => "_mode".to_string(),
-                        OpKind::GeoMean => "_geomean".to_string(),
-                        OpKind::HarmMean => "_harmmean".to_string(),
-                        OpKind::Q1 => "_q1".to_string(),
-                        OpKind::Q3 => "_q3".to_string(),
-                        OpKind::IQR => "_iqr".to_string(),
-                        OpKind::CV => "_cv".to_string(),
-                        OpKind::Range => "_range".to_string(),
-                        OpKind::Unique => "_unique".to_string(),
-                        OpKind::Collapse => "_collapse".to_string(),
-                        OpKind::Rand => "_rand".to_string(),
-                        OpKind::ModeCount => "_mode_count".to_string(),
-                        OpKind::MissingCount => "_missing_count".to_string(),
-                        OpKind::NotMissingCount => "_not_missing_count".to_string(),
-                        OpKind::Quantile(p) => format!("_quantile_{}", p),
                         OpKind::Count => "".to_string(),
+                        kind => format!("_{}", kind.name()),
                     };
 
                     let name = if let Some(custom) = &custom_header_override {
@@ -497,7 +734,8 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
         final_headers.extend(output_headers);
 
         Ok((
-            StatsProcessor::new(ops, config.clone()),
+            StatsProcessor::new(ops.clone(), config.clone()),
+            ops,
             extractor,
             final_headers,
         ))
@@ -523,24 +761,82 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
 
         // Setup processor on first file
         if processor.is_none() {
-            let (proc, extractor, headers) = setup_processor(header_bytes.as_deref())?;
+            let (proc, ops, extractor, headers) =
+                setup_processor(header_bytes.as_deref())?;
             processor = Some(proc);
             group_extractor = extractor;
             use_grouping = group_extractor.is_some();
+            if let (Some(mode), Some(extractor)) = (sets_mode, &group_extractor) {
+                let n = extractor.indices.as_ref().map_or(0, |idxs| idxs.len());
+                grouping_sets = Some(GroupingSets::new(mode, n, placeholder)?);
+            }
 
-            if !use_grouping {
+            if !use_grouping && !transform {
                 aggregator = Some(processor.as_ref().unwrap().create_aggregator());
             }
 
-            // Write header if: 1) input has header, or 2) --write-header is specified
-            if header_config.enabled || write_header {
+            if transform {
+                let (fields, names) = resolve_transforms(
+                    &transform_configs,
+                    header_bytes.as_deref(),
+                    opt_delimiter as char,
+                )?;
+                transform_rows.fields = fields;
+                if let Some(bytes) = &header_bytes {
+                    // The group key is already part of each row
+                    let num_keys = group_extractor
+                        .as_ref()
+                        .and_then(|e| e.indices.as_ref())
+                        .map_or(0, |idxs| idxs.len());
+                    let mut line = vec![String::from_utf8_lossy(bytes).into_owned()];
+                    line.extend(headers.into_iter().skip(num_keys));
+                    line.extend(names);
+                    println!("{}", line.join("\t"));
+                }
+            } else if partial {
+                let meta = PartialMeta {
+                    ops,
+                    grouped: use_grouping,
+                    approx_quantiles: config.approx_quantiles,
+                    hll_precision: config.hll_precision,
+                };
+                println!("{}", meta.to_line());
+                if header_config.enabled || write_header {
+                    println!("{}\t{}", partial::HEADER_PREFIX, headers.join("\t"));
+                }
+            } else if header_config.enabled || write_header {
+                // Write header if: 1) input has header, or 2) --write-header is specified
                 println!("{}", headers.join("\t"));
             }
         }
 
         if let Some(proc) = &processor {
+            let emit = |key: &[u8], agg: &Aggregator| {
+                if partial {
+                    write_partial_group(key, agg);
+                } else {
+                    write_group(proc, key, agg, replace_missing.as_ref());
+                }
+            };
             reader
                 .for_each_row(opt_delimiter, |row| {
+                    if transform {
+                        let key = match group_extractor.as_mut() {
+                            Some(extractor) => extractor
+                                .extract_from_row(row, opt_delimiter)
+                                .map(|k| k.into_owned())
+                                .unwrap_or_default(),
+                            None => KeyBuffer::new(),
+                        };
+                        let entry = groups.entry(key);
+                        let group = entry.index();
+                        proc.update(
+                            entry.or_insert_with(|| proc.create_aggregator()),
+                            row,
+                        );
+                        transform_rows.push(group, row);
+                        return Ok(());
+                    }
                     if use_grouping {
                         let key_res = group_extractor
                             .as_mut()
@@ -551,13 +847,28 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
                             Err(_) => KeyBuffer::new(),
                         };
 
-                        let agg = groups
-                            .entry(key)
-                            .or_insert_with(|| proc.create_aggregator());
+                        if let Some(sets) = &grouping_sets {
+                            for (mask, key) in sets.keys(&key, opt_delimiter) {
+                                let agg = set_groups
+                                    .entry((mask, key))
+                                    .or_insert_with(|| proc.create_aggregator());
+                                proc.update(agg, row);
+                            }
+                            return Ok(());
+                        }
+
+                        let agg = if sorted {
+                            streaming.aggregator(key, proc, emit)?
+                        } else {
+                            groups
+                                .entry(key)
+                                .or_insert_with(|| proc.create_aggregator())
+                        };
                         proc.update(agg, row);
                     } else {
                         if let Some(agg) = &mut aggregator {
                             proc.update(agg, row);
+                            rows_seen = true;
                         }
                     }
                     Ok(())
@@ -567,43 +878,437 @@ pub fn execute(matches: &ArgMatches) -> anyhow::Result<()> {
     }
 
     if let Some(proc) = &processor {
-        if use_grouping {
-            let mut keys: Vec<_> = groups.keys().collect();
-            keys.sort();
-
-            for key in keys {
-                let agg = &groups[key];
-                print!("{}", String::from_utf8_lossy(key));
-
-                let values = proc.format_results(agg);
-                let values = if let Some(replacement) = &replace_missing {
-                    values
-                        .into_iter()
-                        .map(|v| if v == "nan" { replacement.clone() } else { v })
-                        .collect()
-                } else {
-                    values
-                };
+        if let Some((key, agg)) = streaming.current.take() {
+            if partial {
+                write_partial_group(&key, &agg);
+            } else {
+                write_group(proc, &key, &agg, replace_missing.as_ref());
+            }
+        }
+        if transform {
+            transform_rows.write(
+                proc,
+                &groups,
+                replace_missing.as_ref(),
+                config.precision,
+            );
+        } else if partial {
+            // An empty input contributes nothing, not even a `last` value
+            write_partial(&groups, aggregator.as_ref().filter(|_| rows_seen));
+        } else if grouping_sets.is_some() {
+            write_grouping_sets(
+                proc,
+                &set_groups,
+                opt_delimiter,
+                replace_missing.as_ref(),
+            );
+        } else {
+            write_results(proc, &groups, aggregator.as_ref(), replace_missing.as_ref());
+        }
+    }
+
+    Ok(())
+}
+
+/// Row-wise operations with their 0-based field.
+type TransformFields = Vec<(TransformKind, usize)>;
+
+/// Resolves the fields of the `--transform` operations, returning them with
+/// their output headers.
+fn resolve_transforms(
+    configs: &[(usize, TransformKind, String)],
+    header_bytes: Option<&[u8]>,
+    delimiter: char,
+) -> anyhow::Result<(TransformFields, Vec<String>)> {
+    let resolver = FieldResolver::new(header_bytes.map(|b| b.to_vec()), delimiter);
+    let names = header_bytes.and_then(|bytes| {
+        Header::from_column_names(bytes.to_vec(), delimiter).column_names_list()
+    });
+
+    let mut fields = Vec::new();
+    let mut headers = Vec::new();
+    for (_, kind, spec) in configs {
+        let (field_spec, custom) = match spec.rsplit_once(':') {
+            Some((field_spec, custom)) if !custom.is_empty() => {
+                (field_spec, Some(custom))
+            }
+            _ => (spec.as_str(), None),
+        };
+        let indices = resolver
+            .resolve(field_spec)
+            .map_err(|e| anyhow::anyhow!("Error parsing field list: {}", e))?;
+        if custom.is_some() && indices.len() > 1 {
+            return Err(anyhow::anyhow!(
+                "custom header is not allowed with multiple fields"
+            ));
+        }
+
+        for idx in indices {
+            fields.push((*kind, idx - 1));
+            headers.push(match custom {
+                Some(custom) => custom.to_string(),
+                None => {
+                    let name = names
+                        .as_ref()
+                        .and_then(|names| names.get(idx - 1).cloned())
+                        .unwrap_or_else(|| format!("field{}", idx));
+                    format!("{}_{}", name, kind.name())
+                }
+            });
+        }
+    }
+    Ok((fields, headers))
+}
+
+/// `--transform`: every input row with its group, and the values of the
+/// row-wise operations, per group in row order.
+#[derive(Default)]
+struct TransformRows {
+    fields: TransformFields,
+    rows: Vec<(usize, Vec<u8>)>,
+    values: Vec<Vec<Vec<Option<f64>>>>,
+}
+
+impl TransformRows {
+    fn push(&mut self, group: usize, row: &TsvRow) {
+        if group == self.values.len() {
+            self.values.push(vec![Vec::new(); self.fields.len()]);
+        }
+        for (values, (_, idx)) in self.values[group].iter_mut().zip(&self.fields) {
+            values.push(row.get_bytes(idx + 1).and_then(fast_parse_f64));
+        }
+        self.rows.push((group, row.line.to_vec()));
+    }
+
+    /// Prints the rows in input order, each followed by the results of its
+    /// group and of the row-wise operations.
+    fn write(
+        &self,
+        proc: &StatsProcessor,
+        groups: &IndexMap<KeyBuffer, Aggregator>,
+        replace_missing: Option<&String>,
+        precision: Option<usize>,
+    ) {
+        let results: Vec<Vec<String>> = groups
+            .values()
+            .map(|agg| format_values(proc, agg, replace_missing))
+            .collect();
+        // Per group and operation, the results of the rows, consumed in order
+        let mut columns: Vec<Vec<std::vec::IntoIter<String>>> = self
+            .values
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .zip(&self.fields)
+                    .map(|(values, (kind, _))| kind.apply(values, precision).into_iter())
+                    .collect()
+            })
+            .collect();
+
+        for (group, line) in &self.rows {
+            let mut fields = vec![String::from_utf8_lossy(line).into_owned()];
+            fields.extend(results[*group].iter().cloned());
+            fields.extend(
+                columns[*group]
+                    .iter_mut()
+                    .map(|c| c.next().unwrap_or_default()),
+            );
+            println!("{}", fields.join("\t"));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SetsMode {
+    Rollup,
+    Cube,
+}
+
+/// `--rollup`/`--cube`: the grouping sets aggregated alongside each group,
+/// as bitmasks of the collapsed key fields (bit `i` for the `i`-th field).
+struct GroupingSets {
+    masks: Vec<u64>,
+    placeholder: Vec<u8>,
+}
+
+/// `--cube` aggregates `2^n` sets per row.
+const MAX_CUBE_FIELDS: usize = 12;
 
-                if !values.is_empty() {
-                    print!("\t{}", values.join("\t"));
+impl GroupingSets {
+    fn new(mode: SetsMode, n: usize, placeholder: &str) -> anyhow::Result<Self> {
+        if n >= u64::BITS as usize {
+            return Err(anyhow::anyhow!("too many group-by fields: {}", n));
+        }
+        let all = (1u64 << n) - 1;
+        let masks = match mode {
+            // (a, b, c), (a, b), (a), ()
+            SetsMode::Rollup => {
+                (0..=n).map(|k| all & !((1u64 << (n - k)) - 1)).collect()
+            }
+            SetsMode::Cube => {
+                if n > MAX_CUBE_FIELDS {
+                    return Err(anyhow::anyhow!(
+                        "--cube supports at most {} group-by fields, got {}",
+                        MAX_CUBE_FIELDS,
+                        n
+                    ));
                 }
-                println!();
+                (0..=all).collect()
             }
-        } else {
-            if let Some(agg) = &aggregator {
-                let values = proc.format_results(agg);
-                let values = if let Some(replacement) = &replace_missing {
-                    values
-                        .into_iter()
-                        .map(|v| if v == "nan" { replacement.clone() } else { v })
-                        .collect()
+        };
+        Ok(Self {
+            masks,
+            placeholder: placeholder.as_bytes().to_vec(),
+        })
+    }
+
+    /// The key of every set the row with group key `key` belongs to.
+    fn keys<'a>(
+        &'a self,
+        key: &'a [u8],
+        delimiter: u8,
+    ) -> impl Iterator<Item = (u64, KeyBuffer)> + 'a {
+        self.masks.iter().map(move |&mask| {
+            let mut buf = KeyBuffer::new();
+            for (i, part) in key.split(|&b| b == delimiter).enumerate() {
+                if i > 0 {
+                    buf.push(delimiter);
+                }
+                if mask & (1 << i) != 0 {
+                    buf.extend_from_slice(&self.placeholder);
                 } else {
-                    values
-                };
-                println!("{}", values.join("\t"));
+                    buf.extend_from_slice(part);
+                }
+            }
+            (mask, buf)
+        })
+    }
+}
+
+/// Prints the groups and subtotals of `--rollup`/`--cube`, in key order, with
+/// each subtotal after the groups it sums up.
+fn write_grouping_sets(
+    proc: &StatsProcessor,
+    groups: &IndexMap<(u64, KeyBuffer), Aggregator>,
+    delimiter: u8,
+    replace_missing: Option<&String>,
+) {
+    let mut entries: Vec<_> = groups.iter().collect();
+    // Collapsed fields sort after every value of the field
+    let sort_key = |(mask, key): &(u64, KeyBuffer)| -> Vec<(bool, Vec<u8>)> {
+        key.split(|&b| b == delimiter)
+            .enumerate()
+            .map(|(i, part)| {
+                if mask & (1 << i) != 0 {
+                    (true, Vec::new())
+                } else {
+                    (false, part.to_vec())
+                }
+            })
+            .collect()
+    };
+    entries.sort_by_cached_key(|(k, _)| sort_key(k));
+
+    for ((_, key), agg) in entries {
+        write_group(proc, key, agg, replace_missing);
+    }
+}
+
+/// Prints one line per group, in key order, or the single ungrouped line.
+fn write_results(
+    proc: &StatsProcessor,
+    groups: &IndexMap<KeyBuffer, Aggregator>,
+    aggregator: Option<&Aggregator>,
+    replace_missing: Option<&String>,
+) {
+    if let Some(agg) = aggregator {
+        println!("{}", format_values(proc, agg, replace_missing).join("\t"));
+        return;
+    }
+
+    let mut keys: Vec<_> = groups.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        write_group(proc, key, &groups[key], replace_missing);
+    }
+}
+
+/// Prints the results of one group, prefixed by its key.
+fn write_group(
+    proc: &StatsProcessor,
+    key: &[u8],
+    agg: &Aggregator,
+    replace_missing: Option<&String>,
+) {
+    print!("{}", String::from_utf8_lossy(key));
+    let values = format_values(proc, agg, replace_missing);
+    if !values.is_empty() {
+        print!("\t{}", values.join("\t"));
+    }
+    println!();
+}
+
+fn format_values(
+    proc: &StatsProcessor,
+    agg: &Aggregator,
+    replace_missing: Option<&String>,
+) -> Vec<String> {
+    let values = proc.format_results(agg);
+    if let Some(replacement) = replace_missing {
+        values
+            .into_iter()
+            .map(|v| if v == "nan" { replacement.clone() } else { v })
+            .collect()
+    } else {
+        values
+    }
+}
+
+/// Prints the aggregator state of each group, keyed like `write_results`.
+fn write_partial(
+    groups: &IndexMap<KeyBuffer, Aggregator>,
+    aggregator: Option<&Aggregator>,
+) {
+    let mut keys: Vec<_> = groups.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        write_partial_group(key, &groups[key]);
+    }
+    if let Some(agg) = aggregator {
+        println!("\t{}", partial::encode_state(agg).join("\t"));
+    }
+}
+
+/// Prints the aggregator state of one group, prefixed by its escaped key.
+fn write_partial_group(key: &[u8], agg: &Aggregator) {
+    let key = String::from_utf8_lossy(key);
+    let state = partial::encode_state(agg);
+    println!("{}\t{}", partial::escape(&key), state.join("\t"));
+}
+
+/// `--sorted`: the group being aggregated. Keys must come in increasing
+/// byte order, so a key that sorts before the current one means the input is
+/// not sorted, and a closed group can never come back.
+#[derive(Default)]
+struct StreamingGroups {
+    current: Option<(KeyBuffer, Aggregator)>,
+}
+
+impl StreamingGroups {
+    /// Returns the aggregator of `key`. When the key changes, the finished
+    /// group is passed to `emit` and its memory released.
+    fn aggregator(
+        &mut self,
+        key: KeyBuffer,
+        proc: &StatsProcessor,
+        emit: impl Fn(&[u8], &Aggregator),
+    ) -> std::io::Result<&mut Aggregator> {
+        let same = matches!(&self.current, Some((current, _)) if *current == key);
+        if !same {
+            if let Some((done, agg)) = self.current.take() {
+                emit(&done, &agg);
+                if key < done {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "--sorted: group `{}` sorts before group `{}`; \
+                             input is not sorted by the --group-by fields",
+                            String::from_utf8_lossy(&key),
+                            String::from_utf8_lossy(&done)
+                        ),
+                    ));
+                }
+            }
+            self.current = Some((key, proc.create_aggregator()));
+        }
+        Ok(&mut self.current.as_mut().unwrap().1)
+    }
+}
+
+/// `--merge-partials`: combines partial files, in the order given, and prints
+/// the results.
+fn merge_partials(infiles: &[String], mut config: StatsConfig) -> anyhow::Result<()> {
+    let mut meta: Option<PartialMeta> = None;
+    let mut processor: Option<StatsProcessor> = None;
+    let mut header: Option<String> = None;
+    let mut aggregator: Option<Aggregator> = None;
+    let mut groups: IndexMap<KeyBuffer, Aggregator> = IndexMap::new();
+
+    for input in crate::libs::io::input_sources(infiles)? {
+        let mut lines = input.reader.lines();
+        let first = lines.next().transpose()?.unwrap_or_default();
+        let file_meta = PartialMeta::parse(&first)
+            .map_err(|e| anyhow::anyhow!("{}: {}", input.name, e))?;
+
+        match &meta {
+            None => {
+                config.approx_quantiles = file_meta.approx_quantiles;
+                config.hll_precision = file_meta.hll_precision;
+                let proc = StatsProcessor::new(file_meta.ops.clone(), config.clone());
+                if !file_meta.grouped {
+                    aggregator = Some(proc.create_aggregator());
+                }
+                processor = Some(proc);
+                meta = Some(file_meta);
+            }
+            Some(m) if *m != file_meta => {
+                return Err(anyhow::anyhow!(
+                    "{}: partial file was written with different operations or settings",
+                    input.name
+                ));
+            }
+            Some(_) => {}
+        }
+        let proc = processor.as_ref().unwrap();
+
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if i == 0 {
+                if let Some(h) = line.strip_prefix(partial::HEADER_PREFIX) {
+                    if header.is_none() {
+                        header = Some(h.strip_prefix('\t').unwrap_or(h).to_string());
+                    }
+                    continue;
+                }
             }
+
+            let mut fields = line.split('\t');
+            let key = fields.next().unwrap_or_default();
+            let cols: Vec<&str> = fields.collect();
+            let line_error =
+                |e: String| anyhow::anyhow!("{}: line {}: {}", input.name, i + 2, e);
+
+            let mut agg = proc.create_aggregator();
+            partial::decode_state(&cols, &mut agg).map_err(line_error)?;
+
+            if let Some(total) = &mut aggregator {
+                total.merge(&agg);
+            } else {
+                let key = partial::unescape(key)
+                    .ok_or_else(|| line_error(format!("invalid group key `{}`", key)))?;
+                match groups.entry(KeyBuffer::from_slice(key.as_bytes())) {
+                    Entry::Occupied(mut e) => e.get_mut().merge(&agg),
+                    Entry::Vacant(e) => {
+                        e.insert(agg);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(proc) = &processor {
+        if let Some(h) = &header {
+            println!("{}", h);
         }
+        write_results(
+            proc,
+            &groups,
+            aggregator.as_ref(),
+            config.missing_val.as_ref(),
+        );
     }
 
     Ok(())
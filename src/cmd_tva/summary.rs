@@ -0,0 +1,162 @@
+use crate::libs::cli::{
+    build_header_config, delimiter_arg, get_delimiter, header_args_with_columns,
+};
+use crate::libs::io::map_io_err;
+use crate::libs::summary::{Profiler, REPORT_COLUMNS};
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::header::Header;
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::TsvRow;
+use clap::*;
+use std::io::Write;
+
+pub fn make_subcommand() -> Command {
+    Command::new("summary")
+        .about("Profiles every column: type, counts, range, mean and top values")
+        .after_help(include_str!("../../docs/help/summary.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Input TSV file(s) to process (default: stdin)"),
+        )
+        .arg(
+            Arg::new("fields")
+                .long("fields")
+                .short('f')
+                .num_args(1)
+                .help("Fields to profile (default: all)"),
+        )
+        .arg(
+            Arg::new("top")
+                .long("top")
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("3")
+                .help("Number of most frequent values to report (0 to disable)"),
+        )
+        .arg(
+            Arg::new("float-precision")
+                .long("float-precision")
+                .short('p')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("4")
+                .help("Precision for floating point numbers"),
+        )
+        .args(header_args_with_columns())
+        .arg(delimiter_arg())
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+}
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+    let delimiter_char = opt_delimiter as char;
+
+    let fields_spec = args.get_one::<String>("fields");
+    let top = *args.get_one::<usize>("top").unwrap();
+    let precision = args.get_one::<usize>("float-precision").copied();
+
+    let mut profiler: Option<Profiler> = None;
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(map_io_err)?;
+            let Some(header_info) = header_result else {
+                continue; // Empty file
+            };
+
+            if profiler.is_none() {
+                let column_names_bytes = header_info.column_names_line.clone();
+                let names = Header::from_info(header_info, delimiter_char)
+                    .column_names_list()
+                    .unwrap_or_default();
+                let resolver = FieldResolver::new(column_names_bytes, delimiter_char);
+                let columns = select_columns(fields_spec, &resolver, names.len())?
+                    .into_iter()
+                    .map(|idx| {
+                        let name = names
+                            .get(idx)
+                            .cloned()
+                            .unwrap_or_else(|| format!("field{}", idx + 1));
+                        (idx, name)
+                    })
+                    .collect();
+                profiler = Some(Profiler::new(columns, top, precision));
+            }
+        }
+
+        if profiler.is_none() {
+            if let Some(spec) = fields_spec {
+                let resolver = FieldResolver::new(None, delimiter_char);
+                let columns = select_columns(Some(spec), &resolver, 0)?
+                    .into_iter()
+                    .map(|idx| (idx, format!("field{}", idx + 1)))
+                    .collect();
+                profiler = Some(Profiler::new(columns, top, precision));
+            }
+        }
+
+        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+            let profiler = profiler.get_or_insert_with(|| {
+                // Without a header or --fields, the first row tells how many
+                // columns there are
+                let columns = (0..row.field_count())
+                    .map(|idx| (idx, format!("field{}", idx + 1)))
+                    .collect();
+                Profiler::new(columns, top, precision)
+            });
+            profiler.update(row);
+            Ok(())
+        })?;
+    }
+
+    writer.write_all(REPORT_COLUMNS.join("\t").as_bytes())?;
+    writer.write_all(b"\n")?;
+    if let Some(profiler) = profiler {
+        for line in profiler.report() {
+            writer.write_all(line.join("\t").as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Resolves `--fields` to 0-based indices; all `num_columns` columns without it.
+fn select_columns(
+    fields_spec: Option<&String>,
+    resolver: &FieldResolver,
+    num_columns: usize,
+) -> anyhow::Result<Vec<usize>> {
+    match fields_spec {
+        Some(spec) => Ok(resolver
+            .resolve(spec)
+            .map_err(|e| anyhow::anyhow!(e))?
+            .into_iter()
+            .map(|idx| idx - 1)
+            .collect()),
+        None => Ok((0..num_columns).collect()),
+    }
+}
@@ -0,0 +1,235 @@
+use crate::libs::cli::{
+    build_header_config, delimiter_arg, get_delimiter, header_args_with_columns,
+};
+use crate::libs::hypothesis::TwoSampleTest;
+use crate::libs::io::map_io_err;
+use crate::libs::number::{fast_parse_f64, format_float, format_p_value};
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::{Row, TsvRow};
+use clap::*;
+use indexmap::IndexMap;
+use std::io::Write;
+
+pub fn make_subcommand() -> Command {
+    Command::new("test")
+        .about("Compares two groups with t, Mann-Whitney and Kolmogorov-Smirnov tests")
+        .after_help(include_str!("../../docs/help/test.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Input TSV file(s) to process (default: stdin)"),
+        )
+        .arg(
+            Arg::new("value")
+                .long("value")
+                .short('v')
+                .num_args(1)
+                .required(true)
+                .help("Numeric field to compare"),
+        )
+        .arg(
+            Arg::new("group")
+                .long("group")
+                .short('g')
+                .num_args(1)
+                .required(true)
+                .help("Field whose values split the rows into two groups"),
+        )
+        .arg(
+            Arg::new("levels")
+                .long("levels")
+                .num_args(1)
+                .help("The two groups to compare, in order, e.g. `control,treatment`"),
+        )
+        .arg(
+            Arg::new("tests")
+                .long("tests")
+                .short('t')
+                .num_args(1)
+                .default_value("student,welch,mann-whitney,ks")
+                .help("Tests to run: student, welch, mann-whitney, ks"),
+        )
+        .arg(
+            Arg::new("float-precision")
+                .long("float-precision")
+                .short('p')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("4")
+                .help("Precision for statistics and degrees of freedom"),
+        )
+        .args(header_args_with_columns())
+        .arg(delimiter_arg())
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+}
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+    let delimiter_char = opt_delimiter as char;
+
+    let value_spec = args.get_one::<String>("value").unwrap();
+    let group_spec = args.get_one::<String>("group").unwrap();
+    let precision = args.get_one::<usize>("float-precision").copied();
+
+    let tests = args
+        .get_one::<String>("tests")
+        .unwrap()
+        .split(',')
+        .map(|name| {
+            TwoSampleTest::from_name(name.trim()).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown test `{}`, expected student, welch, mann-whitney or ks",
+                    name.trim()
+                )
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let levels: Option<Vec<Vec<u8>>> = match args.get_one::<String>("levels") {
+        Some(spec) => {
+            let levels: Vec<Vec<u8>> =
+                spec.split(',').map(|s| s.as_bytes().to_vec()).collect();
+            if levels.len() != 2 {
+                anyhow::bail!("--levels needs exactly two groups, got {}", levels.len());
+            }
+            Some(levels)
+        }
+        None => None,
+    };
+
+    // 1-based indices of the value and group fields
+    let mut fields: Option<(usize, usize)> = None;
+    // Numeric values of each group, groups in first-seen order
+    let mut groups: IndexMap<Vec<u8>, Vec<f64>> = IndexMap::new();
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut column_names_bytes: Option<Vec<u8>> = None;
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(map_io_err)?;
+            let Some(header_info) = header_result else {
+                continue; // Empty file
+            };
+            column_names_bytes = header_info.column_names_line;
+        }
+
+        if fields.is_none() {
+            let resolver = FieldResolver::new(column_names_bytes, delimiter_char);
+            fields = Some((
+                resolver
+                    .resolve_single(value_spec, "--value")
+                    .map_err(|e| anyhow::anyhow!(e))?,
+                resolver
+                    .resolve_single(group_spec, "--group")
+                    .map_err(|e| anyhow::anyhow!(e))?,
+            ));
+        }
+        let (value_idx, group_idx) = fields.unwrap();
+
+        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+            // NaN and infinite values would spoil the means and ranks
+            let Some(value) = row
+                .get_bytes(value_idx)
+                .and_then(fast_parse_f64)
+                .filter(|v| v.is_finite())
+            else {
+                return Ok(());
+            };
+            let group = row.get_bytes(group_idx).unwrap_or(b"");
+            if let Some(group_values) = groups.get_mut(group) {
+                group_values.push(value);
+            } else {
+                groups.insert(group.to_vec(), vec![value]);
+            }
+            Ok(())
+        })?;
+    }
+
+    let levels = match levels {
+        Some(levels) => levels,
+        None if groups.len() == 2 => groups.keys().cloned().collect(),
+        None => anyhow::bail!(
+            "--group needs exactly two groups with numeric values, found {}; \
+             use --levels to pick two",
+            groups.len()
+        ),
+    };
+    let samples = levels
+        .iter()
+        .map(|level| match groups.get(level) {
+            Some(values) => Ok(values.as_slice()),
+            None => anyhow::bail!(
+                "group `{}` has no numeric values",
+                String::from_utf8_lossy(level)
+            ),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // The t-tests estimate the variance of each group
+    if let Some(test) = tests
+        .iter()
+        .find(|t| matches!(t, TwoSampleTest::Student | TwoSampleTest::Welch))
+    {
+        for (level, values) in levels.iter().zip(&samples) {
+            if values.len() < 2 {
+                anyhow::bail!(
+                    "{} needs at least two values per group, group `{}` has {}",
+                    test.name(),
+                    String::from_utf8_lossy(level),
+                    values.len()
+                );
+            }
+        }
+    }
+
+    writeln!(
+        writer,
+        "test{0}group1{0}group2{0}n1{0}n2{0}statistic{0}df{0}p_value",
+        delimiter_char
+    )?;
+    for test in tests {
+        let result = test.run(samples[0], samples[1]);
+        writer.write_all(test.name().as_bytes())?;
+        for level in &levels {
+            writer.write_all(&[opt_delimiter])?;
+            writer.write_all(level)?;
+        }
+        writeln!(
+            writer,
+            "{0}{1}{0}{2}{0}{3}{0}{4}{0}{5}",
+            delimiter_char,
+            samples[0].len(),
+            samples[1].len(),
+            format_float(result.statistic, precision),
+            result
+                .df
+                .map(|df| format_float(df, precision))
+                .unwrap_or_default(),
+            format_p_value(result.p_value)
+        )?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
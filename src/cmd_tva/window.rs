@@ -0,0 +1,220 @@
+use crate::libs::cli::{
+    build_header_config, delimiter_arg, get_delimiter, header_args_with_columns,
+};
+use crate::libs::io::map_io_err;
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::header::{write_header, Header};
+use crate::libs::tsv::key::KeyExtractor;
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::TsvRow;
+use crate::libs::window::{WindowFunc, WindowSpec, Windower};
+use clap::*;
+use std::io::Write;
+
+pub fn make_subcommand() -> Command {
+    Command::new("window")
+        .about("Appends rolling, cumulative, lag/lead and rank columns")
+        .after_help(include_str!("../../docs/help/window.md"))
+        .arg(
+            Arg::new("infiles")
+                .num_args(0..)
+                .index(1)
+                .help("Input TSV file(s) to process (default: stdin)"),
+        )
+        .arg(
+            Arg::new("window")
+                .long("window")
+                .short('w')
+                .num_args(1)
+                .action(ArgAction::Append)
+                .required(true)
+                .help(
+                    "Window function to append, e.g. rolling_mean:price:7 or lag:price",
+                ),
+        )
+        .arg(
+            Arg::new("group-by")
+                .long("group-by")
+                .short('g')
+                .num_args(1)
+                .help(
+                    "Fields that partition the rows; windows restart in each partition",
+                ),
+        )
+        .arg(
+            Arg::new("float-precision")
+                .long("float-precision")
+                .short('p')
+                .num_args(1)
+                .value_parser(value_parser!(usize))
+                .default_value("4")
+                .help("Precision for floating point numbers"),
+        )
+        .args(header_args_with_columns())
+        .arg(delimiter_arg())
+        .arg(
+            Arg::new("outfile")
+                .long("outfile")
+                .short('o')
+                .num_args(1)
+                .default_value("stdout")
+                .help("Output filename. [stdout] for screen"),
+        )
+        .arg(
+            Arg::new("line-buffered")
+                .long("line-buffered")
+                .action(ArgAction::SetTrue)
+                .help("Enable line-buffered output (flush after each line)"),
+        )
+}
+
+pub fn execute(args: &ArgMatches) -> anyhow::Result<()> {
+    let infiles: Vec<String> = match args.get_many::<String>("infiles") {
+        Some(values) => values.cloned().collect(),
+        None => vec!["stdin".to_string()],
+    };
+    let mut writer =
+        crate::libs::io::writer(args.get_one::<String>("outfile").unwrap())?;
+    let line_buffered = args.get_flag("line-buffered");
+
+    let header_config =
+        build_header_config(args, true).map_err(|e| anyhow::anyhow!(e))?;
+    let opt_delimiter = get_delimiter(args, "delimiter")?;
+    let delimiter_char = opt_delimiter as char;
+    let precision = args.get_one::<usize>("float-precision").copied();
+    let group_by_spec = args.get_one::<String>("group-by");
+
+    let specs = args
+        .get_many::<String>("window")
+        .unwrap()
+        .map(|s| WindowSpec::parse(s))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    let mut windower: Option<Windower> = None;
+    let mut extractor: Option<KeyExtractor> = None;
+    let mut header_written = false;
+
+    for input in crate::libs::io::raw_input_sources(&infiles)? {
+        let mut reader = TsvReader::with_capacity(input.reader, 512 * 1024);
+        let mut column_names_bytes: Option<Vec<u8>> = None;
+
+        if header_config.enabled {
+            let header_result = reader
+                .read_header_mode(header_config.mode)
+                .map_err(map_io_err)?;
+            let Some(header_info) = header_result else {
+                continue; // Empty file
+            };
+            column_names_bytes = header_info.column_names_line.clone();
+
+            if !header_written {
+                let header = Header::from_info(header_info, delimiter_char);
+                let names = header.column_names_list();
+                let resolver =
+                    FieldResolver::new(column_names_bytes.clone(), delimiter_char);
+                let (w, e, new_names) = setup(
+                    &specs,
+                    &resolver,
+                    names.as_deref(),
+                    group_by_spec,
+                    precision,
+                )?;
+                windower = Some(w);
+                extractor = e;
+
+                let suffix: Vec<u8> = new_names
+                    .iter()
+                    .flat_map(|name| std::iter::once(opt_delimiter).chain(name.bytes()))
+                    .collect();
+                write_header(&mut writer, &header, Some(&suffix))?;
+                header_written = true;
+            }
+        }
+
+        if windower.is_none() {
+            let resolver = FieldResolver::new(column_names_bytes, delimiter_char);
+            let (w, e, _) = setup(&specs, &resolver, None, group_by_spec, precision)?;
+            windower = Some(w);
+            extractor = e;
+        }
+        let windower = windower.as_mut().unwrap();
+
+        reader.for_each_row(opt_delimiter, |row: &TsvRow| {
+            let key = match extractor.as_mut() {
+                Some(extractor) => {
+                    match extractor.extract_from_row(row, opt_delimiter) {
+                        Ok(k) => k.into_owned(),
+                        Err(_) => Default::default(),
+                    }
+                }
+                None => Default::default(),
+            };
+            windower.push(&key, row, row.line);
+            while let Some(ready) = windower.pop_ready() {
+                write_row(&mut writer, &ready.line, &ready.values, opt_delimiter)?;
+                if line_buffered {
+                    writer.flush()?;
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    if let Some(windower) = windower.as_mut() {
+        windower.finish();
+        while let Some(ready) = windower.pop_ready() {
+            write_row(&mut writer, &ready.line, &ready.values, opt_delimiter)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+type Setup = (Windower, Option<KeyExtractor>, Vec<String>);
+
+/// Resolves the fields of the window functions and of `--group-by`.
+fn setup(
+    specs: &[WindowSpec],
+    resolver: &FieldResolver,
+    column_names: Option<&[String]>,
+    group_by_spec: Option<&String>,
+    precision: Option<usize>,
+) -> anyhow::Result<Setup> {
+    let mut funcs = Vec::new();
+    let mut names = Vec::new();
+    for spec in specs {
+        let (field, name) = spec
+            .resolve(resolver, column_names)
+            .map_err(|e| anyhow::anyhow!(e))?;
+        funcs.push(WindowFunc::new(spec.kind.clone(), field, precision));
+        names.push(name);
+    }
+
+    let extractor = match group_by_spec {
+        Some(spec) => {
+            let idxs = resolver
+                .resolve(spec)
+                .map_err(|e| anyhow::anyhow!("Error parsing group-by fields: {}", e))?;
+            Some(KeyExtractor::new(Some(idxs), false, false))
+        }
+        None => None,
+    };
+
+    Ok((Windower::new(funcs, precision), extractor, names))
+}
+
+fn write_row<W: Write + ?Sized>(
+    writer: &mut W,
+    line: &[u8],
+    values: &[String],
+    delimiter: u8,
+) -> std::io::Result<()> {
+    writer.write_all(line)?;
+    for value in values {
+        writer.write_all(&[delimiter])?;
+        writer.write_all(value.as_bytes())?;
+    }
+    writer.write_all(b"\n")
+}
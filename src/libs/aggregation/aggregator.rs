@@ -1,4 +1,7 @@
+use super::hll::HyperLogLog;
 use super::math;
+use super::moments::{CoMoments, Moments};
+use super::tdigest::TDigest;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Default)]
@@ -12,13 +15,72 @@ pub struct Aggregator {
     pub maxs: Vec<f64>,
     pub field_counts: Vec<usize>,
     pub values: Vec<Vec<f64>>, // For median/mad/quantiles
+    pub digests: Vec<TDigest>, // For approximate median/quantiles
     pub firsts: Vec<String>,
     pub lasts: Vec<String>,
     pub value_counts: Vec<HashMap<String, usize>>, // For mode/nunique/unique
+    pub hlls: Vec<HyperLogLog>,                    // For approx_nunique
+    pub moments: Vec<Moments>,                     // For skewness/kurtosis
+    pub comoments: Vec<CoMoments>,                 // For covariance/pearson
+    pub pairs: Vec<Vec<(f64, f64)>>,               // For spearman
     pub string_values: Vec<Vec<String>>,           // For collapse/rand
 }
 
 impl Aggregator {
+    /// Combines the state of `other`, built by the same `StatsProcessor` from
+    /// rows that came after those of `self`.
+    pub fn merge(&mut self, other: &Aggregator) {
+        self.count += other.count;
+        add_slots(&mut self.sums, &other.sums);
+        add_slots(&mut self.sum_sqs, &other.sum_sqs);
+        add_slots(&mut self.sum_logs, &other.sum_logs);
+        add_slots(&mut self.sum_invs, &other.sum_invs);
+        for (a, b) in self.mins.iter_mut().zip(&other.mins) {
+            *a = a.min(*b);
+        }
+        for (a, b) in self.maxs.iter_mut().zip(&other.maxs) {
+            *a = a.max(*b);
+        }
+        for (a, b) in self.field_counts.iter_mut().zip(&other.field_counts) {
+            *a += b;
+        }
+        for (a, b) in self.values.iter_mut().zip(&other.values) {
+            a.extend_from_slice(b);
+        }
+        for (a, b) in self.digests.iter_mut().zip(&other.digests) {
+            a.merge(b);
+        }
+        // `first` keeps the first non-empty value
+        for (a, b) in self.firsts.iter_mut().zip(&other.firsts) {
+            if a.is_empty() {
+                a.clone_from(b);
+            }
+        }
+        for (a, b) in self.lasts.iter_mut().zip(&other.lasts) {
+            a.clone_from(b);
+        }
+        for (a, b) in self.value_counts.iter_mut().zip(&other.value_counts) {
+            for (value, count) in b {
+                *a.entry(value.clone()).or_insert(0) += count;
+            }
+        }
+        for (a, b) in self.hlls.iter_mut().zip(&other.hlls) {
+            a.merge(b);
+        }
+        for (a, b) in self.string_values.iter_mut().zip(&other.string_values) {
+            a.extend_from_slice(b);
+        }
+        for (a, b) in self.moments.iter_mut().zip(&other.moments) {
+            a.merge(b);
+        }
+        for (a, b) in self.comoments.iter_mut().zip(&other.comoments) {
+            a.merge(b);
+        }
+        for (a, b) in self.pairs.iter_mut().zip(&other.pairs) {
+            a.extend_from_slice(b);
+        }
+    }
+
     /// Calculate quantile on a SORTED slice.
     /// This is a convenience wrapper around math::quantile.
     #[inline]
@@ -27,6 +89,12 @@ impl Aggregator {
     }
 }
 
+fn add_slots(a: &mut [f64], b: &[f64]) {
+    for (x, y) in a.iter_mut().zip(b) {
+        *x += y;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -38,6 +106,42 @@ mod tests {
         assert!(agg.sums.is_empty());
     }
 
+    #[test]
+    fn test_merge() {
+        let mut a = Aggregator {
+            count: 2,
+            sums: vec![3.0],
+            mins: vec![1.0],
+            maxs: vec![2.0],
+            values: vec![vec![1.0, 2.0]],
+            firsts: vec![String::new()],
+            lasts: vec!["x".to_string()],
+            value_counts: vec![HashMap::from([("x".to_string(), 2)])],
+            ..Default::default()
+        };
+        let b = Aggregator {
+            count: 1,
+            sums: vec![5.0],
+            mins: vec![5.0],
+            maxs: vec![5.0],
+            values: vec![vec![5.0]],
+            firsts: vec!["y".to_string()],
+            lasts: vec!["y".to_string()],
+            value_counts: vec![HashMap::from([("y".to_string(), 1)])],
+            ..Default::default()
+        };
+        a.merge(&b);
+
+        assert_eq!(a.count, 3);
+        assert_eq!(a.sums, vec![8.0]);
+        assert_eq!(a.mins, vec![1.0]);
+        assert_eq!(a.maxs, vec![5.0]);
+        assert_eq!(a.values, vec![vec![1.0, 2.0, 5.0]]);
+        assert_eq!(a.firsts, vec!["y"]);
+        assert_eq!(a.lasts, vec!["y"]);
+        assert_eq!(a.value_counts[0].len(), 2);
+    }
+
     #[test]
     fn test_calculate_quantile() {
         let data = vec![1.0, 2.0, 3.0];
@@ -0,0 +1,95 @@
+//! Most frequent values in bounded memory, for `tva summary --top`.
+//!
+//! [`FrequentItems`] is a Misra-Gries sketch with `k` counters. While a value
+//! is counted, its counter is exact; when a new value arrives and all counters
+//! are taken, every counter is decremented instead, and those reaching zero
+//! are dropped. After `n` values:
+//!
+//! * counts are exact as long as there were at most `k` distinct values;
+//! * otherwise each count is at most `n / (k + 1)` below the true one, and
+//!   every value seen more than `n / (k + 1)` times is kept.
+//!
+//! Each decrement round cancels `k + 1` occurrences, so the cost is amortized
+//! O(1) per value.
+
+use indexmap::IndexMap;
+
+#[derive(Debug, Clone)]
+pub struct FrequentItems {
+    capacity: usize,
+    /// Counters in the order values were first counted.
+    counts: IndexMap<Vec<u8>, u64>,
+}
+
+impl FrequentItems {
+    /// A sketch with `capacity` counters, at least one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counts: IndexMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, value: &[u8]) {
+        if let Some(count) = self.counts.get_mut(value) {
+            *count += 1;
+        } else if self.counts.len() < self.capacity {
+            self.counts.insert(value.to_vec(), 1);
+        } else {
+            for count in self.counts.values_mut() {
+                *count -= 1;
+            }
+            self.counts.retain(|_, count| *count > 0);
+        }
+    }
+
+    /// The `k` values with the largest counts, largest first. Ties keep the
+    /// order in which values were first counted.
+    pub fn top(&self, k: usize) -> Vec<(&[u8], u64)> {
+        let mut entries: Vec<(&[u8], u64)> = self
+            .counts
+            .iter()
+            .map(|(value, count)| (value.as_slice(), *count))
+            .collect();
+        entries.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        entries.truncate(k);
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact() {
+        let mut sketch = FrequentItems::new(3);
+        for value in ["x", "y", "y", "z", "x", "y"] {
+            sketch.add(value.as_bytes());
+        }
+        assert_eq!(
+            sketch.top(2),
+            vec![(b"y".as_slice(), 3), (b"x".as_slice(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_bounded() {
+        // A frequent value among many distinct ones
+        let mut sketch = FrequentItems::new(4);
+        let n = 1000;
+        for i in 0..n {
+            if i % 3 == 0 {
+                sketch.add(b"hot");
+            } else {
+                sketch.add(i.to_string().as_bytes());
+            }
+        }
+        assert!(sketch.counts.len() <= 4);
+
+        let (value, count) = sketch.top(1)[0];
+        assert_eq!(value, b"hot");
+        let actual = (n as u64).div_ceil(3);
+        assert!(count <= actual && count >= actual - n as u64 / 5);
+    }
+}
@@ -30,9 +30,15 @@
 //! ```
 
 pub mod aggregator;
+pub mod frequent;
+pub mod hll;
 pub mod math;
+pub mod moments;
 pub mod ops;
+pub mod partial;
 pub mod processor;
+pub mod tdigest;
+pub mod transform;
 
 #[cfg(test)]
 mod tests;
@@ -70,6 +76,7 @@ pub enum OpKind {
     First,
     Last,
     NUnique,
+    ApproxNUnique,
     Mode,
     Unique,
     Collapse,
@@ -78,9 +85,115 @@ pub enum OpKind {
     MissingCount,
     NotMissingCount,
     Quantile(f64),
+    Skewness,
+    Kurtosis,
+    /// Bivariate operations hold the index of their second field.
+    Covariance(usize),
+    Pearson(usize),
+    Spearman(usize),
 }
 
-#[derive(Debug, Clone)]
+impl OpKind {
+    /// Name used in output headers (`estimate_mean`) and partial files.
+    pub fn name(&self) -> String {
+        match self {
+            OpKind::Count => "count".to_string(),
+            OpKind::Sum => "sum".to_string(),
+            OpKind::Mean => "mean".to_string(),
+            OpKind::Min => "min".to_string(),
+            OpKind::Max => "max".to_string(),
+            OpKind::Range => "range".to_string(),
+            OpKind::Stdev => "stdev".to_string(),
+            OpKind::Variance => "variance".to_string(),
+            OpKind::CV => "cv".to_string(),
+            OpKind::GeoMean => "geomean".to_string(),
+            OpKind::HarmMean => "harmmean".to_string(),
+            OpKind::Median => "median".to_string(),
+            OpKind::Q1 => "q1".to_string(),
+            OpKind::Q3 => "q3".to_string(),
+            OpKind::IQR => "iqr".to_string(),
+            OpKind::Mad => "mad".to_string(),
+            OpKind::First => "first".to_string(),
+            OpKind::Last => "last".to_string(),
+            OpKind::NUnique => "nunique".to_string(),
+            OpKind::ApproxNUnique => "approx_nunique".to_string(),
+            OpKind::Mode => "mode".to_string(),
+            OpKind::Unique => "unique".to_string(),
+            OpKind::Collapse => "collapse".to_string(),
+            OpKind::Rand => "rand".to_string(),
+            OpKind::ModeCount => "mode_count".to_string(),
+            OpKind::MissingCount => "missing_count".to_string(),
+            OpKind::NotMissingCount => "not_missing_count".to_string(),
+            OpKind::Quantile(p) => format!("quantile_{}", p),
+            OpKind::Skewness => "skewness".to_string(),
+            OpKind::Kurtosis => "kurtosis".to_string(),
+            OpKind::Covariance(idx) => format!("covariance_{}", idx + 1),
+            OpKind::Pearson(idx) => format!("pearson_{}", idx + 1),
+            OpKind::Spearman(idx) => format!("spearman_{}", idx + 1),
+        }
+    }
+
+    /// Inverse of [`name`](OpKind::name).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let kind = match name {
+            "count" => OpKind::Count,
+            "sum" => OpKind::Sum,
+            "mean" => OpKind::Mean,
+            "min" => OpKind::Min,
+            "max" => OpKind::Max,
+            "range" => OpKind::Range,
+            "stdev" => OpKind::Stdev,
+            "variance" => OpKind::Variance,
+            "cv" => OpKind::CV,
+            "geomean" => OpKind::GeoMean,
+            "harmmean" => OpKind::HarmMean,
+            "median" => OpKind::Median,
+            "q1" => OpKind::Q1,
+            "q3" => OpKind::Q3,
+            "iqr" => OpKind::IQR,
+            "mad" => OpKind::Mad,
+            "first" => OpKind::First,
+            "last" => OpKind::Last,
+            "nunique" => OpKind::NUnique,
+            "approx_nunique" => OpKind::ApproxNUnique,
+            "mode" => OpKind::Mode,
+            "unique" => OpKind::Unique,
+            "collapse" => OpKind::Collapse,
+            "rand" => OpKind::Rand,
+            "mode_count" => OpKind::ModeCount,
+            "missing_count" => OpKind::MissingCount,
+            "not_missing_count" => OpKind::NotMissingCount,
+            "skewness" => OpKind::Skewness,
+            "kurtosis" => OpKind::Kurtosis,
+            _ => {
+                let (prefix, param) = name.rsplit_once('_')?;
+                match prefix {
+                    "quantile" => OpKind::Quantile(param.parse().ok()?),
+                    "covariance" | "pearson" | "spearman" => {
+                        let idx = param.parse::<usize>().ok()?.checked_sub(1)?;
+                        match prefix {
+                            "covariance" => OpKind::Covariance(idx),
+                            "pearson" => OpKind::Pearson(idx),
+                            _ => OpKind::Spearman(idx),
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+        };
+        Some(kind)
+    }
+
+    /// Whether the operation combines two fields.
+    pub fn is_bivariate(&self) -> bool {
+        matches!(
+            self,
+            OpKind::Covariance(_) | OpKind::Pearson(_) | OpKind::Spearman(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Operation {
     pub kind: OpKind,
     pub field_idx: Option<usize>,
@@ -93,6 +206,8 @@ pub struct StatsConfig {
     pub missing_val: Option<String>, // For replacing missing values (output formatting)
     pub missing_val_f64: Option<f64>, // For replacing missing values in calculations
     pub exclude_missing: bool, // Exclude missing (empty) fields from calculations
+    pub approx_quantiles: Option<usize>, // t-digest compression for median/quartiles/quantiles
+    pub hll_precision: u8,               // HyperLogLog precision for approx_nunique
 }
 
 impl Default for StatsConfig {
@@ -103,6 +218,8 @@ impl Default for StatsConfig {
             missing_val: None,
             missing_val_f64: None,
             exclude_missing: false,
+            approx_quantiles: None,
+            hll_precision: hll::DEFAULT_PRECISION,
         }
     }
 }
@@ -0,0 +1,291 @@
+//! Online central moments for skewness, kurtosis, covariance and correlation.
+//!
+//! Both accumulators use single-pass updates (Welford; Pébay, 2008) that stay
+//! accurate when the mean is large compared to the spread, unlike the
+//! sum-of-powers formulas, and can be merged (Chan et al.) to combine partial
+//! results.
+
+/// Count, mean and the sums of 2nd, 3rd and 4th powers of deviations from the
+/// mean of one variable.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Moments {
+    pub n: f64,
+    pub mean: f64,
+    pub m2: f64,
+    pub m3: f64,
+    pub m4: f64,
+}
+
+impl Moments {
+    pub fn push(&mut self, x: f64) {
+        let n1 = self.n;
+        self.n += 1.0;
+        let n = self.n;
+        let delta = x - self.mean;
+        let delta_n = delta / n;
+        let delta_n2 = delta_n * delta_n;
+        let term1 = delta * delta_n * n1;
+
+        self.mean += delta_n;
+        self.m4 += term1 * delta_n2 * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n2 * self.m2
+            - 4.0 * delta_n * self.m3;
+        self.m3 += term1 * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term1;
+    }
+
+    pub fn merge(&mut self, other: &Moments) {
+        if other.n == 0.0 {
+            return;
+        }
+        if self.n == 0.0 {
+            *self = *other;
+            return;
+        }
+        let (na, nb) = (self.n, other.n);
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+        let delta2 = delta * delta;
+
+        let m2 = self.m2 + other.m2 + delta2 * na * nb / n;
+        let m3 = self.m3
+            + other.m3
+            + delta2 * delta * na * nb * (na - nb) / (n * n)
+            + 3.0 * delta * (na * other.m2 - nb * self.m2) / n;
+        let m4 = self.m4
+            + other.m4
+            + delta2 * delta2 * na * nb * (na * na - na * nb + nb * nb) / (n * n * n)
+            + 6.0 * delta2 * (na * na * other.m2 + nb * nb * self.m2) / (n * n)
+            + 4.0 * delta * (na * other.m3 - nb * self.m3) / n;
+
+        self.mean += delta * nb / n;
+        self.n = n;
+        self.m2 = m2;
+        self.m3 = m3;
+        self.m4 = m4;
+    }
+
+    /// Sample skewness, adjusted for bias (`G1`, as in Excel's `SKEW` and
+    /// pandas). Needs at least three values and a non-zero spread.
+    pub fn skewness(&self) -> f64 {
+        let n = self.n;
+        if n < 3.0 || self.m2 == 0.0 {
+            return f64::NAN;
+        }
+        let g1 = n.sqrt() * self.m3 / self.m2.powf(1.5);
+        g1 * (n * (n - 1.0)).sqrt() / (n - 2.0)
+    }
+
+    /// Sample excess kurtosis, adjusted for bias (`G2`, as in Excel's `KURT`
+    /// and pandas). Needs at least four values and a non-zero spread.
+    pub fn kurtosis(&self) -> f64 {
+        let n = self.n;
+        if n < 4.0 || self.m2 == 0.0 {
+            return f64::NAN;
+        }
+        let g2 = n * self.m4 / (self.m2 * self.m2) - 3.0;
+        ((n + 1.0) * g2 + 6.0) * (n - 1.0) / ((n - 2.0) * (n - 3.0))
+    }
+}
+
+/// Count, means, sums of squared deviations and the co-moment of a pair of
+/// variables.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CoMoments {
+    pub n: f64,
+    pub mean_x: f64,
+    pub mean_y: f64,
+    pub m2_x: f64,
+    pub m2_y: f64,
+    /// Sum of `(x - mean_x) * (y - mean_y)`.
+    pub c: f64,
+}
+
+impl CoMoments {
+    pub fn push(&mut self, x: f64, y: f64) {
+        self.n += 1.0;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / self.n;
+        self.mean_y += dy / self.n;
+        // One old and one updated deviation, as in Welford's algorithm
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c += dx * (y - self.mean_y);
+    }
+
+    pub fn merge(&mut self, other: &CoMoments) {
+        if other.n == 0.0 {
+            return;
+        }
+        if self.n == 0.0 {
+            *self = *other;
+            return;
+        }
+        let (na, nb) = (self.n, other.n);
+        let n = na + nb;
+        let dx = other.mean_x - self.mean_x;
+        let dy = other.mean_y - self.mean_y;
+        let f = na * nb / n;
+
+        self.m2_x += other.m2_x + dx * dx * f;
+        self.m2_y += other.m2_y + dy * dy * f;
+        self.c += other.c + dx * dy * f;
+        self.mean_x += dx * nb / n;
+        self.mean_y += dy * nb / n;
+        self.n = n;
+    }
+
+    /// Sample covariance. Needs at least two pairs.
+    pub fn covariance(&self) -> f64 {
+        if self.n < 2.0 {
+            return f64::NAN;
+        }
+        self.c / (self.n - 1.0)
+    }
+
+    /// Pearson correlation coefficient. NaN if either variable is constant.
+    pub fn pearson(&self) -> f64 {
+        if self.n < 2.0 || self.m2_x == 0.0 || self.m2_y == 0.0 {
+            return f64::NAN;
+        }
+        (self.c / (self.m2_x * self.m2_y).sqrt()).clamp(-1.0, 1.0)
+    }
+}
+
+/// Spearman rank correlation: Pearson correlation of the ranks, with tied
+/// values sharing their average rank.
+pub fn spearman(pairs: &[(f64, f64)]) -> f64 {
+    let xs = ranks(pairs.iter().map(|p| p.0));
+    let ys = ranks(pairs.iter().map(|p| p.1));
+    let mut co = CoMoments::default();
+    for (x, y) in xs.into_iter().zip(ys) {
+        co.push(x, y);
+    }
+    co.pearson()
+}
+
+/// 1-based ranks, averaged over ties.
+pub fn ranks(values: impl Iterator<Item = f64>) -> Vec<f64> {
+    let values: Vec<f64> = values.collect();
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i + 1;
+        while j < order.len() && values[order[j]] == values[order[i]] {
+            j += 1;
+        }
+        // Positions i..j share the ranks i+1..=j
+        let rank = (i + j + 1) as f64 / 2.0;
+        for &k in &order[i..j] {
+            ranks[k] = rank;
+        }
+        i = j;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_moments() {
+        let mut m = Moments::default();
+        for x in [2.0, 8.0, 0.0, 4.0, 1.0, 9.0, 9.0, 0.0] {
+            m.push(x);
+        }
+        assert_eq!(m.n, 8.0);
+        assert!(approx(m.mean, 4.125));
+        // G1 and G2 computed from the two-pass central moments
+        assert!(approx(m.skewness(), 0.3305821804079747));
+        assert!(approx(m.kurtosis(), -2.098602258096087));
+
+        let mut few = Moments::default();
+        few.push(1.0);
+        few.push(2.0);
+        assert!(few.skewness().is_nan());
+        assert!(few.kurtosis().is_nan());
+    }
+
+    #[test]
+    fn test_moments_stable_with_offset() {
+        let mut m = Moments::default();
+        let mut shifted = Moments::default();
+        for x in [1.0, 2.0, 3.0, 4.0, 10.0] {
+            m.push(x);
+            shifted.push(x + 1e9);
+        }
+        assert!((m.skewness() - shifted.skewness()).abs() < 1e-6);
+        assert!((m.kurtosis() - shifted.kurtosis()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_moments_merge() {
+        let xs = [3.0, 1.5, 7.25, 2.0, 9.0, 4.0, 4.0, 11.0, 0.5];
+        let mut whole = Moments::default();
+        let mut a = Moments::default();
+        let mut b = Moments::default();
+        for (i, &x) in xs.iter().enumerate() {
+            whole.push(x);
+            if i < 4 {
+                a.push(x);
+            } else {
+                b.push(x);
+            }
+        }
+        a.merge(&b);
+        assert!(approx(a.mean, whole.mean));
+        assert!(approx(a.m2, whole.m2));
+        assert!(approx(a.m3, whole.m3));
+        assert!(approx(a.m4, whole.m4));
+    }
+
+    #[test]
+    fn test_comoments() {
+        let xs = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let ys = [2.0, 4.0, 5.0, 4.0, 5.0];
+        let mut co = CoMoments::default();
+        let mut a = CoMoments::default();
+        let mut b = CoMoments::default();
+        for (i, (&x, &y)) in xs.iter().zip(&ys).enumerate() {
+            co.push(x, y);
+            if i < 2 {
+                a.push(x, y);
+            } else {
+                b.push(x, y);
+            }
+        }
+        assert!(approx(co.covariance(), 1.5));
+        assert!(approx(co.pearson(), 0.7745966692414834));
+
+        a.merge(&b);
+        assert!(approx(a.covariance(), co.covariance()));
+        assert!(approx(a.pearson(), co.pearson()));
+
+        let mut flat = CoMoments::default();
+        flat.push(1.0, 3.0);
+        flat.push(2.0, 3.0);
+        assert!(flat.pearson().is_nan());
+    }
+
+    #[test]
+    fn test_spearman() {
+        assert_eq!(
+            ranks([10.0, 20.0, 20.0, 5.0].into_iter()),
+            vec![2.0, 3.5, 3.5, 1.0]
+        );
+        // Monotonic but not linear
+        let pairs = [(1.0, 1.0), (2.0, 8.0), (3.0, 27.0), (4.0, 64.0)];
+        assert!(approx(spearman(&pairs), 1.0));
+        // 1 - 6 * sum(d^2) / (n (n^2 - 1)) without ties
+        let pairs = [(1.0, 2.0), (2.0, 1.0), (3.0, 4.0), (4.0, 3.0), (5.0, 5.0)];
+        assert!(approx(spearman(&pairs), 0.8));
+    }
+}
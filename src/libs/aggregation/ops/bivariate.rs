@@ -0,0 +1,184 @@
+use crate::libs::aggregation::moments;
+use crate::libs::aggregation::ops::parse_float;
+use crate::libs::aggregation::{Aggregator, Calculator};
+use crate::libs::number::format_float;
+use crate::libs::tsv::record::Row;
+
+/// Parses both fields of a row. Rows where either value is missing are
+/// skipped, so the statistics use pairwise complete observations.
+#[inline]
+fn parse_pair(
+    row: &dyn Row,
+    field_idx: usize,
+    field2_idx: usize,
+    missing_val: Option<f64>,
+    exclude_missing: bool,
+) -> Option<(f64, f64)> {
+    let x = parse_float(row, field_idx, missing_val, exclude_missing)?;
+    let y = parse_float(row, field2_idx, missing_val, exclude_missing)?;
+    Some((x, y))
+}
+
+pub struct Covariance {
+    pub field_idx: usize,
+    pub field2_idx: usize,
+    pub comoments_slot: usize,
+    pub precision: Option<usize>,
+    pub missing_val: Option<f64>,
+    pub exclude_missing: bool,
+}
+
+impl Calculator for Covariance {
+    fn update(&self, agg: &mut Aggregator, row: &dyn Row) {
+        if let Some((x, y)) = parse_pair(
+            row,
+            self.field_idx,
+            self.field2_idx,
+            self.missing_val,
+            self.exclude_missing,
+        ) {
+            agg.comoments[self.comoments_slot].push(x, y);
+        }
+    }
+
+    fn format(&self, agg: &Aggregator) -> String {
+        format_float(
+            agg.comoments[self.comoments_slot].covariance(),
+            self.precision,
+        )
+    }
+}
+
+pub struct Pearson {
+    pub field_idx: usize,
+    pub field2_idx: usize,
+    pub comoments_slot: usize,
+    pub precision: Option<usize>,
+    pub missing_val: Option<f64>,
+    pub exclude_missing: bool,
+}
+
+impl Calculator for Pearson {
+    fn update(&self, agg: &mut Aggregator, row: &dyn Row) {
+        if let Some((x, y)) = parse_pair(
+            row,
+            self.field_idx,
+            self.field2_idx,
+            self.missing_val,
+            self.exclude_missing,
+        ) {
+            agg.comoments[self.comoments_slot].push(x, y);
+        }
+    }
+
+    fn format(&self, agg: &Aggregator) -> String {
+        format_float(agg.comoments[self.comoments_slot].pearson(), self.precision)
+    }
+}
+
+pub struct Spearman {
+    pub field_idx: usize,
+    pub field2_idx: usize,
+    pub pairs_slot: usize,
+    pub precision: Option<usize>,
+    pub missing_val: Option<f64>,
+    pub exclude_missing: bool,
+}
+
+impl Calculator for Spearman {
+    fn update(&self, agg: &mut Aggregator, row: &dyn Row) {
+        if let Some(pair) = parse_pair(
+            row,
+            self.field_idx,
+            self.field2_idx,
+            self.missing_val,
+            self.exclude_missing,
+        ) {
+            agg.pairs[self.pairs_slot].push(pair);
+        }
+    }
+
+    fn format(&self, agg: &Aggregator) -> String {
+        format_float(
+            moments::spearman(&agg.pairs[self.pairs_slot]),
+            self.precision,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::aggregation::moments::CoMoments;
+    use crate::libs::tsv::record::StrSliceRow;
+
+    fn new_agg() -> Aggregator {
+        Aggregator {
+            count: 0,
+            sums: vec![],
+            mins: vec![],
+            maxs: vec![],
+            field_counts: vec![],
+            sum_sqs: vec![],
+            sum_logs: vec![],
+            sum_invs: vec![],
+            firsts: vec![],
+            lasts: vec![],
+            string_values: vec![],
+            values: vec![],
+            digests: vec![],
+            value_counts: vec![],
+            hlls: vec![],
+            moments: vec![],
+            comoments: vec![CoMoments::default()],
+            pairs: vec![vec![]],
+        }
+    }
+
+    #[test]
+    fn test_bivariate() {
+        let cov = Covariance {
+            field_idx: 0,
+            field2_idx: 1,
+            comoments_slot: 0,
+            precision: Some(4),
+            missing_val: None,
+            exclude_missing: false,
+        };
+        let pearson = Pearson {
+            field_idx: 0,
+            field2_idx: 1,
+            comoments_slot: 0,
+            precision: Some(4),
+            missing_val: None,
+            exclude_missing: false,
+        };
+        let spearman = Spearman {
+            field_idx: 0,
+            field2_idx: 1,
+            pairs_slot: 0,
+            precision: Some(4),
+            missing_val: None,
+            exclude_missing: false,
+        };
+        let mut agg = new_agg();
+        let rows = [
+            ["1", "2"],
+            ["2", "4"],
+            ["3", "5"],
+            ["", "7"],
+            ["4", "4"],
+            ["5", "5"],
+        ];
+        for r in &rows {
+            let row = StrSliceRow { fields: r };
+            cov.update(&mut agg, &row);
+            spearman.update(&mut agg, &row);
+        }
+        // The row with an empty first field is skipped
+        assert_eq!(agg.comoments[0].n, 5.0);
+        assert_eq!(cov.format(&agg), "1.5");
+        assert_eq!(pearson.format(&agg), "0.7746");
+        assert_eq!(spearman.format(&agg), "0.7379");
+    }
+}
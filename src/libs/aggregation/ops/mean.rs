@@ -109,7 +109,12 @@ mod tests {
             lasts: vec![],
             string_values: vec![],
             values: vec![],
+            digests: vec![],
             value_counts: vec![],
+            hlls: vec![],
+            moments: vec![],
+            comoments: vec![],
+            pairs: vec![],
         }
     }
 
@@ -1,5 +1,7 @@
 pub mod basic;
+pub mod bivariate;
 pub mod mean;
+pub mod moment;
 pub mod quantile;
 pub mod set;
 pub mod text;
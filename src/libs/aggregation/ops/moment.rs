@@ -0,0 +1,103 @@
+use crate::libs::aggregation::ops::parse_float;
+use crate::libs::aggregation::{Aggregator, Calculator};
+use crate::libs::number::format_float;
+use crate::libs::tsv::record::Row;
+
+pub struct Skewness {
+    pub field_idx: usize,
+    pub moments_slot: usize,
+    pub precision: Option<usize>,
+    pub missing_val: Option<f64>,
+    pub exclude_missing: bool,
+}
+
+impl Calculator for Skewness {
+    fn update(&self, agg: &mut Aggregator, row: &dyn Row) {
+        if let Some(val) =
+            parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
+        {
+            agg.moments[self.moments_slot].push(val);
+        }
+    }
+
+    fn format(&self, agg: &Aggregator) -> String {
+        format_float(agg.moments[self.moments_slot].skewness(), self.precision)
+    }
+}
+
+pub struct Kurtosis {
+    pub field_idx: usize,
+    pub moments_slot: usize,
+    pub precision: Option<usize>,
+    pub missing_val: Option<f64>,
+    pub exclude_missing: bool,
+}
+
+impl Calculator for Kurtosis {
+    fn update(&self, agg: &mut Aggregator, row: &dyn Row) {
+        if let Some(val) =
+            parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
+        {
+            agg.moments[self.moments_slot].push(val);
+        }
+    }
+
+    fn format(&self, agg: &Aggregator) -> String {
+        format_float(agg.moments[self.moments_slot].kurtosis(), self.precision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::aggregation::moments::Moments;
+    use crate::libs::tsv::record::StrSliceRow;
+
+    fn new_agg() -> Aggregator {
+        Aggregator {
+            count: 0,
+            sums: vec![],
+            mins: vec![],
+            maxs: vec![],
+            field_counts: vec![],
+            sum_sqs: vec![],
+            sum_logs: vec![],
+            sum_invs: vec![],
+            firsts: vec![],
+            lasts: vec![],
+            string_values: vec![],
+            values: vec![],
+            digests: vec![],
+            value_counts: vec![],
+            hlls: vec![],
+            moments: vec![Moments::default()],
+            comoments: vec![],
+            pairs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_skewness_kurtosis() {
+        let skew = Skewness {
+            field_idx: 0,
+            moments_slot: 0,
+            precision: Some(4),
+            missing_val: None,
+            exclude_missing: false,
+        };
+        let kurt = Kurtosis {
+            field_idx: 0,
+            moments_slot: 0,
+            precision: Some(4),
+            missing_val: None,
+            exclude_missing: false,
+        };
+        let mut agg = new_agg();
+        for v in ["1", "2", "3", "10"] {
+            let row = StrSliceRow { fields: &[v] };
+            skew.update(&mut agg, &row);
+        }
+        assert_eq!(skew.format(&agg), "1.7636");
+        assert_eq!(kurt.format(&agg), "3.228");
+    }
+}
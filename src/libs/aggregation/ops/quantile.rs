@@ -4,9 +4,54 @@ use crate::libs::aggregation::{Aggregator, Calculator};
 use crate::libs::number::format_float;
 use crate::libs::tsv::record::Row;
 
+/// Where a quantile calculator keeps the values it has seen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantileStore {
+    /// Every value, in `Aggregator::values`. Exact.
+    Values(usize),
+    /// A t-digest in `Aggregator::digests`. Approximate, with bounded memory.
+    Digest(usize),
+}
+
+impl QuantileStore {
+    #[inline]
+    fn push(self, agg: &mut Aggregator, val: f64) {
+        match self {
+            QuantileStore::Values(slot) => agg.values[slot].push(val),
+            QuantileStore::Digest(slot) => agg.digests[slot].add(val),
+        }
+    }
+
+    /// Returns the values at each probability, or `None` if nothing was seen.
+    fn quantiles<const N: usize>(
+        self,
+        agg: &Aggregator,
+        probs: [f64; N],
+    ) -> Option<[f64; N]> {
+        match self {
+            QuantileStore::Values(slot) => {
+                let vals = &agg.values[slot];
+                if vals.is_empty() {
+                    return None;
+                }
+                let mut sorted_vals = vals.clone();
+                sorted_vals.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                Some(probs.map(|p| math::quantile(&sorted_vals, p)))
+            }
+            QuantileStore::Digest(slot) => {
+                let digest = &agg.digests[slot];
+                if digest.is_empty() {
+                    return None;
+                }
+                Some(probs.map(|p| digest.quantile(p)))
+            }
+        }
+    }
+}
+
 pub struct Quantile {
     pub field_idx: usize,
-    pub values_slot: usize,
+    pub store: QuantileStore,
     pub precision: Option<usize>,
     pub probability: f64,
     pub missing_val: Option<f64>,
@@ -18,26 +63,21 @@ impl Calculator for Quantile {
         if let Some(val) =
             parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
         {
-            agg.values[self.values_slot].push(val);
+            self.store.push(agg, val);
         }
     }
 
     fn format(&self, agg: &Aggregator) -> String {
-        let vals = &agg.values[self.values_slot];
-        if !vals.is_empty() {
-            let mut sorted_vals = vals.clone();
-            sorted_vals.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            let res = math::quantile(&sorted_vals, self.probability);
-            format_float(res, self.precision)
-        } else {
-            "nan".to_string()
+        match self.store.quantiles(agg, [self.probability]) {
+            Some([res]) => format_float(res, self.precision),
+            None => "nan".to_string(),
         }
     }
 }
 
 pub struct Median {
     pub field_idx: usize,
-    pub values_slot: usize,
+    pub store: QuantileStore,
     pub precision: Option<usize>,
     pub missing_val: Option<f64>,
     pub exclude_missing: bool,
@@ -48,26 +88,21 @@ impl Calculator for Median {
         if let Some(val) =
             parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
         {
-            agg.values[self.values_slot].push(val);
+            self.store.push(agg, val);
         }
     }
 
     fn format(&self, agg: &Aggregator) -> String {
-        let vals = &agg.values[self.values_slot];
-        if !vals.is_empty() {
-            let mut sorted_vals = vals.clone();
-            sorted_vals.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            let res = math::quantile(&sorted_vals, 0.5);
-            format_float(res, self.precision)
-        } else {
-            "nan".to_string()
+        match self.store.quantiles(agg, [0.5]) {
+            Some([res]) => format_float(res, self.precision),
+            None => "nan".to_string(),
         }
     }
 }
 
 pub struct Q1 {
     pub field_idx: usize,
-    pub values_slot: usize,
+    pub store: QuantileStore,
     pub precision: Option<usize>,
     pub missing_val: Option<f64>,
     pub exclude_missing: bool,
@@ -78,26 +113,21 @@ impl Calculator for Q1 {
         if let Some(val) =
             parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
         {
-            agg.values[self.values_slot].push(val);
+            self.store.push(agg, val);
         }
     }
 
     fn format(&self, agg: &Aggregator) -> String {
-        let vals = &agg.values[self.values_slot];
-        if !vals.is_empty() {
-            let mut sorted_vals = vals.clone();
-            sorted_vals.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            let res = math::quantile(&sorted_vals, 0.25);
-            format_float(res, self.precision)
-        } else {
-            "nan".to_string()
+        match self.store.quantiles(agg, [0.25]) {
+            Some([res]) => format_float(res, self.precision),
+            None => "nan".to_string(),
         }
     }
 }
 
 pub struct Q3 {
     pub field_idx: usize,
-    pub values_slot: usize,
+    pub store: QuantileStore,
     pub precision: Option<usize>,
     pub missing_val: Option<f64>,
     pub exclude_missing: bool,
@@ -108,26 +138,21 @@ impl Calculator for Q3 {
         if let Some(val) =
             parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
         {
-            agg.values[self.values_slot].push(val);
+            self.store.push(agg, val);
         }
     }
 
     fn format(&self, agg: &Aggregator) -> String {
-        let vals = &agg.values[self.values_slot];
-        if !vals.is_empty() {
-            let mut sorted_vals = vals.clone();
-            sorted_vals.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            let res = math::quantile(&sorted_vals, 0.75);
-            format_float(res, self.precision)
-        } else {
-            "nan".to_string()
+        match self.store.quantiles(agg, [0.75]) {
+            Some([res]) => format_float(res, self.precision),
+            None => "nan".to_string(),
         }
     }
 }
 
 pub struct IQR {
     pub field_idx: usize,
-    pub values_slot: usize,
+    pub store: QuantileStore,
     pub precision: Option<usize>,
     pub missing_val: Option<f64>,
     pub exclude_missing: bool,
@@ -138,21 +163,14 @@ impl Calculator for IQR {
         if let Some(val) =
             parse_float(row, self.field_idx, self.missing_val, self.exclude_missing)
         {
-            agg.values[self.values_slot].push(val);
+            self.store.push(agg, val);
         }
     }
 
     fn format(&self, agg: &Aggregator) -> String {
-        let vals = &agg.values[self.values_slot];
-        if !vals.is_empty() {
-            let mut sorted_vals = vals.clone();
-            sorted_vals.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            let q1 = math::quantile(&sorted_vals, 0.25);
-            let q3 = math::quantile(&sorted_vals, 0.75);
-            let res = q3 - q1;
-            format_float(res, self.precision)
-        } else {
-            "nan".to_string()
+        match self.store.quantiles(agg, [0.25, 0.75]) {
+            Some([q1, q3]) => format_float(q3 - q1, self.precision),
+            None => "nan".to_string(),
         }
     }
 }
@@ -190,6 +208,7 @@ impl Calculator for Mad {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::libs::aggregation::tdigest::TDigest;
     use crate::libs::tsv::record::StrSliceRow;
 
     // Helper to create a dummy aggregator
@@ -207,7 +226,12 @@ mod tests {
             lasts: vec![],
             string_values: vec![],
             values: vec![vec![]], // One slot for values
+            digests: vec![],
             value_counts: vec![],
+            hlls: vec![],
+            moments: vec![],
+            comoments: vec![],
+            pairs: vec![],
         }
     }
 
@@ -216,7 +240,7 @@ mod tests {
         let mut agg = new_agg();
         let calc = Median {
             field_idx: 0,
-            values_slot: 0,
+            store: QuantileStore::Values(0),
             precision: None,
             missing_val: None,
             exclude_missing: false,
@@ -235,7 +259,7 @@ mod tests {
         let mut agg = new_agg();
         let calc = Quantile {
             field_idx: 0,
-            values_slot: 0,
+            store: QuantileStore::Values(0),
             precision: None,
             probability: 0.9,
             missing_val: None,
@@ -260,7 +284,7 @@ mod tests {
         let mut agg = new_agg();
         let calc = IQR {
             field_idx: 0,
-            values_slot: 0,
+            store: QuantileStore::Values(0),
             precision: None,
             missing_val: None,
             exclude_missing: false,
@@ -318,11 +342,40 @@ mod tests {
         let agg = new_agg();
         let calc = Median {
             field_idx: 0,
-            values_slot: 0,
+            store: QuantileStore::Values(0),
             precision: None,
             missing_val: None,
             exclude_missing: false,
         };
         assert_eq!(calc.format(&agg), "nan");
     }
+
+    #[test]
+    fn test_digest_store() {
+        let mut agg = new_agg();
+        agg.digests = vec![TDigest::new(100)];
+        let median = Median {
+            field_idx: 0,
+            store: QuantileStore::Digest(0),
+            precision: None,
+            missing_val: None,
+            exclude_missing: false,
+        };
+        let iqr = IQR {
+            field_idx: 0,
+            store: QuantileStore::Digest(0),
+            precision: None,
+            missing_val: None,
+            exclude_missing: false,
+        };
+        assert_eq!(median.format(&agg), "nan");
+
+        // Shares the slot for the test only; 0, 10, 20, 30, 40
+        for v in ["0", "10", "20", "30", "40"] {
+            median.update(&mut agg, &StrSliceRow { fields: &[v] });
+        }
+        assert!(agg.values[0].is_empty());
+        assert_eq!(median.format(&agg), "20");
+        assert_eq!(iqr.format(&agg), "20");
+    }
 }
@@ -20,6 +20,22 @@ impl Calculator for NUnique {
     }
 }
 
+pub struct ApproxNUnique {
+    pub field_idx: usize,
+    pub hll_slot: usize,
+}
+
+impl Calculator for ApproxNUnique {
+    fn update(&self, agg: &mut Aggregator, row: &dyn Row) {
+        let val = row.get_bytes(self.field_idx + 1).unwrap_or(b"");
+        agg.hlls[self.hll_slot].add(val);
+    }
+
+    fn format(&self, agg: &Aggregator) -> String {
+        format!("{}", agg.hlls[self.hll_slot].estimate().round() as u64)
+    }
+}
+
 pub struct Mode {
     pub field_idx: usize,
     pub value_counts_slot: usize,
@@ -120,7 +136,12 @@ mod tests {
             lasts: vec![],
             string_values: vec![],
             values: vec![],
+            digests: vec![],
             value_counts: vec![HashMap::new()], // One slot
+            hlls: vec![],
+            moments: vec![],
+            comoments: vec![],
+            pairs: vec![],
         }
     }
 
@@ -99,7 +99,12 @@ mod tests {
             lasts: vec!["".to_string()],  // One slot initialized
             string_values: vec![vec![]],  // One slot for values
             values: vec![],
+            digests: vec![],
             value_counts: vec![],
+            hlls: vec![],
+            moments: vec![],
+            comoments: vec![],
+            pairs: vec![],
         }
     }
 
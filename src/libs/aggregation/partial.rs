@@ -0,0 +1,464 @@
+//! Partial aggregates for `tva stats --partial` and `--merge-partials`.
+//!
+//! A partial file records the [`Aggregator`] state of every group, so that
+//! the results of several runs can be combined as if the inputs had been
+//! processed in one pass:
+//!
+//! ```text
+//! #tva-stats-partial  ops=mean@3,median@3  grouped=1  compression=-  hll=14
+//! #header  variable  estimate_mean  estimate_median
+//! income   5         138176         5                24476,27040,...
+//! ```
+//!
+//! (Columns are separated by tabs.)
+//!
+//! - The first line holds the settings that determine the aggregator layout
+//!   ([`PartialMeta`]); every file being merged must have the same one.
+//! - An optional `#header` line holds the output header of the original run.
+//! - Every other line is a group: its key (empty without `--group-by`),
+//!   followed by one column per aggregator slot (see [`encode_state`]).
+//!
+//! Strings are percent-encoded (`%`, tab, newline, `,` and `=`), so they
+//! never clash with the separators.
+
+use super::aggregator::Aggregator;
+use super::hll::HyperLogLog;
+use super::moments::{CoMoments, Moments};
+use super::tdigest::TDigest;
+use super::{OpKind, Operation};
+use std::borrow::Cow;
+
+/// First field of the first line of a partial file.
+pub const MAGIC: &str = "#tva-stats-partial";
+
+/// First field of the optional header line.
+pub const HEADER_PREFIX: &str = "#header";
+
+/// Settings that fix the layout of the aggregator state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialMeta {
+    pub ops: Vec<Operation>,
+    pub grouped: bool,
+    pub approx_quantiles: Option<usize>,
+    pub hll_precision: u8,
+}
+
+impl PartialMeta {
+    pub fn to_line(&self) -> String {
+        let ops: Vec<String> = self
+            .ops
+            .iter()
+            .map(|op| match op.field_idx {
+                Some(idx) => format!("{}@{}", op.kind.name(), idx + 1),
+                None => op.kind.name(),
+            })
+            .collect();
+        let compression = self
+            .approx_quantiles
+            .map_or("-".to_string(), |c| c.to_string());
+        format!(
+            "{}\tops={}\tgrouped={}\tcompression={}\thll={}",
+            MAGIC,
+            ops.join(","),
+            self.grouped as u8,
+            compression,
+            self.hll_precision
+        )
+    }
+
+    /// Parses the first line of a partial file.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut fields = line.split('\t');
+        if fields.next() != Some(MAGIC) {
+            return Err("not a tva stats partial file".to_string());
+        }
+
+        let mut meta = PartialMeta {
+            ops: Vec::new(),
+            grouped: false,
+            approx_quantiles: None,
+            hll_precision: super::hll::DEFAULT_PRECISION,
+        };
+        for field in fields {
+            let invalid = || format!("invalid partial setting `{}`", field);
+            let (name, value) = field.split_once('=').ok_or_else(invalid)?;
+            match name {
+                "ops" => {
+                    for op in value.split(',').filter(|op| !op.is_empty()) {
+                        let (kind, field_idx) = match op.split_once('@') {
+                            Some((kind, idx)) => {
+                                let idx: usize = idx.parse().map_err(|_| invalid())?;
+                                (kind, Some(idx.checked_sub(1).ok_or_else(invalid)?))
+                            }
+                            None => (op, None),
+                        };
+                        let kind = OpKind::from_name(kind).ok_or_else(invalid)?;
+                        meta.ops.push(Operation { kind, field_idx });
+                    }
+                }
+                "grouped" => meta.grouped = value == "1",
+                "compression" => {
+                    meta.approx_quantiles = match value {
+                        "-" => None,
+                        _ => Some(value.parse().map_err(|_| invalid())?),
+                    }
+                }
+                "hll" => meta.hll_precision = value.parse().map_err(|_| invalid())?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(meta)
+    }
+}
+
+/// Percent-encodes the characters used as separators in partial files.
+pub fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(['%', '\t', '\n', '\r', ',', '=']) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            '\t' => out.push_str("%09"),
+            '\n' => out.push_str("%0A"),
+            '\r' => out.push_str("%0D"),
+            ',' => out.push_str("%2C"),
+            '=' => out.push_str("%3D"),
+            _ => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Inverse of [`escape`].
+pub fn unescape(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('%') {
+        out.push_str(&rest[..pos]);
+        let code = rest.get(pos + 1..pos + 3)?;
+        out.push(u8::from_str_radix(code, 16).ok()? as char);
+        rest = &rest[pos + 3..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+/// Serialises an aggregator, one column per slot: `count`, then the slots of
+/// `sums`, `sum_sqs`, `sum_logs`, `sum_invs`, `mins`, `maxs`, `field_counts`,
+/// `values`, `digests`, `firsts`, `lasts`, `value_counts`, `hlls`, `moments`,
+/// `comoments`, `pairs` and `string_values`, in that order.
+pub fn encode_state(agg: &Aggregator) -> Vec<String> {
+    let mut cols = vec![agg.count.to_string()];
+    for slots in [
+        &agg.sums,
+        &agg.sum_sqs,
+        &agg.sum_logs,
+        &agg.sum_invs,
+        &agg.mins,
+        &agg.maxs,
+    ] {
+        cols.extend(slots.iter().map(|v| v.to_string()));
+    }
+    cols.extend(agg.field_counts.iter().map(|v| v.to_string()));
+    cols.extend(agg.values.iter().map(|vals| {
+        vals.iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }));
+    cols.extend(agg.digests.iter().map(|d| d.encode()));
+    cols.extend(agg.firsts.iter().map(|s| escape(s).into_owned()));
+    cols.extend(agg.lasts.iter().map(|s| escape(s).into_owned()));
+    cols.extend(agg.value_counts.iter().map(|counts| {
+        // Sorted, so that partial files are reproducible
+        let mut entries: Vec<_> = counts.iter().collect();
+        entries.sort();
+        entries
+            .into_iter()
+            .map(|(value, count)| format!("{}={}", escape(value), count))
+            .collect::<Vec<_>>()
+            .join(",")
+    }));
+    cols.extend(agg.hlls.iter().map(|h| h.encode()));
+    cols.extend(
+        agg.moments
+            .iter()
+            .map(|m| format!("{},{},{},{},{}", m.n, m.mean, m.m2, m.m3, m.m4)),
+    );
+    cols.extend(agg.comoments.iter().map(|c| {
+        format!(
+            "{},{},{},{},{},{}",
+            c.n, c.mean_x, c.mean_y, c.m2_x, c.m2_y, c.c
+        )
+    }));
+    cols.extend(agg.pairs.iter().map(|pairs| {
+        pairs
+            .iter()
+            .map(|(x, y)| format!("{}:{}", x, y))
+            .collect::<Vec<_>>()
+            .join(",")
+    }));
+    // Prefixed with the length, as "" and [""] would look the same
+    cols.extend(agg.string_values.iter().map(|vals| {
+        let items: Vec<Cow<str>> = vals.iter().map(|s| escape(s)).collect();
+        format!("{}:{}", vals.len(), items.join(","))
+    }));
+    cols
+}
+
+/// Fills `agg`, created by the processor the partial file was written with,
+/// from the columns written by [`encode_state`].
+pub fn decode_state(cols: &[&str], agg: &mut Aggregator) -> Result<(), String> {
+    let expected = 1
+        + agg.sums.len()
+        + agg.sum_sqs.len()
+        + agg.sum_logs.len()
+        + agg.sum_invs.len()
+        + agg.mins.len()
+        + agg.maxs.len()
+        + agg.field_counts.len()
+        + agg.values.len()
+        + agg.digests.len()
+        + agg.firsts.len()
+        + agg.lasts.len()
+        + agg.value_counts.len()
+        + agg.hlls.len()
+        + agg.moments.len()
+        + agg.comoments.len()
+        + agg.pairs.len()
+        + agg.string_values.len();
+    if cols.len() != expected {
+        return Err(format!(
+            "expected {} state columns, got {}",
+            expected,
+            cols.len()
+        ));
+    }
+
+    let mut iter = cols.iter().copied().enumerate();
+    let mut next = || {
+        let (i, col) = iter.next().unwrap();
+        (col, move || {
+            format!("invalid state `{}` in column {}", col, i + 1)
+        })
+    };
+
+    let (col, invalid) = next();
+    agg.count = col.parse().map_err(|_| invalid())?;
+    for slots in [
+        &mut agg.sums,
+        &mut agg.sum_sqs,
+        &mut agg.sum_logs,
+        &mut agg.sum_invs,
+        &mut agg.mins,
+        &mut agg.maxs,
+    ] {
+        for slot in slots.iter_mut() {
+            let (col, invalid) = next();
+            *slot = col.parse().map_err(|_| invalid())?;
+        }
+    }
+    for slot in agg.field_counts.iter_mut() {
+        let (col, invalid) = next();
+        *slot = col.parse().map_err(|_| invalid())?;
+    }
+    for slot in agg.values.iter_mut() {
+        let (col, invalid) = next();
+        *slot = col
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+    }
+    for slot in agg.digests.iter_mut() {
+        let (col, invalid) = next();
+        *slot = TDigest::decode(col, slot.compression()).ok_or_else(invalid)?;
+    }
+    for slot in agg.firsts.iter_mut().chain(agg.lasts.iter_mut()) {
+        let (col, invalid) = next();
+        *slot = unescape(col).ok_or_else(invalid)?;
+    }
+    for slot in agg.value_counts.iter_mut() {
+        let (col, invalid) = next();
+        slot.clear();
+        for entry in col.split(',').filter(|e| !e.is_empty()) {
+            let (value, count) = entry.rsplit_once('=').ok_or_else(invalid)?;
+            let value = unescape(value).ok_or_else(invalid)?;
+            slot.insert(value, count.parse().map_err(|_| invalid())?);
+        }
+    }
+    for slot in agg.hlls.iter_mut() {
+        let (col, invalid) = next();
+        *slot = HyperLogLog::decode(col, slot.precision()).ok_or_else(invalid)?;
+    }
+    for slot in agg.moments.iter_mut() {
+        let (col, invalid) = next();
+        let [n, mean, m2, m3, m4] = parse_floats(col).ok_or_else(invalid)?;
+        *slot = Moments {
+            n,
+            mean,
+            m2,
+            m3,
+            m4,
+        };
+    }
+    for slot in agg.comoments.iter_mut() {
+        let (col, invalid) = next();
+        let [n, mean_x, mean_y, m2_x, m2_y, c] =
+            parse_floats(col).ok_or_else(invalid)?;
+        *slot = CoMoments {
+            n,
+            mean_x,
+            mean_y,
+            m2_x,
+            m2_y,
+            c,
+        };
+    }
+    for slot in agg.pairs.iter_mut() {
+        let (col, invalid) = next();
+        slot.clear();
+        for pair in col.split(',').filter(|p| !p.is_empty()) {
+            let (x, y) = pair.split_once(':').ok_or_else(invalid)?;
+            let x = x.parse().map_err(|_| invalid())?;
+            let y = y.parse().map_err(|_| invalid())?;
+            slot.push((x, y));
+        }
+    }
+    for slot in agg.string_values.iter_mut() {
+        let (col, invalid) = next();
+        let (len, items) = col.split_once(':').ok_or_else(invalid)?;
+        let len: usize = len.parse().map_err(|_| invalid())?;
+        *slot = if len == 0 {
+            Vec::new()
+        } else {
+            items
+                .split(',')
+                .map(unescape)
+                .collect::<Option<_>>()
+                .ok_or_else(invalid)?
+        };
+        if slot.len() != len {
+            return Err(invalid());
+        }
+    }
+    Ok(())
+}
+
+/// Parses exactly `N` comma-separated floats.
+fn parse_floats<const N: usize>(col: &str) -> Option<[f64; N]> {
+    let mut out = [0.0; N];
+    let mut parts = col.split(',');
+    for slot in out.iter_mut() {
+        *slot = parts.next()?.parse().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::aggregation::{StatsConfig, StatsProcessor};
+    use crate::libs::tsv::record::StrSliceRow;
+
+    fn all_ops() -> Vec<Operation> {
+        [
+            OpKind::Count,
+            OpKind::Mean,
+            OpKind::Range,
+            OpKind::Stdev,
+            OpKind::GeoMean,
+            OpKind::HarmMean,
+            OpKind::Median,
+            OpKind::First,
+            OpKind::Last,
+            OpKind::Unique,
+            OpKind::ApproxNUnique,
+            OpKind::Skewness,
+            OpKind::Pearson(0),
+            OpKind::Spearman(0),
+            OpKind::Collapse,
+        ]
+        .into_iter()
+        .map(|kind| Operation {
+            kind,
+            field_idx: if kind == OpKind::Count { None } else { Some(0) },
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a,b=c%d\te"), "a%2Cb%3Dc%25d%09e");
+        assert_eq!(unescape("a%2Cb%3Dc%25d%09e").unwrap(), "a,b=c%d\te");
+        assert!(unescape("bad%2").is_none());
+    }
+
+    #[test]
+    fn test_meta_round_trip() {
+        let meta = PartialMeta {
+            ops: vec![
+                Operation {
+                    kind: OpKind::Count,
+                    field_idx: None,
+                },
+                Operation {
+                    kind: OpKind::Quantile(0.9),
+                    field_idx: Some(2),
+                },
+            ],
+            grouped: true,
+            approx_quantiles: Some(50),
+            hll_precision: 12,
+        };
+        let line = meta.to_line();
+        assert_eq!(
+            line,
+            "#tva-stats-partial\tops=count,quantile_0.9@3\tgrouped=1\tcompression=50\thll=12"
+        );
+        assert_eq!(PartialMeta::parse(&line).unwrap(), meta);
+
+        assert!(PartialMeta::parse("value\t1").is_err());
+        assert!(PartialMeta::parse("#tva-stats-partial\tops=bogus@1").is_err());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let processor = StatsProcessor::new(all_ops(), StatsConfig::default());
+        let mut agg = processor.create_aggregator();
+        for v in ["", "3", "1,5", "2=x", "4", "7.5", "-1"] {
+            processor.update(&mut agg, &StrSliceRow { fields: &[v] });
+        }
+
+        let cols = encode_state(&agg);
+        let cols: Vec<&str> = cols.iter().map(|s| s.as_str()).collect();
+        let mut decoded = processor.create_aggregator();
+        decode_state(&cols, &mut decoded).unwrap();
+        assert_eq!(
+            processor.format_results(&decoded),
+            processor.format_results(&agg)
+        );
+
+        assert!(decode_state(&cols[1..], &mut decoded).is_err());
+    }
+
+    #[test]
+    fn test_empty_strings_survive() {
+        let processor = StatsProcessor::new(all_ops(), StatsConfig::default());
+        let mut agg = processor.create_aggregator();
+        processor.update(&mut agg, &StrSliceRow { fields: &[""] });
+
+        let cols = encode_state(&agg);
+        let cols: Vec<&str> = cols.iter().map(|s| s.as_str()).collect();
+        let mut decoded = processor.create_aggregator();
+        decode_state(&cols, &mut decoded).unwrap();
+        assert_eq!(decoded.string_values, vec![vec![String::new()]]);
+    }
+}
@@ -1,5 +1,9 @@
 use super::aggregator::Aggregator;
+use super::hll::HyperLogLog;
+use super::moments::{CoMoments, Moments};
+use super::ops::quantile::QuantileStore;
 use super::ops::*;
+use super::tdigest::{TDigest, DEFAULT_COMPRESSION};
 use super::Calculator;
 use super::{OpKind, Operation, StatsConfig};
 use crate::libs::tsv::record::Row;
@@ -17,9 +21,16 @@ pub struct StatsProcessor {
     num_maxs: usize,
     num_counts: usize,
     num_values: usize,
+    num_digests: usize,
+    compression: usize,
     num_firsts: usize,
     num_lasts: usize,
     num_value_counts: usize,
+    num_hlls: usize,
+    hll_precision: u8,
+    num_moments: usize,
+    num_comoments: usize,
+    num_pairs: usize,
     num_string_values: usize,
 }
 
@@ -40,9 +51,14 @@ impl StatsProcessor {
         let mut num_maxs = 0;
         let mut num_counts = 0;
         let mut num_values = 0;
+        let mut num_digests = 0;
         let mut num_firsts = 0;
         let mut num_lasts = 0;
         let mut num_value_counts = 0;
+        let mut num_hlls = 0;
+        let mut num_moments = 0;
+        let mut num_comoments = 0;
+        let mut num_pairs = 0;
         let mut num_string_values = 0;
 
         for op in ops {
@@ -212,11 +228,14 @@ impl StatsProcessor {
                 }
                 OpKind::Median => {
                     if let Some(idx) = op.field_idx {
-                        let slot = num_values;
-                        num_values += 1;
+                        let store = quantile_store(
+                            config.approx_quantiles,
+                            &mut num_values,
+                            &mut num_digests,
+                        );
                         calculators.push(Box::new(quantile::Median {
                             field_idx: idx,
-                            values_slot: slot,
+                            store,
                             precision: config.precision,
                             missing_val: config.missing_val_f64,
                             exclude_missing: config.exclude_missing,
@@ -225,11 +244,14 @@ impl StatsProcessor {
                 }
                 OpKind::Q1 => {
                     if let Some(idx) = op.field_idx {
-                        let slot = num_values;
-                        num_values += 1;
+                        let store = quantile_store(
+                            config.approx_quantiles,
+                            &mut num_values,
+                            &mut num_digests,
+                        );
                         calculators.push(Box::new(quantile::Q1 {
                             field_idx: idx,
-                            values_slot: slot,
+                            store,
                             precision: config.precision,
                             missing_val: config.missing_val_f64,
                             exclude_missing: config.exclude_missing,
@@ -238,11 +260,14 @@ impl StatsProcessor {
                 }
                 OpKind::Q3 => {
                     if let Some(idx) = op.field_idx {
-                        let slot = num_values;
-                        num_values += 1;
+                        let store = quantile_store(
+                            config.approx_quantiles,
+                            &mut num_values,
+                            &mut num_digests,
+                        );
                         calculators.push(Box::new(quantile::Q3 {
                             field_idx: idx,
-                            values_slot: slot,
+                            store,
                             precision: config.precision,
                             missing_val: config.missing_val_f64,
                             exclude_missing: config.exclude_missing,
@@ -251,11 +276,14 @@ impl StatsProcessor {
                 }
                 OpKind::IQR => {
                     if let Some(idx) = op.field_idx {
-                        let slot = num_values;
-                        num_values += 1;
+                        let store = quantile_store(
+                            config.approx_quantiles,
+                            &mut num_values,
+                            &mut num_digests,
+                        );
                         calculators.push(Box::new(quantile::IQR {
                             field_idx: idx,
-                            values_slot: slot,
+                            store,
                             precision: config.precision,
                             missing_val: config.missing_val_f64,
                             exclude_missing: config.exclude_missing,
@@ -305,6 +333,16 @@ impl StatsProcessor {
                         }));
                     }
                 }
+                OpKind::ApproxNUnique => {
+                    if let Some(idx) = op.field_idx {
+                        let slot = num_hlls;
+                        num_hlls += 1;
+                        calculators.push(Box::new(set::ApproxNUnique {
+                            field_idx: idx,
+                            hll_slot: slot,
+                        }));
+                    }
+                }
                 OpKind::Mode => {
                     if let Some(idx) = op.field_idx {
                         let slot = num_value_counts;
@@ -379,11 +417,14 @@ impl StatsProcessor {
                 }
                 OpKind::Quantile(p) => {
                     if let Some(idx) = op.field_idx {
-                        let slot = num_values;
-                        num_values += 1;
+                        let store = quantile_store(
+                            config.approx_quantiles,
+                            &mut num_values,
+                            &mut num_digests,
+                        );
                         calculators.push(Box::new(quantile::Quantile {
                             field_idx: idx,
-                            values_slot: slot,
+                            store,
                             precision: config.precision,
                             probability: p,
                             missing_val: config.missing_val_f64,
@@ -391,6 +432,74 @@ impl StatsProcessor {
                         }));
                     }
                 }
+                OpKind::Skewness => {
+                    if let Some(idx) = op.field_idx {
+                        let slot = num_moments;
+                        num_moments += 1;
+                        calculators.push(Box::new(moment::Skewness {
+                            field_idx: idx,
+                            moments_slot: slot,
+                            precision: config.precision,
+                            missing_val: config.missing_val_f64,
+                            exclude_missing: config.exclude_missing,
+                        }));
+                    }
+                }
+                OpKind::Kurtosis => {
+                    if let Some(idx) = op.field_idx {
+                        let slot = num_moments;
+                        num_moments += 1;
+                        calculators.push(Box::new(moment::Kurtosis {
+                            field_idx: idx,
+                            moments_slot: slot,
+                            precision: config.precision,
+                            missing_val: config.missing_val_f64,
+                            exclude_missing: config.exclude_missing,
+                        }));
+                    }
+                }
+                OpKind::Covariance(idx2) => {
+                    if let Some(idx) = op.field_idx {
+                        let slot = num_comoments;
+                        num_comoments += 1;
+                        calculators.push(Box::new(bivariate::Covariance {
+                            field_idx: idx,
+                            field2_idx: idx2,
+                            comoments_slot: slot,
+                            precision: config.precision,
+                            missing_val: config.missing_val_f64,
+                            exclude_missing: config.exclude_missing,
+                        }));
+                    }
+                }
+                OpKind::Pearson(idx2) => {
+                    if let Some(idx) = op.field_idx {
+                        let slot = num_comoments;
+                        num_comoments += 1;
+                        calculators.push(Box::new(bivariate::Pearson {
+                            field_idx: idx,
+                            field2_idx: idx2,
+                            comoments_slot: slot,
+                            precision: config.precision,
+                            missing_val: config.missing_val_f64,
+                            exclude_missing: config.exclude_missing,
+                        }));
+                    }
+                }
+                OpKind::Spearman(idx2) => {
+                    if let Some(idx) = op.field_idx {
+                        let slot = num_pairs;
+                        num_pairs += 1;
+                        calculators.push(Box::new(bivariate::Spearman {
+                            field_idx: idx,
+                            field2_idx: idx2,
+                            pairs_slot: slot,
+                            precision: config.precision,
+                            missing_val: config.missing_val_f64,
+                            exclude_missing: config.exclude_missing,
+                        }));
+                    }
+                }
             }
         }
 
@@ -404,9 +513,16 @@ impl StatsProcessor {
             num_maxs,
             num_counts,
             num_values,
+            num_digests,
+            compression: config.approx_quantiles.unwrap_or(DEFAULT_COMPRESSION),
             num_firsts,
             num_lasts,
             num_value_counts,
+            num_hlls,
+            hll_precision: config.hll_precision,
+            num_moments,
+            num_comoments,
+            num_pairs,
             num_string_values,
         }
     }
@@ -422,9 +538,14 @@ impl StatsProcessor {
             maxs: vec![f64::NEG_INFINITY; self.num_maxs],
             field_counts: vec![0; self.num_counts],
             values: vec![Vec::new(); self.num_values],
+            digests: vec![TDigest::new(self.compression); self.num_digests],
             firsts: vec![String::new(); self.num_firsts],
             lasts: vec![String::new(); self.num_lasts],
             value_counts: vec![HashMap::new(); self.num_value_counts],
+            hlls: vec![HyperLogLog::new(self.hll_precision); self.num_hlls],
+            moments: vec![Moments::default(); self.num_moments],
+            comoments: vec![CoMoments::default(); self.num_comoments],
+            pairs: vec![Vec::new(); self.num_pairs],
             string_values: vec![Vec::new(); self.num_string_values],
         }
     }
@@ -441,6 +562,22 @@ impl StatsProcessor {
     }
 }
 
+/// Allocates a slot for a quantile-like operation: a t-digest when
+/// approximation is enabled, the full value list otherwise.
+fn quantile_store(
+    approx: Option<usize>,
+    num_values: &mut usize,
+    num_digests: &mut usize,
+) -> QuantileStore {
+    if approx.is_some() {
+        *num_digests += 1;
+        QuantileStore::Digest(*num_digests - 1)
+    } else {
+        *num_values += 1;
+        QuantileStore::Values(*num_values - 1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -572,4 +709,70 @@ mod tests {
         assert_eq!(processor.num_value_counts, 1);
         assert_eq!(processor.num_string_values, 1);
     }
+
+    #[test]
+    fn test_processor_approx_quantiles() {
+        let ops = vec![
+            Operation {
+                kind: OpKind::Median,
+                field_idx: Some(0),
+            },
+            Operation {
+                kind: OpKind::Quantile(0.9),
+                field_idx: Some(0),
+            },
+            Operation {
+                kind: OpKind::Mad,
+                field_idx: Some(0),
+            }, // always exact
+        ];
+        let config = StatsConfig {
+            approx_quantiles: Some(50),
+            ..StatsConfig::default()
+        };
+        let processor = StatsProcessor::new(ops, config);
+
+        assert_eq!(processor.num_digests, 2);
+        assert_eq!(processor.num_values, 1);
+
+        let agg = processor.create_aggregator();
+        assert_eq!(agg.digests.len(), 2);
+        assert_eq!(agg.digests[0].compression(), 50);
+    }
+
+    #[test]
+    fn test_processor_moments() {
+        let ops = vec![
+            Operation {
+                kind: OpKind::Skewness,
+                field_idx: Some(0),
+            },
+            Operation {
+                kind: OpKind::Pearson(1),
+                field_idx: Some(0),
+            },
+            Operation {
+                kind: OpKind::Covariance(1),
+                field_idx: Some(0),
+            },
+            Operation {
+                kind: OpKind::Spearman(1),
+                field_idx: Some(0),
+            },
+        ];
+        let processor = StatsProcessor::new(ops, StatsConfig::default());
+        assert_eq!(processor.num_moments, 1);
+        assert_eq!(processor.num_comoments, 2);
+        assert_eq!(processor.num_pairs, 1);
+
+        let mut agg = processor.create_aggregator();
+        for (x, y) in [("1", "10"), ("2", "20"), ("3", "30"), ("10", "40")] {
+            let row = crate::libs::tsv::record::StrSliceRow { fields: &[x, y] };
+            processor.update(&mut agg, &row);
+        }
+        let results = processor.format_results(&agg);
+        assert_eq!(results[3], "1");
+        assert_eq!(agg.comoments[0].n, 4.0);
+        assert_eq!(agg.pairs[0].len(), 4);
+    }
 }
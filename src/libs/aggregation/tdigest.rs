@@ -0,0 +1,371 @@
+//! Streaming quantile sketch for `tva stats --approx-quantiles`.
+//!
+//! [`TDigest`] is a merging t-digest (Dunning & Ertl, 2019). Values are
+//! buffered, then folded into a sorted list of weighted centroids whose sizes
+//! are limited by the `k1` scale function `k(q) = δ/(2π)·asin(2q - 1)`, where
+//! `δ` is the compression: centroids near the median may hold many values,
+//! centroids near the tails only a few.
+//!
+//! # Memory
+//!
+//! A digest holds at most about `δ` centroids plus a buffer of
+//! `BUFFER_FACTOR·δ` values, however many values are added.
+//!
+//! # Accuracy
+//!
+//! A centroid around quantile `q` spans at most `2π·sqrt(q(1-q))/δ` of the
+//! distribution, which bounds the rank error of [`TDigest::quantile`]. With
+//! the default compression of 100 that is ±3.1% of the rank at the median,
+//! ±2.7% at the quartiles and ±0.6% at the 1st and 99th percentiles. Typical
+//! errors are an order of magnitude smaller, as estimates are interpolated
+//! between centroid centers. The minimum and maximum are always exact, and so
+//! is every quantile until the first buffer flush.
+
+use super::math;
+use std::f64::consts::PI;
+
+/// Default compression (`δ`).
+pub const DEFAULT_COMPRESSION: usize = 100;
+
+/// Size of the insertion buffer, as a multiple of the compression.
+const BUFFER_FACTOR: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A mergeable, bounded-memory sketch of a distribution.
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    /// Total weight of `centroids`.
+    weight: f64,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: usize) -> Self {
+        Self {
+            compression: compression.max(1) as f64,
+            centroids: Vec::new(),
+            weight: 0.0,
+            buffer: Vec::new(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn compression(&self) -> usize {
+        self.compression as usize
+    }
+
+    /// Number of values added so far.
+    pub fn count(&self) -> f64 {
+        self.weight + self.buffer.len() as f64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.centroids.is_empty() && self.buffer.is_empty()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Adds a value. NaN cannot be ranked and is ignored.
+    pub fn add(&mut self, x: f64) {
+        if x.is_nan() {
+            return;
+        }
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        self.buffer.push(x);
+        if self.buffer.len() >= BUFFER_FACTOR * self.compression as usize {
+            self.fold(Vec::new());
+        }
+    }
+
+    /// Adds every value summarised by `other`. Buffered values are added one
+    /// by one, so merging digests that were never flushed stays exact.
+    pub fn merge(&mut self, other: &TDigest) {
+        for &x in &other.buffer {
+            self.add(x);
+        }
+        if !other.centroids.is_empty() {
+            self.min = self.min.min(other.min);
+            self.max = self.max.max(other.max);
+            self.fold(other.centroids.clone());
+        }
+    }
+
+    /// Serialises the digest as `min;max;mean:weight,...;value,...`, or an
+    /// empty string if it is empty.
+    pub fn encode(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        let centroids: Vec<String> = self
+            .centroids
+            .iter()
+            .map(|c| format!("{}:{}", c.mean, c.weight))
+            .collect();
+        let buffer: Vec<String> = self.buffer.iter().map(|x| x.to_string()).collect();
+        format!(
+            "{};{};{};{}",
+            self.min,
+            self.max,
+            centroids.join(","),
+            buffer.join(",")
+        )
+    }
+
+    /// Parses the output of [`encode`](TDigest::encode).
+    pub fn decode(s: &str, compression: usize) -> Option<Self> {
+        let mut digest = Self::new(compression);
+        if s.is_empty() {
+            return Some(digest);
+        }
+        let parts: Vec<&str> = s.split(';').collect();
+        let [min, max, centroids, buffer] = parts[..] else {
+            return None;
+        };
+        digest.min = min.parse().ok()?;
+        digest.max = max.parse().ok()?;
+        for c in centroids.split(',').filter(|c| !c.is_empty()) {
+            let (mean, weight) = c.split_once(':')?;
+            let c = Centroid {
+                mean: mean.parse().ok()?,
+                weight: weight.parse().ok()?,
+            };
+            digest.weight += c.weight;
+            digest.centroids.push(c);
+        }
+        for x in buffer.split(',').filter(|x| !x.is_empty()) {
+            digest.buffer.push(x.parse().ok()?);
+        }
+        Some(digest)
+    }
+
+    /// Estimates the value at probability `p`, interpolating like
+    /// [`math::quantile`]. Returns NaN for an empty digest.
+    pub fn quantile(&self, p: f64) -> f64 {
+        if self.centroids.is_empty() {
+            if self.buffer.is_empty() {
+                return f64::NAN;
+            }
+            // Nothing has been summarised yet, so the answer is exact
+            let mut sorted = self.buffer.clone();
+            sorted.sort_by(|a, b| a.total_cmp(b));
+            return math::quantile(&sorted, p);
+        }
+        if !self.buffer.is_empty() {
+            let mut digest = self.clone();
+            digest.fold(Vec::new());
+            return digest.interpolate(p);
+        }
+        self.interpolate(p)
+    }
+
+    /// Merges the buffer and `incoming` into the centroid list.
+    fn fold(&mut self, mut incoming: Vec<Centroid>) {
+        incoming.extend(self.buffer.drain(..).map(|x| Centroid {
+            mean: x,
+            weight: 1.0,
+        }));
+        incoming.append(&mut self.centroids);
+        incoming.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total: f64 = incoming.iter().map(|c| c.weight).sum();
+        let mut iter = incoming.into_iter();
+        let Some(mut current) = iter.next() else {
+            return;
+        };
+
+        let mut before = 0.0;
+        let mut limit = self.q_limit(0.0) * total;
+        for c in iter {
+            if before + current.weight + c.weight <= limit {
+                current.weight += c.weight;
+                current.mean += (c.mean - current.mean) * c.weight / current.weight;
+            } else {
+                before += current.weight;
+                self.centroids.push(current);
+                limit = self.q_limit(before / total) * total;
+                current = c;
+            }
+        }
+        self.centroids.push(current);
+        self.weight = total;
+    }
+
+    /// The largest quantile a centroid starting at `q` may reach: one unit
+    /// further along the scale function.
+    fn q_limit(&self, q: f64) -> f64 {
+        let k = self.compression / (2.0 * PI) * (2.0 * q - 1.0).clamp(-1.0, 1.0).asin();
+        let angle = (k + 1.0) * 2.0 * PI / self.compression;
+        if angle >= PI / 2.0 {
+            1.0
+        } else {
+            (angle.sin() + 1.0) / 2.0
+        }
+    }
+
+    /// Interpolates between centroid centers, anchored by the exact minimum
+    /// and maximum. With unit-weight centroids this matches
+    /// [`math::quantile`] exactly.
+    fn interpolate(&self, p: f64) -> f64 {
+        let n = self.weight;
+        // Position of the wanted value, measured in the same units as the
+        // centroid centers (a unit-weight centroid `i` is centered at i + 0.5)
+        let target = p.clamp(0.0, 1.0) * (n - 1.0) + 0.5;
+
+        let mut prev = (0.5, self.min);
+        let mut cumulative = 0.0;
+        for c in &self.centroids {
+            let center = cumulative + c.weight / 2.0;
+            if target <= center {
+                return lerp(prev, (center, c.mean), target);
+            }
+            prev = (center, c.mean);
+            cumulative += c.weight;
+        }
+        lerp(prev, (n - 0.5, self.max), target)
+    }
+}
+
+#[inline]
+fn lerp(a: (f64, f64), b: (f64, f64), x: f64) -> f64 {
+    if b.0 <= a.0 {
+        return b.1;
+    }
+    let t = ((x - a.0) / (b.0 - a.0)).clamp(0.0, 1.0);
+    a.1 + (b.1 - a.1) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic shuffle of 0..n
+    fn shuffled(n: usize) -> Vec<f64> {
+        let mut vals: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        let mut seed: u64 = 7;
+        for i in (1..n).rev() {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let j = (seed >> 33) as usize % (i + 1);
+            vals.swap(i, j);
+        }
+        vals
+    }
+
+    #[test]
+    fn test_empty() {
+        let digest = TDigest::default();
+        assert!(digest.is_empty());
+        assert!(digest.quantile(0.5).is_nan());
+    }
+
+    #[test]
+    fn test_small_is_exact() {
+        let mut digest = TDigest::new(100);
+        let vals = [3.0, 1.0, 4.0, 1.0, 5.0, 9.0, 2.0, 6.0];
+        for v in vals {
+            digest.add(v);
+        }
+        let mut sorted = vals.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        for p in [0.0, 0.1, 0.25, 0.5, 0.75, 0.9, 1.0] {
+            assert_eq!(digest.quantile(p), math::quantile(&sorted, p));
+        }
+    }
+
+    #[test]
+    fn test_bounded_and_accurate() {
+        let n = 100_000;
+        let mut digest = TDigest::new(100);
+        for v in shuffled(n) {
+            digest.add(v);
+        }
+        assert_eq!(digest.count(), n as f64);
+        assert!(digest.centroids.len() <= 100);
+        assert!(digest.buffer.len() < BUFFER_FACTOR * 100);
+        assert_eq!(digest.quantile(0.0), 0.0);
+        assert_eq!(digest.quantile(1.0), (n - 1) as f64);
+
+        // Rank error within the documented bound
+        for p in [0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99] {
+            let rank = digest.quantile(p) / (n - 1) as f64;
+            let bound = 2.0 * PI * (p * (1.0 - p)).sqrt() / 100.0;
+            assert!((rank - p).abs() <= bound, "p={} rank={}", p, rank);
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let vals = shuffled(20_000);
+        let mut whole = TDigest::new(100);
+        let mut left = TDigest::new(100);
+        let mut right = TDigest::new(100);
+        for (i, &v) in vals.iter().enumerate() {
+            whole.add(v);
+            if i % 2 == 0 {
+                left.add(v);
+            } else {
+                right.add(v);
+            }
+        }
+        left.merge(&right);
+        assert_eq!(left.count(), whole.count());
+        assert_eq!(left.min(), 0.0);
+        assert_eq!(left.max(), 19_999.0);
+        let median = left.quantile(0.5);
+        assert!((median - 9_999.5).abs() < 20_000.0 * 0.01, "{}", median);
+    }
+
+    #[test]
+    fn test_merge_unflushed_is_exact() {
+        let mut left = TDigest::new(100);
+        let mut right = TDigest::new(100);
+        for v in [5.0, 1.0, 3.0] {
+            left.add(v);
+        }
+        for v in [2.0, 4.0] {
+            right.add(v);
+        }
+        left.merge(&right);
+        assert_eq!(left.quantile(0.5), 3.0);
+        assert_eq!(left.quantile(0.25), 2.0);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let mut digest = TDigest::new(20);
+        for v in shuffled(1_000) {
+            digest.add(v);
+        }
+        let decoded = TDigest::decode(&digest.encode(), 20).unwrap();
+        assert_eq!(decoded.count(), digest.count());
+        for p in [0.0, 0.1, 0.5, 0.9, 1.0] {
+            assert_eq!(decoded.quantile(p), digest.quantile(p));
+        }
+
+        let empty = TDigest::decode("", 20).unwrap();
+        assert!(empty.is_empty());
+        assert!(TDigest::decode("1;2;x", 20).is_none());
+    }
+}
@@ -0,0 +1,145 @@
+//! Group-relative transforms for `tva stats --transform`.
+//!
+//! Unlike a [`Calculator`](super::Calculator), which reduces a group to one
+//! value, a transform gives each row a value relative to its group, so it
+//! needs all of the group's values before the first row can be written.
+
+use crate::libs::aggregation::math;
+use crate::libs::number::format_float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransformKind {
+    /// `(x - mean) / stdev`, with the sample standard deviation.
+    ZScore,
+    /// 1 for the smallest value; equal values share a rank, without gaps.
+    DenseRank,
+    /// 1 to n by value; equal values are ranked in row order.
+    OrdinalRank,
+    /// `(rank - 1) / (n - 1)`, where equal values take their lowest rank.
+    PercentRank,
+    /// `x / sum`.
+    Share,
+}
+
+impl TransformKind {
+    /// Name used in output headers (`price_zscore`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransformKind::ZScore => "zscore",
+            TransformKind::DenseRank => "dense_rank",
+            TransformKind::OrdinalRank => "ordinal_rank",
+            TransformKind::PercentRank => "percent_rank",
+            TransformKind::Share => "share",
+        }
+    }
+
+    /// Computes the transform for the values of one group, in row order.
+    /// Rows whose value is not a number get an empty result and are left out
+    /// of the group statistics.
+    pub fn apply(
+        &self,
+        values: &[Option<f64>],
+        precision: Option<usize>,
+    ) -> Vec<String> {
+        let numbers: Vec<f64> = values.iter().flatten().copied().collect();
+        match self {
+            TransformKind::ZScore => {
+                let sum: f64 = numbers.iter().sum();
+                let sum_sq: f64 = numbers.iter().map(|v| v * v).sum();
+                let mean = math::mean(sum, numbers.len());
+                let stdev = math::stdev(sum_sq, sum, numbers.len());
+                map_values(values, |v| format_float((v - mean) / stdev, precision))
+            }
+            TransformKind::Share => {
+                let sum: f64 = numbers.iter().sum();
+                map_values(values, |v| format_float(v / sum, precision))
+            }
+            TransformKind::DenseRank
+            | TransformKind::OrdinalRank
+            | TransformKind::PercentRank => self.ranks(values, precision),
+        }
+    }
+
+    fn ranks(&self, values: &[Option<f64>], precision: Option<usize>) -> Vec<String> {
+        // Row positions of the numbers, by value; the sort is stable, so
+        // equal values stay in row order
+        let mut order: Vec<(usize, f64)> = values
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| v.map(|v| (i, v)))
+            .collect();
+        order.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let n = order.len();
+        let mut results = vec![String::new(); values.len()];
+        let mut dense = 0;
+        let mut lowest = 0;
+        for (pos, &(row, value)) in order.iter().enumerate() {
+            if pos == 0 || value != order[pos - 1].1 {
+                dense += 1;
+                lowest = pos + 1;
+            }
+            results[row] = match self {
+                TransformKind::DenseRank => dense.to_string(),
+                TransformKind::OrdinalRank => (pos + 1).to_string(),
+                _ => {
+                    let rank = if n > 1 {
+                        (lowest - 1) as f64 / (n - 1) as f64
+                    } else {
+                        0.0
+                    };
+                    format_float(rank, precision)
+                }
+            };
+        }
+        results
+    }
+}
+
+fn map_values(values: &[Option<f64>], f: impl Fn(f64) -> String) -> Vec<String> {
+    values
+        .iter()
+        .map(|v| v.map(&f).unwrap_or_default())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zscore_and_share() {
+        let values = [Some(2.0), None, Some(4.0), Some(6.0)];
+        assert_eq!(
+            TransformKind::ZScore.apply(&values, Some(4)),
+            ["-1", "", "0", "1"]
+        );
+        assert_eq!(
+            TransformKind::Share.apply(&values, Some(4)),
+            ["0.1667", "", "0.3333", "0.5"]
+        );
+        // A single value has no standard deviation
+        assert_eq!(TransformKind::ZScore.apply(&[Some(1.0)], Some(4)), ["nan"]);
+    }
+
+    #[test]
+    fn test_ranks() {
+        let values = [Some(30.0), Some(10.0), None, Some(30.0), Some(20.0)];
+        assert_eq!(
+            TransformKind::DenseRank.apply(&values, Some(4)),
+            ["3", "1", "", "3", "2"]
+        );
+        assert_eq!(
+            TransformKind::OrdinalRank.apply(&values, Some(4)),
+            ["3", "1", "", "4", "2"]
+        );
+        assert_eq!(
+            TransformKind::PercentRank.apply(&values, Some(4)),
+            ["0.6667", "0", "", "0.6667", "0.3333"]
+        );
+        assert_eq!(
+            TransformKind::PercentRank.apply(&[Some(5.0)], Some(4)),
+            ["0"]
+        );
+    }
+}
@@ -20,6 +20,7 @@
 //! However, `Cell` reuses the math logic from `libs::aggregation::math` to ensure consistency
 //! in statistical calculations.
 
+use crate::libs::aggregation::moments::Moments;
 use crate::libs::aggregation::{math, OpKind};
 
 /// A state container for accumulating values in `tva wider`.
@@ -63,13 +64,20 @@ impl Cell {
             | OpKind::Q1
             | OpKind::Q3
             | OpKind::IQR
-            | OpKind::Quantile(_) => Cell::Values(Vec::new()),
+            | OpKind::Quantile(_)
+            | OpKind::Skewness
+            | OpKind::Kurtosis => Cell::Values(Vec::new()),
+            // A cell holds a single field
+            OpKind::Covariance(_) | OpKind::Pearson(_) | OpKind::Spearman(_) => {
+                Cell::Empty
+            }
             OpKind::First
             | OpKind::Last
             | OpKind::Mode
             | OpKind::ModeCount
             | OpKind::Unique
             | OpKind::NUnique
+            | OpKind::ApproxNUnique
             | OpKind::Collapse
             | OpKind::Rand => Cell::Strings(Vec::new()),
         }
@@ -204,7 +212,9 @@ impl Cell {
             | OpKind::Q1
             | OpKind::Q3
             | OpKind::IQR
-            | OpKind::Quantile(_) => {
+            | OpKind::Quantile(_)
+            | OpKind::Skewness
+            | OpKind::Kurtosis => {
                 if let Some(v) = val {
                     if let Cell::Values(vals) = self {
                         vals.push(v);
@@ -243,6 +253,7 @@ impl Cell {
             | OpKind::ModeCount
             | OpKind::Unique
             | OpKind::NUnique
+            | OpKind::ApproxNUnique
             | OpKind::Collapse
             | OpKind::Rand => {
                 if !val_str.is_empty() {
@@ -253,6 +264,7 @@ impl Cell {
                     }
                 }
             }
+            OpKind::Covariance(_) | OpKind::Pearson(_) | OpKind::Spearman(_) => {}
         }
     }
 
@@ -356,6 +368,22 @@ impl Cell {
                     let q3 = math::quantile(&v, 0.75);
                     (q3 - q1).to_string()
                 }
+                OpKind::Skewness | OpKind::Kurtosis => {
+                    let mut m = Moments::default();
+                    for &v in vals {
+                        m.push(v);
+                    }
+                    let res = if op == OpKind::Skewness {
+                        m.skewness()
+                    } else {
+                        m.kurtosis()
+                    };
+                    if res.is_nan() {
+                        "nan".to_string()
+                    } else {
+                        res.to_string()
+                    }
+                }
                 _ => "".to_string(),
             },
             Cell::Strings(vals) => match op {
@@ -366,7 +394,7 @@ impl Cell {
                         "".to_string()
                     }
                 }
-                OpKind::NUnique => {
+                OpKind::NUnique | OpKind::ApproxNUnique => {
                     let unique_vals: std::collections::HashSet<_> =
                         vals.iter().collect();
                     unique_vals.len().to_string()
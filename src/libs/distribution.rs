@@ -0,0 +1,344 @@
+//! Probability distributions for the statistical tests of `tva crosstab` and
+//! `tva test`.
+//!
+//! Only the survival functions (upper tail probabilities) needed for p-values
+//! are implemented, on top of the regularized incomplete gamma and beta
+//! functions.
+
+const MAX_ITERATIONS: usize = 500;
+const EPSILON: f64 = 1e-15;
+
+/// Natural logarithm of the gamma function, by the Lanczos approximation
+/// (g = 7, n = 9), accurate to about 15 digits for `x > 0`.
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, c)| {
+            acc + c / (x + i as f64 + 1.0)
+        });
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x) = Γ(a, x) / Γ(a)`.
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if x.is_nan() || a.is_nan() || a <= 0.0 || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.is_infinite() {
+        return 0.0;
+    }
+    let ln_prefix = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        // Series for P(a, x), converging quickly below a + 1
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        1.0 - sum * ln_prefix.exp()
+    } else {
+        // Continued fraction for Q(a, x), by the modified Lentz method
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..MAX_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        ln_prefix.exp() * h
+    }
+}
+
+/// Upper tail probability `P(X >= x)` of a chi-square distribution with `df`
+/// degrees of freedom.
+pub fn chi2_sf(x: f64, df: f64) -> f64 {
+    if df <= 0.0 {
+        return f64::NAN;
+    }
+    gamma_q(df / 2.0, x.max(0.0) / 2.0)
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`.
+pub fn beta_inc(a: f64, b: f64, x: f64) -> f64 {
+    if x.is_nan() || a.is_nan() || b.is_nan() || a <= 0.0 || b <= 0.0 {
+        return f64::NAN;
+    }
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_prefix =
+        ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+
+    // The continued fraction converges quickly below (a + 1) / (a + b + 2);
+    // above it, use I_x(a, b) = 1 - I_{1-x}(b, a)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        ln_prefix.exp() * beta_cf(a, b, x) / a
+    } else {
+        1.0 - ln_prefix.exp() * beta_cf(b, a, 1.0 - x) / b
+    }
+}
+
+/// Continued fraction of the incomplete beta function, by the modified Lentz
+/// method.
+fn beta_cf(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = f64::MIN_POSITIVE / EPSILON;
+    let clamp = |v: f64| if v.abs() < tiny { tiny } else { v };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..MAX_ITERATIONS {
+        let m = m as f64;
+        // Even step
+        let an = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + an * d);
+        c = clamp(1.0 + an / c);
+        h *= d * c;
+        // Odd step
+        let an = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + an * d);
+        c = clamp(1.0 + an / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// Upper tail probability `P(T >= t)` of Student's t distribution with `df`
+/// degrees of freedom, which need not be an integer.
+pub fn t_sf(t: f64, df: f64) -> f64 {
+    if t.is_nan() || df.is_nan() || df <= 0.0 {
+        return f64::NAN;
+    }
+    let tail = 0.5 * beta_inc(df / 2.0, 0.5, df / (df + t * t));
+    if t >= 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+/// Upper tail probability `P(Z >= z)` of the standard normal distribution.
+pub fn normal_sf(z: f64) -> f64 {
+    // erfc(z / sqrt(2)) = Q(1/2, z^2 / 2)
+    let tail = 0.5 * gamma_q(0.5, z * z / 2.0);
+    if z >= 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+/// Upper tail probability `P(K > lambda)` of the Kolmogorov distribution, the
+/// limit of the scaled Kolmogorov-Smirnov statistic.
+pub fn kolmogorov_sf(lambda: f64) -> f64 {
+    if lambda.is_nan() {
+        return f64::NAN;
+    }
+    // The alternating series converges slowly for small lambda, where the
+    // probability is 1 to double precision
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..MAX_ITERATIONS {
+        let k = k as f64;
+        let term = sign * (-2.0 * k * k * lambda * lambda).exp();
+        sum += term;
+        if term.abs() <= EPSILON * sum.abs() {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// Pearson's chi-square test of independence on a contingency table of
+/// observed counts. Returns the statistic, the degrees of freedom and the
+/// p-value; rows or columns whose total is zero are ignored.
+pub fn chi2_independence(observed: &[Vec<u64>]) -> (f64, usize, f64) {
+    let num_cols = observed.iter().map(|row| row.len()).max().unwrap_or(0);
+    let row_totals: Vec<f64> = observed
+        .iter()
+        .map(|row| row.iter().sum::<u64>() as f64)
+        .collect();
+    let col_totals: Vec<f64> = (0..num_cols)
+        .map(|j| {
+            observed
+                .iter()
+                .map(|row| row.get(j).copied().unwrap_or(0))
+                .sum::<u64>() as f64
+        })
+        .collect();
+    let total: f64 = row_totals.iter().sum();
+
+    let mut statistic = 0.0;
+    for (row, row_total) in observed.iter().zip(&row_totals) {
+        for (j, col_total) in col_totals.iter().enumerate() {
+            let expected = row_total * col_total / total;
+            if expected > 0.0 {
+                let diff = row.get(j).copied().unwrap_or(0) as f64 - expected;
+                statistic += diff * diff / expected;
+            }
+        }
+    }
+
+    let used = |totals: &[f64]| totals.iter().filter(|t| **t > 0.0).count();
+    let df = used(&row_totals).saturating_sub(1) * used(&col_totals).saturating_sub(1);
+    let p_value = if df > 0 {
+        chi2_sf(statistic, df as f64)
+    } else {
+        f64::NAN
+    };
+    (statistic, df, p_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 1e-10 * expected.abs().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(5.0), 24f64.ln());
+        assert_close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln());
+        assert_close(ln_gamma(100.5), 361.435_540_467_777_6);
+    }
+
+    #[test]
+    fn test_chi2_sf() {
+        // 3.8415 is the 95% critical value of df = 1. For even df,
+        // Q(k, y) = exp(-y) * sum(y^i / i!, i < k)
+        assert_close(chi2_sf(3.841_458_820_694_124, 1.0), 0.05);
+        assert_close(chi2_sf(2.0, 2.0), (-1.0f64).exp());
+        assert_close(chi2_sf(10.0, 4.0), 6.0 * (-5.0f64).exp());
+        assert_close(chi2_sf(100.0, 80.0), 0.064_570_368_921_132_96);
+        assert_close(chi2_sf(0.0, 3.0), 1.0);
+        assert!(chi2_sf(1.0, 0.0).is_nan());
+    }
+
+    #[test]
+    fn test_beta_inc() {
+        // I_x(a, 1) = x^a, I_x(1, b) = 1 - (1 - x)^b, I_0.5(a, a) = 0.5
+        assert_close(beta_inc(3.0, 1.0, 0.4), 0.4f64.powi(3));
+        assert_close(beta_inc(1.0, 2.5, 0.3), 1.0 - 0.7f64.powf(2.5));
+        assert_close(beta_inc(7.5, 7.5, 0.5), 0.5);
+        assert_eq!(beta_inc(2.0, 3.0, 0.0), 0.0);
+        assert_eq!(beta_inc(2.0, 3.0, 1.0), 1.0);
+        assert!(beta_inc(0.0, 3.0, 0.5).is_nan());
+    }
+
+    #[test]
+    fn test_t_sf() {
+        // Closed forms for df = 1 (Cauchy) and df = 2
+        let pi = std::f64::consts::PI;
+        assert_close(t_sf(1.5, 1.0), 0.5 - 1.5f64.atan() / pi);
+        assert_close(t_sf(-0.7, 1.0), 0.5 + 0.7f64.atan() / pi);
+        assert_close(t_sf(2.0, 2.0), 0.5 * (1.0 - 2.0 / 6.0f64.sqrt()));
+        assert_close(t_sf(0.0, 9.3), 0.5);
+        // 2.2281 is the 97.5% quantile of df = 10
+        assert!((t_sf(2.228_138_851_986_273_4, 10.0) - 0.025).abs() < 1e-12);
+        assert!(t_sf(1.0, 0.0).is_nan());
+    }
+
+    #[test]
+    fn test_normal_sf() {
+        assert_close(normal_sf(0.0), 0.5);
+        assert_close(normal_sf(1.959_963_984_540_054), 0.025);
+        assert_close(normal_sf(-1.959_963_984_540_054), 0.975);
+        assert_eq!(normal_sf(f64::INFINITY), 0.0);
+    }
+
+    #[test]
+    fn test_kolmogorov_sf() {
+        // Critical values of the Kolmogorov distribution
+        assert!((kolmogorov_sf(1.223_847_870_217_082) - 0.1).abs() < 1e-9);
+        assert!((kolmogorov_sf(1.358_098_639_322_550_5) - 0.05).abs() < 1e-9);
+        assert!((kolmogorov_sf(1.627_623_611_518_950_2) - 0.01).abs() < 1e-9);
+        assert_eq!(kolmogorov_sf(0.1), 1.0);
+        assert!(kolmogorov_sf(5.0) < 1e-20);
+    }
+
+    #[test]
+    fn test_chi2_independence() {
+        // Statistic = 100 * (10*40 - 20*30)^2 / (30 * 70 * 40 * 60)
+        let (statistic, df, p_value) = chi2_independence(&[vec![10, 20], vec![30, 40]]);
+        assert_close(
+            statistic,
+            100.0 * 200.0f64.powi(2) / (30.0 * 70.0 * 40.0 * 60.0),
+        );
+        assert_eq!(df, 1);
+        assert_close(p_value, chi2_sf(statistic, 1.0));
+
+        // Independent rows
+        let (statistic, df, p_value) =
+            chi2_independence(&[vec![1, 2, 3], vec![2, 4, 6]]);
+        assert_close(statistic, 0.0);
+        assert_eq!(df, 2);
+        assert_close(p_value, 1.0);
+
+        // A single row has no degrees of freedom
+        let (_, df, p_value) = chi2_independence(&[vec![5, 7]]);
+        assert_eq!(df, 0);
+        assert!(p_value.is_nan());
+    }
+}
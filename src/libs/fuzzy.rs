@@ -0,0 +1,301 @@
+//! Approximate string matching for `tva join --fuzzy`.
+//!
+//! Keys are first normalised ([`normalize`]), then compared with a
+//! [`Similarity`] measure scaled to `0.0..=1.0`, where `1.0` means equal.
+
+/// Similarity measure between normalised keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Similarity {
+    /// Equal normalised forms only.
+    Normalized,
+    /// `1 - distance / max_len`, using the Levenshtein edit distance.
+    Levenshtein,
+    /// Jaro-Winkler similarity.
+    JaroWinkler,
+}
+
+impl Similarity {
+    pub fn from_arg(s: &str) -> Self {
+        match s {
+            "levenshtein" => Similarity::Levenshtein,
+            "jaro-winkler" => Similarity::JaroWinkler,
+            _ => Similarity::Normalized,
+        }
+    }
+
+    pub fn score(self, a: &[char], b: &[char]) -> f64 {
+        match self {
+            Similarity::Normalized => {
+                if a == b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Similarity::Levenshtein => levenshtein_similarity(a, b),
+            Similarity::JaroWinkler => jaro_winkler(a, b),
+        }
+    }
+
+    /// Returns the candidate with the highest score of at least `threshold`.
+    /// Ties go to the earliest candidate.
+    pub fn best_match(
+        self,
+        query: &[char],
+        candidates: &[Vec<char>],
+        threshold: f64,
+    ) -> Option<(usize, f64)> {
+        let mut best: Option<(usize, f64)> = None;
+        for (i, candidate) in candidates.iter().enumerate() {
+            let floor = best.map_or(threshold, |(_, score)| score);
+            if self.length_bound(query.len(), candidate.len()) < floor {
+                continue;
+            }
+            let score = self.score(query, candidate);
+            if score >= threshold && best.is_none_or(|(_, s)| score > s) {
+                best = Some((i, score));
+            }
+        }
+        best
+    }
+
+    /// Highest score keys of lengths `a` and `b` can reach, known from the
+    /// lengths alone, so that most candidates are skipped without scoring.
+    fn length_bound(self, a: usize, b: usize) -> f64 {
+        let (short, long) = (a.min(b) as f64, a.max(b) as f64);
+        if long == 0.0 {
+            return 1.0;
+        }
+        match self {
+            Similarity::Normalized => {
+                if a == b {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            // Every character of the length difference costs an edit
+            Similarity::Levenshtein => 1.0 - (long - short) / long,
+            Similarity::JaroWinkler => {
+                if short == 0.0 {
+                    return 0.0;
+                }
+                // At most `short` characters match, with no transpositions,
+                // and the prefix boost is at most four tenths of the rest
+                let jaro = (1.0 + short / long + 1.0) / 3.0;
+                jaro + 0.4 * (1.0 - jaro)
+            }
+        }
+    }
+}
+
+/// Folds case, turns punctuation into spaces and collapses whitespace.
+///
+/// `"  ACME   Corp."` becomes `"acme corp"`.
+pub fn normalize(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending_space = false;
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            if pending_space && !out.is_empty() {
+                out.push(' ');
+            }
+            pending_space = false;
+            out.extend(c.to_lowercase());
+        } else {
+            pending_space = true;
+        }
+    }
+    out
+}
+
+/// Levenshtein edit distance (insertions, deletions and substitutions).
+pub fn levenshtein(a: &[char], b: &[char]) -> usize {
+    if a.is_empty() {
+        return b.len();
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Levenshtein distance scaled to a similarity, `1 - distance / max_len`.
+pub fn levenshtein_similarity(a: &[char], b: &[char]) -> f64 {
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / max_len as f64
+}
+
+/// Jaro similarity.
+pub fn jaro(a: &[char], b: &[char]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut b_used = vec![false; b.len()];
+    let mut a_matches = Vec::with_capacity(a.len());
+
+    for (i, ca) in a.iter().enumerate() {
+        let lo = i.saturating_sub(window);
+        let hi = (i + window + 1).min(b.len());
+        for j in lo..hi {
+            if !b_used[j] && b[j] == *ca {
+                b_used[j] = true;
+                a_matches.push(*ca);
+                break;
+            }
+        }
+    }
+
+    let m = a_matches.len();
+    if m == 0 {
+        return 0.0;
+    }
+
+    let b_matches = b
+        .iter()
+        .zip(&b_used)
+        .filter(|(_, &used)| used)
+        .map(|(c, _)| c);
+    let transpositions = a_matches
+        .iter()
+        .zip(b_matches)
+        .filter(|(x, y)| x != y)
+        .count()
+        / 2;
+
+    let m = m as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0
+}
+
+/// Jaro-Winkler similarity: Jaro, boosted for a common prefix of up to four
+/// characters.
+pub fn jaro_winkler(a: &[char], b: &[char]) -> f64 {
+    let sim = jaro(a, b);
+    let prefix = a.iter().zip(b).take(4).take_while(|(x, y)| x == y).count();
+    sim + prefix as f64 * 0.1 * (1.0 - sim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  ACME   Corp."), "acme corp");
+        assert_eq!(normalize("Acme-Corp, Inc"), "acme corp inc");
+        assert_eq!(normalize("Ünïcode\tName"), "ünïcode name");
+        assert_eq!(normalize("..."), "");
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+        assert_eq!(levenshtein(&chars("abc"), &chars("")), 3);
+        assert_eq!(levenshtein(&chars("flaw"), &chars("lawn")), 2);
+        assert_eq!(levenshtein_similarity(&chars(""), &chars("")), 1.0);
+        assert!(
+            (levenshtein_similarity(&chars("abcd"), &chars("abce")) - 0.75).abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn test_jaro_winkler() {
+        let approx = |a: f64, b: f64| (a - b).abs() < 1e-3;
+        assert!(approx(jaro(&chars("martha"), &chars("marhta")), 0.944));
+        assert!(approx(
+            jaro_winkler(&chars("martha"), &chars("marhta")),
+            0.961
+        ));
+        assert!(approx(
+            jaro_winkler(&chars("dwayne"), &chars("duane")),
+            0.84
+        ));
+        assert!(approx(
+            jaro_winkler(&chars("dixon"), &chars("dicksonx")),
+            0.813
+        ));
+        assert_eq!(jaro_winkler(&chars("abc"), &chars("abc")), 1.0);
+        assert_eq!(jaro_winkler(&chars("abc"), &chars("xyz")), 0.0);
+    }
+
+    #[test]
+    fn test_length_bound() {
+        let words = [
+            "",
+            "a",
+            "ab",
+            "abc",
+            "acme",
+            "acme corp",
+            "acme corporation",
+        ];
+        for similarity in [Similarity::Levenshtein, Similarity::JaroWinkler] {
+            for a in words {
+                for b in words {
+                    let (a, b) = (chars(a), chars(b));
+                    assert!(
+                        similarity.score(&a, &b)
+                            <= similarity.length_bound(a.len(), b.len()),
+                        "{:?} {:?} {:?}",
+                        similarity,
+                        a,
+                        b
+                    );
+                }
+            }
+        }
+        // Keys of very different lengths are ruled out; Jaro-Winkler never
+        // bounds below 0.8 from lengths alone
+        assert!(Similarity::Levenshtein.length_bound(4, 40) < 0.8);
+        assert!(Similarity::JaroWinkler.length_bound(4, 40) < 0.9);
+        assert!(Similarity::JaroWinkler.length_bound(4, 40) >= 0.8);
+    }
+
+    #[test]
+    fn test_best_match() {
+        let candidates: Vec<Vec<char>> = ["acme corporation", "globex", "acme"]
+            .iter()
+            .map(|s| chars(s))
+            .collect();
+        let query = chars("acme corp");
+
+        let (i, score) = Similarity::JaroWinkler
+            .best_match(&query, &candidates, 0.8)
+            .unwrap();
+        assert_eq!(i, 0);
+        assert!(score > 0.9);
+
+        assert!(Similarity::Levenshtein
+            .best_match(&chars("initech"), &candidates, 0.8)
+            .is_none());
+        assert_eq!(
+            Similarity::Normalized.best_match(&chars("acme"), &candidates, 0.8),
+            Some((2, 1.0))
+        );
+    }
+}
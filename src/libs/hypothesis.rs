@@ -0,0 +1,275 @@
+//! Two-sample tests for `tva test`.
+//!
+//! Each test compares the values of two groups and returns its statistic with
+//! a two-sided p-value. The statistics are signed or oriented by the first
+//! group, as in R and SciPy.
+
+use crate::libs::aggregation::moments;
+use crate::libs::distribution::{kolmogorov_sf, normal_sf, t_sf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TwoSampleTest {
+    /// Student's t-test, assuming equal variances.
+    Student,
+    /// Welch's t-test, with Welch-Satterthwaite degrees of freedom.
+    Welch,
+    /// Mann-Whitney U test, by the normal approximation with tie and
+    /// continuity corrections.
+    MannWhitney,
+    /// Two-sample Kolmogorov-Smirnov test, with the asymptotic p-value.
+    KolmogorovSmirnov,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestResult {
+    pub statistic: f64,
+    /// Degrees of freedom, for the t-tests.
+    pub df: Option<f64>,
+    pub p_value: f64,
+}
+
+impl TwoSampleTest {
+    pub const ALL: [TwoSampleTest; 4] = [
+        TwoSampleTest::Student,
+        TwoSampleTest::Welch,
+        TwoSampleTest::MannWhitney,
+        TwoSampleTest::KolmogorovSmirnov,
+    ];
+
+    /// Name used on the command line and in the output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TwoSampleTest::Student => "student",
+            TwoSampleTest::Welch => "welch",
+            TwoSampleTest::MannWhitney => "mann-whitney",
+            TwoSampleTest::KolmogorovSmirnov => "ks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|test| test.name() == name)
+    }
+
+    /// Compares the samples `a` and `b`. Results are NaN when a sample is too
+    /// small for the test.
+    pub fn run(&self, a: &[f64], b: &[f64]) -> TestResult {
+        match self {
+            TwoSampleTest::Student => student(a, b),
+            TwoSampleTest::Welch => welch(a, b),
+            TwoSampleTest::MannWhitney => mann_whitney(a, b),
+            TwoSampleTest::KolmogorovSmirnov => kolmogorov_smirnov(a, b),
+        }
+    }
+}
+
+/// Size, mean and sample variance, NaN when undefined. The variance sums
+/// squared deviations from the mean in a second pass, corrected for the
+/// rounding of the mean, so it stays accurate when the mean is large compared
+/// to the spread.
+fn describe(values: &[f64]) -> (f64, f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = if values.len() > 1 {
+        let (sum_dev, sum_sq_dev) = values.iter().fold((0.0, 0.0), |(s, s2), v| {
+            let dev = v - mean;
+            (s + dev, s2 + dev * dev)
+        });
+        (sum_sq_dev - sum_dev * sum_dev / n) / (n - 1.0)
+    } else {
+        f64::NAN
+    };
+    (n, mean, variance)
+}
+
+fn t_result(t: f64, df: f64) -> TestResult {
+    TestResult {
+        statistic: t,
+        df: Some(df),
+        p_value: 2.0 * t_sf(t.abs(), df),
+    }
+}
+
+fn student(a: &[f64], b: &[f64]) -> TestResult {
+    let (n1, m1, v1) = describe(a);
+    let (n2, m2, v2) = describe(b);
+    // A single value adds nothing to the pooled sum of squares
+    let sum_sq = |n: f64, v: f64| if n > 1.0 { (n - 1.0) * v } else { 0.0 };
+
+    let df = n1 + n2 - 2.0;
+    let pooled = (sum_sq(n1, v1) + sum_sq(n2, v2)) / df;
+    let t = (m1 - m2) / (pooled * (1.0 / n1 + 1.0 / n2)).sqrt();
+    t_result(t, df)
+}
+
+fn welch(a: &[f64], b: &[f64]) -> TestResult {
+    let (n1, m1, v1) = describe(a);
+    let (n2, m2, v2) = describe(b);
+
+    let (se1, se2) = (v1 / n1, v2 / n2);
+    let t = (m1 - m2) / (se1 + se2).sqrt();
+    let df = (se1 + se2).powi(2) / (se1 * se1 / (n1 - 1.0) + se2 * se2 / (n2 - 1.0));
+    t_result(t, df)
+}
+
+fn mann_whitney(a: &[f64], b: &[f64]) -> TestResult {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let n = n1 + n2;
+    let mut ranks = moments::ranks(a.iter().chain(b).copied());
+
+    // U of the first sample
+    let rank_sum: f64 = ranks[..a.len()].iter().sum();
+    let u1 = rank_sum - n1 * (n1 + 1.0) / 2.0;
+
+    // Tied values share their average rank, so runs of equal ranks are ties
+    ranks.sort_by(|x, y| x.total_cmp(y));
+    let ties: f64 = ranks
+        .chunk_by(|x, y| x == y)
+        .map(|run| {
+            let t = run.len() as f64;
+            t * t * t - t
+        })
+        .sum();
+    let sigma = (n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)))).sqrt();
+
+    let p_value = if sigma > 0.0 {
+        let u = u1.max(n1 * n2 - u1);
+        let z = (u - n1 * n2 / 2.0 - 0.5) / sigma;
+        (2.0 * normal_sf(z)).min(1.0)
+    } else {
+        f64::NAN
+    };
+    TestResult {
+        statistic: u1,
+        df: None,
+        p_value,
+    }
+}
+
+fn kolmogorov_smirnov(a: &[f64], b: &[f64]) -> TestResult {
+    if a.is_empty() || b.is_empty() {
+        return TestResult {
+            statistic: f64::NAN,
+            df: None,
+            p_value: f64::NAN,
+        };
+    }
+    let sorted = |values: &[f64]| {
+        let mut values = values.to_vec();
+        values.sort_by(|x, y| x.total_cmp(y));
+        values
+    };
+    let (xs, ys) = (sorted(a), sorted(b));
+    let (n1, n2) = (xs.len() as f64, ys.len() as f64);
+
+    // Largest distance between the empirical distribution functions, which
+    // only change at the sample values
+    let (mut i, mut j) = (0, 0);
+    let mut d: f64 = 0.0;
+    while i < xs.len() && j < ys.len() {
+        let value = xs[i].min(ys[j]);
+        while i < xs.len() && xs[i] <= value {
+            i += 1;
+        }
+        while j < ys.len() && ys[j] <= value {
+            j += 1;
+        }
+        d = d.max((i as f64 / n1 - j as f64 / n2).abs());
+    }
+
+    // Stephens' small-sample correction of the asymptotic distribution
+    let en = (n1 * n2 / (n1 + n2)).sqrt();
+    TestResult {
+        statistic: d,
+        df: None,
+        p_value: kolmogorov_sf((en + 0.12 + 0.11 / en) * d),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: [f64; 5] = [1.1, 2.3, 2.3, 4.0, 5.2];
+    const B: [f64; 6] = [3.4, 4.4, 5.0, 6.1, 6.1, 7.3];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_t_tests() {
+        // Reference values from the textbook formulas, computed separately
+        const T_STUDENT: f64 = -2.650_808_944_646_819_5;
+        const T_WELCH: f64 = -2.612_404_577_801_64;
+        const DF_WELCH: f64 = 8.040_869_766_195_62;
+
+        let result = TwoSampleTest::Student.run(&A, &B);
+        assert_close(result.statistic, T_STUDENT);
+        assert_eq!(result.df, Some(9.0));
+        assert_close(result.p_value, 2.0 * t_sf(T_STUDENT.abs(), 9.0));
+
+        let result = TwoSampleTest::Welch.run(&A, &B);
+        assert_close(result.statistic, T_WELCH);
+        assert_close(result.df.unwrap(), DF_WELCH);
+        assert_close(result.p_value, 2.0 * t_sf(T_WELCH.abs(), DF_WELCH));
+
+        // Too few values
+        assert!(TwoSampleTest::Welch.run(&[1.0], &B).p_value.is_nan());
+    }
+
+    #[test]
+    fn test_t_tests_large_offset() {
+        // The spread is tiny compared to the values
+        let offset = 1_000_000_000.0;
+        let a = [1.0, 2.0, 3.0, 4.0];
+        let b = [2.0, 3.0, 5.0, 6.0];
+        let shift =
+            |values: &[f64]| values.iter().map(|v| v + offset).collect::<Vec<_>>();
+
+        for test in [TwoSampleTest::Student, TwoSampleTest::Welch] {
+            let expected = test.run(&a, &b);
+            let result = test.run(&shift(&a), &shift(&b));
+            assert_close(expected.statistic, -3.0 / 5.0_f64.sqrt());
+            assert_close(result.statistic, expected.statistic);
+            assert_close(result.df.unwrap(), expected.df.unwrap());
+            assert_close(result.p_value, expected.p_value);
+        }
+        assert_close(TwoSampleTest::Welch.run(&a, &b).df.unwrap(), 5.4);
+    }
+
+    #[test]
+    fn test_mann_whitney() {
+        let result = TwoSampleTest::MannWhitney.run(&A, &B);
+        assert_eq!(result.statistic, 4.0);
+        assert_eq!(result.df, None);
+        assert_close(result.p_value, 0.054_129_028_579_733_21);
+
+        // Identical samples
+        let result = TwoSampleTest::MannWhitney.run(&[1.0, 2.0], &[1.0, 2.0]);
+        assert_eq!(result.p_value, 1.0);
+    }
+
+    #[test]
+    fn test_kolmogorov_smirnov() {
+        let result = TwoSampleTest::KolmogorovSmirnov.run(&A, &B);
+        assert_close(result.statistic, 0.8 - 1.0 / 6.0);
+        assert_close(result.p_value, 0.132_999_657_783_665_9);
+        assert!(TwoSampleTest::KolmogorovSmirnov
+            .run(&[], &B)
+            .statistic
+            .is_nan());
+    }
+
+    #[test]
+    fn test_names() {
+        for test in TwoSampleTest::ALL {
+            assert_eq!(TwoSampleTest::from_name(test.name()), Some(test));
+        }
+        assert_eq!(TwoSampleTest::from_name("anova"), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Interval overlap queries for `tva join --interval`.
+//!
+//! [`IntervalTree`] is a static, implicit augmented interval tree (as in
+//! cgranges): intervals are sorted by start and stored in a flat array, where
+//! every element is a node of a balanced binary tree that also records the
+//! largest end of its subtree. Queries visit only the subtrees that can
+//! contain an overlap.
+//!
+//! All intervals are half-open, `[start, end)`. An empty interval
+//! (`start == end`) overlaps nothing; `tva join` widens them to one position.
+
+/// A single interval with its payload.
+struct Node<T> {
+    start: i64,
+    end: i64,
+    /// Largest end in the subtree rooted at this node.
+    max: i64,
+    value: T,
+}
+
+/// A static set of intervals supporting overlap queries.
+///
+/// Add intervals with [`push`](IntervalTree::push), then call
+/// [`index`](IntervalTree::index) once before querying.
+pub struct IntervalTree<T> {
+    nodes: Vec<Node<T>>,
+    max_level: u32,
+    indexed: bool,
+}
+
+impl<T> Default for IntervalTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> IntervalTree<T> {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            max_level: 0,
+            indexed: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds the interval `[start, end)`.
+    pub fn push(&mut self, start: i64, end: i64, value: T) {
+        self.nodes.push(Node {
+            start,
+            end,
+            max: end,
+            value,
+        });
+        self.indexed = false;
+    }
+
+    /// Sorts the intervals and builds the tree.
+    pub fn index(&mut self) {
+        if self.indexed {
+            return;
+        }
+        self.indexed = true;
+        self.nodes.sort_by_key(|node| node.start);
+
+        let n = self.nodes.len();
+        if n == 0 {
+            self.max_level = 0;
+            return;
+        }
+
+        // Leaves (even positions) cover themselves
+        let mut last_i = 0;
+        let mut last = 0;
+        for i in (0..n).step_by(2) {
+            last_i = i;
+            self.nodes[i].max = self.nodes[i].end;
+            last = self.nodes[i].max;
+        }
+
+        let mut k = 1;
+        while (1usize << k) <= n {
+            let x = 1usize << (k - 1);
+            let i0 = (x << 1) - 1;
+            let step = x << 2;
+            for i in (i0..n).step_by(step) {
+                let left = self.nodes[i - x].max;
+                let right = if i + x < n {
+                    self.nodes[i + x].max
+                } else {
+                    last
+                };
+                self.nodes[i].max = self.nodes[i].end.max(left).max(right);
+            }
+            // The last node of this level, which may lack a right subtree
+            last_i = if (last_i >> k) & 1 == 1 {
+                last_i - x
+            } else {
+                last_i + x
+            };
+            if last_i < n && self.nodes[last_i].max > last {
+                last = self.nodes[last_i].max;
+            }
+            k += 1;
+        }
+        self.max_level = k - 1;
+    }
+
+    /// Calls `f` with every interval overlapping `[start, end)`, in order of
+    /// interval start.
+    ///
+    /// # Panics
+    /// Panics if intervals were added after the last call to `index`.
+    pub fn overlaps<F: FnMut(i64, i64, &T)>(&self, start: i64, end: i64, mut f: F) {
+        assert!(
+            self.indexed,
+            "IntervalTree::index must be called before queries"
+        );
+        let n = self.nodes.len();
+        if n == 0 {
+            return;
+        }
+
+        // (node, level, whether the left subtree has been visited)
+        let mut stack: Vec<(usize, u32, bool)> = Vec::with_capacity(64);
+        stack.push(((1usize << self.max_level) - 1, self.max_level, false));
+
+        while let Some((x, k, visited)) = stack.pop() {
+            if k <= 3 {
+                // Small subtree: scan it linearly
+                let i0 = x >> k << k;
+                let i1 = (i0 + (1usize << (k + 1)) - 1).min(n);
+                for node in &self.nodes[i0..i1] {
+                    if node.start >= end {
+                        break;
+                    }
+                    if start < node.end {
+                        f(node.start, node.end, &node.value);
+                    }
+                }
+            } else if !visited {
+                stack.push((x, k, true));
+                let y = x - (1usize << (k - 1));
+                // A missing left child is approximated by the rest of the array
+                if y >= n || self.nodes[y].max > start {
+                    stack.push((y, k - 1, false));
+                }
+            } else if x < n && self.nodes[x].start < end {
+                let node = &self.nodes[x];
+                if start < node.end {
+                    f(node.start, node.end, &node.value);
+                }
+                stack.push((x + (1usize << (k - 1)), k - 1, false));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(intervals: &[(i64, i64)], start: i64, end: i64) -> Vec<usize> {
+        intervals
+            .iter()
+            .enumerate()
+            .filter(|(_, &(s, e))| s < end && start < e)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn query(tree: &IntervalTree<usize>, start: i64, end: i64) -> Vec<usize> {
+        let mut hits = Vec::new();
+        tree.overlaps(start, end, |_, _, &v| hits.push(v));
+        hits.sort_unstable();
+        hits
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let mut tree: IntervalTree<usize> = IntervalTree::new();
+        tree.index();
+        assert!(tree.is_empty());
+        assert!(query(&tree, 0, 100).is_empty());
+    }
+
+    #[test]
+    fn test_simple_overlaps() {
+        let mut tree = IntervalTree::new();
+        tree.push(10, 20, 0);
+        tree.push(15, 25, 1);
+        tree.push(30, 40, 2);
+        tree.index();
+
+        assert_eq!(query(&tree, 0, 10), Vec::<usize>::new());
+        assert_eq!(query(&tree, 0, 11), vec![0]);
+        assert_eq!(query(&tree, 18, 19), vec![0, 1]);
+        assert_eq!(query(&tree, 20, 30), vec![1]);
+        assert_eq!(query(&tree, 25, 30), Vec::<usize>::new());
+        assert_eq!(query(&tree, 0, 100), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_results_in_start_order() {
+        let mut tree = IntervalTree::new();
+        for (i, s) in [50, 10, 40, 20, 30].iter().enumerate() {
+            tree.push(*s, s + 100, i);
+        }
+        tree.index();
+        let mut starts = Vec::new();
+        tree.overlaps(0, 1000, |s, _, _| starts.push(s));
+        assert_eq!(starts, vec![10, 20, 30, 40, 50]);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // Deterministic pseudo-random intervals, including long ones
+        let mut seed: u64 = 42;
+        let mut next = |m: i64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((seed >> 33) as i64) % m
+        };
+
+        for n in [1, 2, 3, 7, 16, 33, 100, 257] {
+            let intervals: Vec<(i64, i64)> = (0..n)
+                .map(|_| {
+                    let s = next(1000);
+                    let len = if next(10) == 0 { next(500) } else { next(20) };
+                    (s, s + len + 1)
+                })
+                .collect();
+
+            let mut tree = IntervalTree::new();
+            for (i, &(s, e)) in intervals.iter().enumerate() {
+                tree.push(s, e, i);
+            }
+            tree.index();
+
+            for _ in 0..200 {
+                let s = next(1100) - 50;
+                let e = s + next(50) + 1;
+                assert_eq!(query(&tree, s, e), brute_force(&intervals, s, e));
+            }
+        }
+    }
+}
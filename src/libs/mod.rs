@@ -1,10 +1,17 @@
 pub mod aggregation;
 pub mod cell;
 pub mod cli;
+pub mod distribution;
 pub mod expr;
 pub mod filter;
+pub mod fuzzy;
+pub mod hypothesis;
+pub mod interval;
 pub mod io;
 pub mod number;
 pub mod plot;
 pub mod sampling;
+pub mod sort;
+pub mod summary;
 pub mod tsv;
+pub mod window;
@@ -32,6 +32,68 @@ pub fn fast_parse_f64(bytes: &[u8]) -> Option<f64> {
     lexical::parse(trimmed).ok()
 }
 
+/// Parses an ISO 8601 date or timestamp into seconds since the epoch.
+/// Timestamps without a time zone are taken as UTC.
+pub fn parse_timestamp(s: &str) -> Option<f64> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+    let seconds = |micros: i64| micros as f64 / 1e6;
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(seconds(dt.timestamp_micros()));
+    }
+    for fmt in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(ndt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(seconds(ndt.and_utc().timestamp_micros()));
+        }
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    Some(seconds(
+        date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_micros(),
+    ))
+}
+
+/// Parses a number with an optional binary size suffix, such as `512K`, `-3G`
+/// or `1.5MiB`.
+///
+/// Suffixes `K`, `M`, `G`, `T`, `P` and `E` are case-insensitive powers of 1024
+/// and may be followed by `B` or `iB`. Returns None if parsing fails.
+pub fn parse_human_size(bytes: &[u8]) -> Option<f64> {
+    let trimmed = trim_bytes(bytes);
+    let lower = trimmed.to_ascii_lowercase();
+    let body = lower
+        .strip_suffix(b"ib")
+        .or_else(|| lower.strip_suffix(b"b"))
+        .unwrap_or(&lower);
+
+    let (num, exponent) = match body.last() {
+        Some(b'k') => (&body[..body.len() - 1], 1),
+        Some(b'm') => (&body[..body.len() - 1], 2),
+        Some(b'g') => (&body[..body.len() - 1], 3),
+        Some(b't') => (&body[..body.len() - 1], 4),
+        Some(b'p') => (&body[..body.len() - 1], 5),
+        Some(b'e') => (&body[..body.len() - 1], 6),
+        _ => (body, 0),
+    };
+
+    fast_parse_f64(num).map(|v| v * 1024f64.powi(exponent))
+}
+
+/// Parses a human-readable byte size such as `512K`, `64M` or `1.5G`.
+///
+/// See [`parse_human_size`] for the accepted suffixes. A bare number is taken
+/// as bytes.
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    match parse_human_size(s.as_bytes()) {
+        Some(v) if v.is_finite() && v >= 0.0 => Ok(v as usize),
+        _ => Err(format!("invalid size `{}`", s)),
+    }
+}
+
 /// Formats a number with thousands separators and fixed decimal precision.
 pub fn format_number(number: f64, decimal_digits: usize) -> String {
     let sign = if number < 0.0 { -1 } else { 1 };
@@ -71,6 +133,16 @@ pub fn format_number(number: f64, decimal_digits: usize) -> String {
     }
 }
 
+/// Formats a p-value with 4 decimal places, switching to scientific notation
+/// for values that would round to zero.
+pub fn format_p_value(p: f64) -> String {
+    if p > 0.0 && p < 1e-4 {
+        format!("{:.3e}", p)
+    } else {
+        format_float(p, Some(4))
+    }
+}
+
 /// Formats a float with fixed precision, handling NaN and Inf.
 /// Trims trailing zeros for cleaner output.
 /// If precision is None, uses default formatting (full precision).
@@ -132,6 +204,53 @@ mod tests {
         assert_eq!(format_number(1234.0, 5), "1,234.00000");
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("100"), Ok(100));
+        assert_eq!(parse_size("1K"), Ok(1024));
+        assert_eq!(parse_size("64m"), Ok(64 * 1024 * 1024));
+        assert_eq!(parse_size("2GB"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1.5KiB"), Ok(1536));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("abc").is_err());
+        assert!(parse_size("-1M").is_err());
+    }
+
+    #[test]
+    fn test_parse_human_size() {
+        assert_eq!(parse_human_size(b"10"), Some(10.0));
+        assert_eq!(parse_human_size(b"10K"), Some(10240.0));
+        assert_eq!(
+            parse_human_size(b" -2g "),
+            Some(-2.0 * 1024.0 * 1024.0 * 1024.0)
+        );
+        assert_eq!(parse_human_size(b"1.5MiB"), Some(1.5 * 1024.0 * 1024.0));
+        assert_eq!(parse_human_size(b"3T"), Some(3.0 * 1024f64.powi(4)));
+        assert_eq!(parse_human_size(b"K"), None);
+        assert_eq!(parse_human_size(b"abc"), None);
+        assert_eq!(parse_human_size(b""), None);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-02"), Some(86400.0));
+        assert_eq!(parse_timestamp("1970-01-01T00:01:30"), Some(90.0));
+        assert_eq!(parse_timestamp("1970-01-01 01:00"), Some(3600.0));
+        assert_eq!(parse_timestamp("1970-01-01T01:00:00+01:00"), Some(0.0));
+        assert_eq!(parse_timestamp("2024-02-30"), None);
+        assert_eq!(parse_timestamp("12"), None);
+    }
+
+    #[test]
+    fn test_format_p_value() {
+        assert_eq!(format_p_value(0.05), "0.05");
+        assert_eq!(format_p_value(0.123456), "0.1235");
+        assert_eq!(format_p_value(1.0), "1");
+        assert_eq!(format_p_value(0.0), "0");
+        assert_eq!(format_p_value(1.234567e-7), "1.235e-7");
+        assert_eq!(format_p_value(f64::NAN), "nan");
+    }
+
     #[test]
     fn test_format_float() {
         assert_eq!(format_float(1.23456, Some(2)), "1.23");
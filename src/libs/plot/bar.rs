@@ -0,0 +1,67 @@
+//! Horizontal Unicode bars for `tva hist`.
+//!
+//! Bars are printed as plain text, so they need no terminal UI. Each cell is
+//! split into eighths with the partial block characters.
+
+/// Partial blocks, indexed by the number of eighths they fill.
+const PARTIAL_BLOCKS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+const FULL_BLOCK: &str = "█";
+
+/// Maps a value to the length of its bar, as a fraction of the longest one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarScale {
+    Linear,
+    /// `ln(1 + x)`, so that small values stay visible next to large ones.
+    Log,
+}
+
+impl BarScale {
+    /// Fraction in `[0, 1]` of the full width taken by `value`, where `max`
+    /// is the largest value of the chart.
+    pub fn ratio(&self, value: f64, max: f64) -> f64 {
+        let (value, max) = match self {
+            BarScale::Linear => (value, max),
+            BarScale::Log => (value.ln_1p(), max.ln_1p()),
+        };
+        if max > 0.0 && value > 0.0 {
+            (value / max).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Renders a bar `ratio * width` cells long, rounded to an eighth of a cell.
+/// A positive ratio always gets at least one eighth, so it can be told apart
+/// from zero.
+pub fn render_bar(ratio: f64, width: usize) -> String {
+    let mut eighths = (ratio * width as f64 * 8.0).round() as usize;
+    if ratio > 0.0 && width > 0 {
+        eighths = eighths.max(1);
+    }
+    let mut bar = FULL_BLOCK.repeat(eighths / 8);
+    bar.push_str(PARTIAL_BLOCKS[eighths % 8]);
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(BarScale::Linear.ratio(5.0, 10.0), 0.5);
+        assert_eq!(BarScale::Linear.ratio(0.0, 10.0), 0.0);
+        assert_eq!(BarScale::Linear.ratio(1.0, 0.0), 0.0);
+        assert!((BarScale::Log.ratio(9.0, 99.0) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_render_bar() {
+        assert_eq!(render_bar(1.0, 4), "████");
+        assert_eq!(render_bar(0.5, 3), "█▌");
+        assert_eq!(render_bar(0.0, 10), "");
+        // Too small to show, but not zero
+        assert_eq!(render_bar(0.001, 10), "▏");
+    }
+}
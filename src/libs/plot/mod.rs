@@ -7,6 +7,7 @@ use anyhow::Result;
 
 // Core modules
 pub mod axis;
+pub mod bar;
 pub mod binning;
 pub mod boxplot;
 pub mod data;
@@ -0,0 +1,289 @@
+//! External (bounded-memory) merge sort.
+//!
+//! Records are accumulated into an in-memory run until the configured buffer
+//! size is exceeded. The run is then sorted and spilled to an anonymous
+//! temporary file. At the end, all runs are k-way merged with a binary heap.
+//!
+//! Ties are broken by run order, and runs are created in input order, so the
+//! result is identical to a stable in-memory sort.
+
+use crate::libs::sort::key::{SortKey, SortSpec};
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::TsvRecord;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// Maximum number of runs merged at once. More runs are merged in several passes
+/// to stay well below typical open file limits.
+const MERGE_FAN_IN: usize = 64;
+
+/// Fixed per-record overhead used for buffer accounting.
+const RECORD_OVERHEAD: usize =
+    std::mem::size_of::<(SortKey, TsvRecord)>() + 2 * std::mem::size_of::<usize>();
+
+/// Sorts records with a bounded amount of memory, spilling to disk as needed.
+pub struct ExternalSorter {
+    spec: SortSpec,
+    buffer_size: usize,
+    temp_dir: Option<PathBuf>,
+    delimiter: u8,
+    run: Vec<(SortKey, TsvRecord)>,
+    run_bytes: usize,
+    spills: Vec<File>,
+}
+
+impl ExternalSorter {
+    /// Creates a sorter that keeps at most about `buffer_size` bytes of records in memory.
+    ///
+    /// Use `usize::MAX` for a purely in-memory sort.
+    pub fn new(spec: SortSpec, buffer_size: usize, delimiter: u8) -> Self {
+        Self {
+            spec,
+            buffer_size,
+            temp_dir: None,
+            delimiter,
+            run: Vec::new(),
+            run_bytes: 0,
+            spills: Vec::new(),
+        }
+    }
+
+    /// Sets the directory for temporary run files (default: the system temp dir).
+    pub fn with_temp_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.temp_dir = dir;
+        self
+    }
+
+    /// Returns the number of runs spilled to disk so far.
+    pub fn spilled_runs(&self) -> usize {
+        self.spills.len()
+    }
+
+    /// Adds a record, spilling the current run if the buffer is full.
+    pub fn push(&mut self, record: TsvRecord) -> io::Result<()> {
+        let key = self.spec.key(&record);
+        self.run_bytes += record.as_line().len()
+            + record.len() * std::mem::size_of::<usize>()
+            + key.heap_size()
+            + RECORD_OVERHEAD;
+        self.run.push((key, record));
+
+        if self.run_bytes >= self.buffer_size {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Sorts everything pushed so far and writes it to `writer`, one record per line.
+    pub fn finish<W: Write>(mut self, writer: &mut W) -> io::Result<()> {
+        self.sort_run();
+
+        if self.spills.is_empty() {
+            for (_, record) in &self.run {
+                write_record(writer, record)?;
+            }
+            return Ok(());
+        }
+
+        // Reduce the number of runs until they can be merged in a single pass.
+        // The in-memory run counts as one source in the final merge.
+        while self.spills.len() + 1 > MERGE_FAN_IN {
+            let mut merged = Vec::new();
+            let spills = std::mem::take(&mut self.spills);
+            let mut iter = spills.into_iter().peekable();
+            while iter.peek().is_some() {
+                let chunk: Vec<File> = iter.by_ref().take(MERGE_FAN_IN).collect();
+                let sources = chunk.into_iter().map(RunSource::file).collect();
+                let file = self.temp_file()?;
+                let mut out = BufWriter::with_capacity(256 * 1024, file);
+                self.merge(sources, &mut out)?;
+                merged.push(rewind(out)?);
+            }
+            self.spills = merged;
+        }
+
+        let mut sources: Vec<RunSource> = std::mem::take(&mut self.spills)
+            .into_iter()
+            .map(RunSource::file)
+            .collect();
+        sources.push(RunSource::Memory(std::mem::take(&mut self.run).into_iter()));
+        self.merge(sources, writer)
+    }
+
+    fn sort_run(&mut self) {
+        self.run.sort_by(|(ka, _), (kb, _)| ka.cmp(kb));
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        if self.run.is_empty() {
+            return Ok(());
+        }
+        self.sort_run();
+
+        let file = self.temp_file()?;
+        let mut out = BufWriter::with_capacity(256 * 1024, file);
+        for (_, record) in self.run.drain(..) {
+            write_record(&mut out, &record)?;
+        }
+        self.spills.push(rewind(out)?);
+        self.run_bytes = 0;
+        Ok(())
+    }
+
+    fn temp_file(&self) -> io::Result<File> {
+        match &self.temp_dir {
+            Some(dir) => tempfile::tempfile_in(dir),
+            None => tempfile::tempfile(),
+        }
+    }
+
+    /// K-way merges sorted sources into `writer`. Earlier sources win ties.
+    fn merge<W: Write>(
+        &self,
+        mut sources: Vec<RunSource>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let mut heads: Vec<Option<TsvRecord>> = Vec::with_capacity(sources.len());
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+
+        for (i, source) in sources.iter_mut().enumerate() {
+            match source.next(&self.spec, self.delimiter)? {
+                Some((key, record)) => {
+                    heap.push(Reverse((key, i)));
+                    heads.push(Some(record));
+                }
+                None => heads.push(None),
+            }
+        }
+
+        while let Some(Reverse((_, i))) = heap.pop() {
+            if let Some(record) = heads[i].take() {
+                write_record(writer, &record)?;
+            }
+            if let Some((key, record)) = sources[i].next(&self.spec, self.delimiter)? {
+                heap.push(Reverse((key, i)));
+                heads[i] = Some(record);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A sorted run being consumed during a merge.
+enum RunSource {
+    File(TsvReader<File>),
+    Memory(std::vec::IntoIter<(SortKey, TsvRecord)>),
+}
+
+impl RunSource {
+    fn file(file: File) -> Self {
+        RunSource::File(TsvReader::with_capacity(file, 256 * 1024))
+    }
+
+    fn next(
+        &mut self,
+        spec: &SortSpec,
+        delimiter: u8,
+    ) -> io::Result<Option<(SortKey, TsvRecord)>> {
+        match self {
+            RunSource::File(reader) => match reader.next_row(delimiter)? {
+                Some(row) => {
+                    let record = if row.line.is_empty() {
+                        TsvRecord::new()
+                    } else {
+                        TsvRecord::from_row(&row)
+                    };
+                    Ok(Some((spec.key(&record), record)))
+                }
+                None => Ok(None),
+            },
+            RunSource::Memory(iter) => Ok(iter.next()),
+        }
+    }
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &TsvRecord) -> io::Result<()> {
+    writer.write_all(record.as_line())?;
+    writer.write_all(b"\n")
+}
+
+fn rewind(out: BufWriter<File>) -> io::Result<File> {
+    let mut file = out.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::sort::key::{Comparison, KeyField, KeyOptions};
+
+    fn spec(comparison: Comparison, reverse: bool) -> SortSpec {
+        SortSpec {
+            keys: vec![KeyField {
+                index: 1,
+                options: KeyOptions {
+                    comparison,
+                    reverse,
+                },
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn sort_lines(
+        lines: &[&str],
+        spec: SortSpec,
+        buffer_size: usize,
+    ) -> (String, usize) {
+        let mut sorter = ExternalSorter::new(spec, buffer_size, b'\t');
+        for line in lines {
+            let mut record = TsvRecord::new();
+            record.parse_line(line.as_bytes(), b'\t');
+            sorter.push(record).unwrap();
+        }
+        let spilled = sorter.spilled_runs();
+        let mut out = Vec::new();
+        sorter.finish(&mut out).unwrap();
+        (String::from_utf8(out).unwrap(), spilled)
+    }
+
+    #[test]
+    fn test_in_memory() {
+        let spec = spec(Comparison::Lexical, false);
+        let (out, spilled) = sort_lines(&["c", "a", "b"], spec, usize::MAX);
+        assert_eq!(out, "a\nb\nc\n");
+        assert_eq!(spilled, 0);
+    }
+
+    #[test]
+    fn test_spill_every_record() {
+        let spec = spec(Comparison::Numeric, false);
+        let lines: Vec<String> = (0..200).rev().map(|i| i.to_string()).collect();
+        let refs: Vec<&str> = lines.iter().map(|s| s.as_str()).collect();
+        let (out, spilled) = sort_lines(&refs, spec, 1);
+        assert_eq!(spilled, 200);
+
+        let expected: String = (0..200).map(|i| format!("{}\n", i)).collect();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_stable_across_runs() {
+        let spec = spec(Comparison::Lexical, false);
+        let lines = ["b\t1", "a\t1", "b\t2", "a\t2", "b\t3", "a\t3"];
+        let (out, spilled) = sort_lines(&lines, spec, 150);
+        assert!(spilled > 0);
+        assert_eq!(out, "a\t1\na\t2\na\t3\nb\t1\nb\t2\nb\t3\n");
+    }
+
+    #[test]
+    fn test_reverse_with_spills() {
+        let spec = spec(Comparison::Lexical, true);
+        let (out, _) = sort_lines(&["a", "c", "b", "", "d"], spec, 1);
+        assert_eq!(out, "d\nc\nb\na\n\n");
+    }
+}
@@ -0,0 +1,535 @@
+//! Sort key construction and comparison.
+//!
+//! A [`SortSpec`] is a list of [`KeyField`]s, each with its own [`Comparison`]
+//! and direction. The spec computes a [`SortKey`] once per record; the key
+//! carries everything needed to order it, so keys can be stored in heaps or
+//! compared across sorted runs.
+//!
+//! Key arguments use the syntax `FIELDS[:MODIFIERS]`, e.g. `chrom`, `score:nr`
+//! or `2,4-5:V`. See [`KeyOptions::parse_modifiers`] for the modifier letters.
+
+use crate::libs::number::{fast_parse_f64, parse_human_size};
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::key::KeyBuffer;
+use crate::libs::tsv::record::{Row, TsvRecord, TsvRow};
+use smallvec::SmallVec;
+use std::cmp::Ordering;
+
+/// How the values of a key field are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Comparison {
+    /// Byte-wise lexicographic order.
+    #[default]
+    Lexical,
+    /// Lexicographic order ignoring ASCII case.
+    IgnoreCase,
+    /// Numeric order. Non-numeric values compare as 0.
+    Numeric,
+    /// Numeric order including `inf`/`nan`. Non-numeric values sort first.
+    GeneralNumeric,
+    /// Natural (version) order: digit runs compare by numeric value.
+    Natural,
+    /// Numeric order with size suffixes, e.g. `10K` < `3G`.
+    HumanSize,
+}
+
+/// Comparison and direction of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyOptions {
+    pub comparison: Comparison,
+    pub reverse: bool,
+}
+
+impl KeyOptions {
+    /// Parses modifier letters, as in `score:nr`.
+    ///
+    /// * `n`: numeric, `g`: general numeric, `V`: natural/version,
+    ///   `h`: human sizes, `f`: ignore case
+    /// * `r`: reverse
+    ///
+    /// At most one comparison letter may be given.
+    pub fn parse_modifiers(mods: &str) -> Result<Self, String> {
+        let mut opts = KeyOptions::default();
+        let mut comparison = None;
+        for c in mods.chars() {
+            let cmp = match c {
+                'n' => Comparison::Numeric,
+                'g' => Comparison::GeneralNumeric,
+                'V' => Comparison::Natural,
+                'h' => Comparison::HumanSize,
+                'f' => Comparison::IgnoreCase,
+                'r' => {
+                    opts.reverse = true;
+                    continue;
+                }
+                _ => return Err(format!("unknown sort modifier `{}` in `{}`", c, mods)),
+            };
+            if comparison.is_some_and(|prev| prev != cmp) {
+                return Err(format!("conflicting sort modifiers in `{}`", mods));
+            }
+            comparison = Some(cmp);
+        }
+        opts.comparison = comparison.unwrap_or_default();
+        Ok(opts)
+    }
+}
+
+/// Splits a key argument into its field list and optional modifiers.
+///
+/// The text after the last `:` is treated as modifiers only if it consists
+/// solely of modifier letters, so field names containing `:` still work.
+pub fn split_key_arg(arg: &str) -> (&str, Option<&str>) {
+    if let Some(pos) = arg.rfind(':') {
+        let mods = &arg[pos + 1..];
+        if !mods.is_empty() && mods.chars().all(|c| "ngVhfr".contains(c)) {
+            return (&arg[..pos], Some(mods));
+        }
+    }
+    (arg, None)
+}
+
+/// A single key field: 1-based index plus how to compare it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyField {
+    pub index: usize,
+    pub options: KeyOptions,
+}
+
+/// A value extracted from one key field.
+#[derive(Debug, Clone)]
+pub enum KeyValue {
+    /// Raw (or case-folded) field bytes, compared lexicographically.
+    Text(KeyBuffer),
+    /// Parsed numeric value; never NaN. Non-numeric fields, `nan` included,
+    /// are 0.
+    Number(f64),
+    /// General numeric value, with NaN sorting before all numbers.
+    General(f64),
+    /// Raw field bytes, compared in natural order.
+    Natural(KeyBuffer),
+}
+
+impl KeyValue {
+    fn new(field: &[u8], comparison: Comparison) -> Self {
+        match comparison {
+            Comparison::Lexical => KeyValue::Text(KeyBuffer::from_slice(field)),
+            Comparison::IgnoreCase => {
+                KeyValue::Text(field.iter().map(|b| b.to_ascii_lowercase()).collect())
+            }
+            Comparison::Numeric => {
+                KeyValue::Number(number_or_zero(fast_parse_f64(field)))
+            }
+            Comparison::GeneralNumeric => {
+                KeyValue::General(fast_parse_f64(field).unwrap_or(f64::NAN))
+            }
+            Comparison::Natural => KeyValue::Natural(KeyBuffer::from_slice(field)),
+            Comparison::HumanSize => {
+                KeyValue::Number(number_or_zero(parse_human_size(field)))
+            }
+        }
+    }
+
+    /// The value an empty field would have under the same comparison.
+    fn empty_like(&self) -> Self {
+        match self {
+            KeyValue::Text(_) => KeyValue::Text(KeyBuffer::new()),
+            KeyValue::Number(_) => KeyValue::Number(0.0),
+            KeyValue::General(_) => KeyValue::General(f64::NAN),
+            KeyValue::Natural(_) => KeyValue::Natural(KeyBuffer::new()),
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        match self {
+            KeyValue::Text(buf) | KeyValue::Natural(buf) if buf.spilled() => {
+                buf.capacity()
+            }
+            _ => 0,
+        }
+    }
+
+    fn cmp_value(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (KeyValue::Text(a), KeyValue::Text(b)) => a.as_slice().cmp(b.as_slice()),
+            (KeyValue::Number(a), KeyValue::Number(b)) => {
+                a.partial_cmp(b).unwrap_or(Ordering::Equal)
+            }
+            (KeyValue::General(a), KeyValue::General(b)) => {
+                match (a.is_nan(), b.is_nan()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (false, false) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+                }
+            }
+            (KeyValue::Natural(a), KeyValue::Natural(b)) => natural_cmp(a, b),
+            // Values of a single key field always share a comparison.
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// Maps unparsable values and NaN to 0, keeping numeric keys totally ordered.
+fn number_or_zero(value: Option<f64>) -> f64 {
+    value.filter(|v| !v.is_nan()).unwrap_or(0.0)
+}
+
+/// Compares two byte strings in natural order.
+///
+/// Runs of ASCII digits are compared by numeric value (ignoring leading zeros),
+/// everything else byte-wise. Ties are broken by plain byte order.
+pub fn natural_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let si = i;
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            let sj = j;
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+            let da = trim_leading_zeros(&a[si..i]);
+            let db = trim_leading_zeros(&b[sj..j]);
+            let ord = da.len().cmp(&db.len()).then_with(|| da.cmp(db));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        } else {
+            if a[i] != b[j] {
+                return a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j)).then_with(|| a.cmp(b))
+}
+
+fn trim_leading_zeros(digits: &[u8]) -> &[u8] {
+    let start = digits
+        .iter()
+        .position(|&d| d != b'0')
+        .unwrap_or(digits.len());
+    &digits[start..]
+}
+
+/// One component of a sort key.
+#[derive(Debug, Clone)]
+pub struct KeyPart {
+    pub value: KeyValue,
+    pub reverse: bool,
+}
+
+/// A precomputed sort key for one record.
+#[derive(Debug, Clone)]
+pub struct SortKey {
+    parts: SmallVec<[KeyPart; 2]>,
+}
+
+impl SortKey {
+    /// Approximate heap memory used by this key, for buffer accounting.
+    pub fn heap_size(&self) -> usize {
+        let parts = if self.parts.spilled() {
+            self.parts.capacity() * std::mem::size_of::<KeyPart>()
+        } else {
+            0
+        };
+        parts
+            + self
+                .parts
+                .iter()
+                .map(|p| p.value.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let n = self.parts.len().max(other.parts.len());
+        for i in 0..n {
+            // A missing part (only possible in whole-record mode) compares like an
+            // empty field.
+            let (ord, reverse) = match (self.parts.get(i), other.parts.get(i)) {
+                (Some(a), Some(b)) => (a.value.cmp_value(&b.value), a.reverse),
+                (Some(a), None) => (a.value.cmp_value(&a.value.empty_like()), a.reverse),
+                (None, Some(b)) => (b.value.empty_like().cmp_value(&b.value), b.reverse),
+                (None, None) => (Ordering::Equal, false),
+            };
+            if ord != Ordering::Equal {
+                return if reverse { ord.reverse() } else { ord };
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+/// Describes how records are ordered.
+#[derive(Debug, Clone, Default)]
+pub struct SortSpec {
+    /// Key fields in priority order. Empty means all fields of the record.
+    pub keys: Vec<KeyField>,
+    /// Options applied to every field when `keys` is empty.
+    pub whole_record: KeyOptions,
+}
+
+impl SortSpec {
+    /// Builds a spec from `--key` arguments.
+    ///
+    /// Keys without modifiers use `default`; keys with modifiers use only their own.
+    /// Field lists are resolved with `resolver`, so header names work.
+    pub fn from_key_args(
+        args: &[String],
+        default: KeyOptions,
+        resolver: &FieldResolver,
+    ) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for arg in args {
+            let (fields, mods) = split_key_arg(arg);
+            let options = match mods {
+                Some(m) => KeyOptions::parse_modifiers(m)?,
+                None => default,
+            };
+            if fields.split(',').any(|p| p.trim().is_empty())
+                && !fields.trim().is_empty()
+            {
+                return Err(format!("empty key list element in `{}`", arg));
+            }
+            for index in resolver.resolve(fields)? {
+                keys.push(KeyField { index, options });
+            }
+        }
+        Ok(Self {
+            keys,
+            whole_record: default,
+        })
+    }
+
+    /// Builds the sort key for a record. Missing fields are treated as empty.
+    pub fn key(&self, record: &TsvRecord) -> SortKey {
+        self.build_key(record.len(), |i| record.get(i))
+    }
+
+    /// Builds the sort key for a borrowed row, without copying the row.
+    pub fn key_from_row(&self, row: &TsvRow) -> SortKey {
+        self.build_key(row.field_count(), |i| row.get_bytes(i + 1))
+    }
+
+    /// `field` takes a 0-based index; `len` is the number of fields.
+    fn build_key<'a>(
+        &self,
+        len: usize,
+        field: impl Fn(usize) -> Option<&'a [u8]>,
+    ) -> SortKey {
+        let mut parts = SmallVec::new();
+        if self.keys.is_empty() {
+            for i in 0..len {
+                parts.push(KeyPart {
+                    value: KeyValue::new(
+                        field(i).unwrap_or(b""),
+                        self.whole_record.comparison,
+                    ),
+                    reverse: self.whole_record.reverse,
+                });
+            }
+        } else {
+            for key in &self.keys {
+                parts.push(KeyPart {
+                    value: KeyValue::new(
+                        field(key.index - 1).unwrap_or(b""),
+                        key.options.comparison,
+                    ),
+                    reverse: key.options.reverse,
+                });
+            }
+        }
+        SortKey { parts }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(line: &[u8]) -> TsvRecord {
+        let mut r = TsvRecord::new();
+        r.parse_line(line, b'\t');
+        r
+    }
+
+    fn spec(index: usize, comparison: Comparison, reverse: bool) -> SortSpec {
+        SortSpec {
+            keys: vec![KeyField {
+                index,
+                options: KeyOptions {
+                    comparison,
+                    reverse,
+                },
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lexical_key() {
+        let spec = spec(2, Comparison::Lexical, false);
+        let a = spec.key(&record(b"x\tapple"));
+        let b = spec.key(&record(b"y\tbanana"));
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_numeric_key() {
+        let spec = spec(1, Comparison::Numeric, false);
+        let a = spec.key(&record(b"10"));
+        let b = spec.key(&record(b"9"));
+        let c = spec.key(&record(b"abc"));
+        assert!(b < a);
+        assert!(c < b); // non-numeric -> 0
+
+        // NaN compares as 0 as well, so the order stays total
+        let nan = spec.key(&record(b"nan"));
+        let neg = spec.key(&record(b"-1"));
+        assert_eq!(nan, c);
+        assert!(neg < nan);
+        assert!(nan < b);
+    }
+
+    #[test]
+    fn test_general_numeric_key() {
+        let spec = spec(1, Comparison::GeneralNumeric, false);
+        let text = spec.key(&record(b"abc"));
+        let neg = spec.key(&record(b"-1e3"));
+        let inf = spec.key(&record(b"inf"));
+        assert!(text < neg);
+        assert!(neg < inf);
+    }
+
+    #[test]
+    fn test_human_size_key() {
+        let spec = spec(1, Comparison::HumanSize, false);
+        let k = spec.key(&record(b"10K"));
+        let m = spec.key(&record(b"2M"));
+        let g = spec.key(&record(b"3G"));
+        let plain = spec.key(&record(b"999"));
+        assert!(plain < k);
+        assert!(k < m);
+        assert!(m < g);
+    }
+
+    #[test]
+    fn test_ignore_case_key() {
+        let spec = spec(1, Comparison::IgnoreCase, false);
+        assert_eq!(spec.key(&record(b"ABC")), spec.key(&record(b"abc")));
+        assert!(spec.key(&record(b"a")) < spec.key(&record(b"B")));
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp(b"chr2", b"chr10"), Ordering::Less);
+        assert_eq!(natural_cmp(b"v1.10", b"v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp(b"a", b"a1"), Ordering::Less);
+        assert_eq!(natural_cmp(b"x01", b"x1"), Ordering::Less); // tie broken by bytes
+        assert_eq!(natural_cmp(b"abc", b"abc"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_reverse_key() {
+        let spec = spec(1, Comparison::Lexical, true);
+        let a = spec.key(&record(b"a"));
+        let b = spec.key(&record(b"b"));
+        assert!(b < a);
+    }
+
+    #[test]
+    fn test_mixed_directions() {
+        let spec = SortSpec::from_key_args(
+            &["1".to_string(), "2:nr".to_string()],
+            KeyOptions::default(),
+            &FieldResolver::new(None, '\t'),
+        )
+        .unwrap();
+        let a = spec.key(&record(b"chr1\t5"));
+        let b = spec.key(&record(b"chr1\t10"));
+        let c = spec.key(&record(b"chr2\t50"));
+        assert!(b < a);
+        assert!(a < c);
+    }
+
+    #[test]
+    fn test_whole_record_key_with_missing_fields() {
+        let spec = SortSpec::default();
+        let a = spec.key(&record(b"b"));
+        let b = spec.key(&record(b"b\t"));
+        let c = spec.key(&record(b"b\tx"));
+        assert_eq!(a, b);
+        assert!(b < c);
+    }
+
+    #[test]
+    fn test_key_from_row_matches_record() {
+        let spec = SortSpec::default();
+        let line = b"b\t10\tx";
+        let ends = [1, 4, 6];
+        let row = TsvRow { line, ends: &ends };
+        assert_eq!(spec.key_from_row(&row), spec.key(&record(line)));
+        assert_eq!(spec.key_from_row(&row).parts.len(), 3);
+    }
+
+    #[test]
+    fn test_split_key_arg() {
+        assert_eq!(split_key_arg("score:nr"), ("score", Some("nr")));
+        assert_eq!(split_key_arg("2,4-5:V"), ("2,4-5", Some("V")));
+        assert_eq!(split_key_arg("chrom"), ("chrom", None));
+        assert_eq!(split_key_arg("a:b:x"), ("a:b:x", None));
+        assert_eq!(split_key_arg("time:"), ("time:", None));
+    }
+
+    #[test]
+    fn test_parse_modifiers() {
+        let opts = KeyOptions::parse_modifiers("nr").unwrap();
+        assert_eq!(opts.comparison, Comparison::Numeric);
+        assert!(opts.reverse);
+        assert!(KeyOptions::parse_modifiers("nV").is_err());
+        assert!(KeyOptions::parse_modifiers("x").is_err());
+    }
+
+    #[test]
+    fn test_from_key_args_with_header() {
+        let resolver = FieldResolver::new(Some(b"chrom\tscore\tsample".to_vec()), '\t');
+        let spec = SortSpec::from_key_args(
+            &["chrom".to_string(), "score:nr".to_string()],
+            KeyOptions::default(),
+            &resolver,
+        )
+        .unwrap();
+        assert_eq!(spec.keys.len(), 2);
+        assert_eq!(spec.keys[0].index, 1);
+        assert_eq!(spec.keys[1].index, 2);
+        assert_eq!(spec.keys[1].options.comparison, Comparison::Numeric);
+        assert!(spec.keys[1].options.reverse);
+
+        let err = SortSpec::from_key_args(
+            &["1,,2".to_string()],
+            KeyOptions::default(),
+            &resolver,
+        )
+        .unwrap_err();
+        assert!(err.contains("empty key list element"));
+    }
+}
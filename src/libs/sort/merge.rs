@@ -0,0 +1,173 @@
+//! Streaming merge of already-sorted inputs.
+//!
+//! [`SortedReader`] yields records with their sort keys and verifies that each
+//! key does not sort before the previous one, failing fast with the offending
+//! line number otherwise. [`merge_sorted`] k-way merges several such readers.
+
+use crate::libs::sort::key::{SortKey, SortSpec};
+use crate::libs::tsv::reader::TsvReader;
+use crate::libs::tsv::record::TsvRecord;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Read, Write};
+
+/// Reads records from an input that must already be sorted by a [`SortSpec`].
+pub struct SortedReader<R> {
+    name: String,
+    reader: TsvReader<R>,
+    line: u64,
+    prev: Option<SortKey>,
+}
+
+impl<R: Read> SortedReader<R> {
+    /// Wraps a reader positioned after `skipped_lines` lines (e.g. the header),
+    /// which are only used for line numbers in error messages.
+    pub fn new(name: &str, reader: TsvReader<R>, skipped_lines: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            reader,
+            line: skipped_lines,
+            prev: None,
+        }
+    }
+
+    /// The 1-based line number of the last record returned.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Returns the next record and its key.
+    ///
+    /// # Errors
+    /// Returns `InvalidData` if the record sorts before the previous one.
+    pub fn next_record(
+        &mut self,
+        spec: &SortSpec,
+        delimiter: u8,
+    ) -> io::Result<Option<(SortKey, TsvRecord)>> {
+        let Some(row) = self.reader.next_row(delimiter)? else {
+            return Ok(None);
+        };
+        self.line += 1;
+
+        let record = if row.line.is_empty() {
+            TsvRecord::new()
+        } else {
+            TsvRecord::from_row(&row)
+        };
+        let key = spec.key(&record);
+
+        if let Some(prev) = &self.prev {
+            if key < *prev {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{}: input is not sorted: line {} sorts before line {}",
+                        self.name,
+                        self.line,
+                        self.line - 1
+                    ),
+                ));
+            }
+        }
+        self.prev = Some(key.clone());
+
+        Ok(Some((key, record)))
+    }
+}
+
+/// K-way merges sorted readers into `writer`, one record per line.
+///
+/// Records with equal keys are output in input order (earlier readers first).
+pub fn merge_sorted<R: Read, W: Write>(
+    mut sources: Vec<SortedReader<R>>,
+    spec: &SortSpec,
+    delimiter: u8,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut heads: Vec<Option<TsvRecord>> = Vec::with_capacity(sources.len());
+    let mut heap = BinaryHeap::with_capacity(sources.len());
+
+    for (i, source) in sources.iter_mut().enumerate() {
+        match source.next_record(spec, delimiter)? {
+            Some((key, record)) => {
+                heap.push(Reverse((key, i)));
+                heads.push(Some(record));
+            }
+            None => heads.push(None),
+        }
+    }
+
+    while let Some(Reverse((_, i))) = heap.pop() {
+        if let Some(record) = heads[i].take() {
+            writer.write_all(record.as_line())?;
+            writer.write_all(b"\n")?;
+        }
+        if let Some((key, record)) = sources[i].next_record(spec, delimiter)? {
+            heap.push(Reverse((key, i)));
+            heads[i] = Some(record);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::sort::key::{Comparison, KeyField, KeyOptions};
+    use std::io::Cursor;
+
+    fn source<'a>(name: &str, data: &'a [u8]) -> SortedReader<Cursor<&'a [u8]>> {
+        SortedReader::new(name, TsvReader::new(Cursor::new(data)), 0)
+    }
+
+    fn numeric_spec() -> SortSpec {
+        SortSpec {
+            keys: vec![KeyField {
+                index: 2,
+                options: KeyOptions {
+                    comparison: Comparison::Numeric,
+                    reverse: false,
+                },
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_sorted() {
+        let sources = vec![
+            source("a", b"a\t1\na\t5\na\t10\n"),
+            source("b", b"b\t2\nb\t5\n"),
+            source("c", b""),
+        ];
+        let mut out = Vec::new();
+        merge_sorted(sources, &numeric_spec(), b'\t', &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a\t1\nb\t2\na\t5\nb\t5\na\t10\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_detects_unsorted() {
+        let sources = vec![source("a", b"a\t1\na\t5\n"), source("b", b"b\t9\nb\t2\n")];
+        let mut out = Vec::new();
+        let err = merge_sorted(sources, &numeric_spec(), b'\t', &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .contains("b: input is not sorted: line 2 sorts before line 1"));
+    }
+
+    #[test]
+    fn test_sorted_reader_line_numbers() {
+        let mut reader =
+            SortedReader::new("x", TsvReader::new(Cursor::new(&b"a\t3\na\t1\n"[..])), 1);
+        let spec = numeric_spec();
+        assert!(reader.next_record(&spec, b'\t').unwrap().is_some());
+        assert_eq!(reader.line(), 2);
+        let err = reader.next_record(&spec, b'\t').unwrap_err();
+        assert!(err.to_string().contains("line 3 sorts before line 2"));
+    }
+}
@@ -0,0 +1,22 @@
+//! Sorting primitives for `tva sort` and `tva merge`.
+//!
+//! This module provides:
+//!
+//! - **Key**: Per-field comparisons (lexical, numeric, natural, human sizes, ...)
+//!   and per-record sort keys that are self-contained and totally ordered, so
+//!   they can be compared without access to the originating [`SortSpec`].
+//! - **External**: Bounded-memory sorting that spills sorted runs to temporary
+//!   files and k-way merges them ([`ExternalSorter`]).
+//! - **Top**: Bounded-heap top-N selection, optionally per group ([`TopSelector`]).
+//! - **Merge**: Streaming k-way merge of already-sorted inputs with order
+//!   verification ([`SortedReader`], [`merge_sorted`]).
+
+pub mod external;
+pub mod key;
+pub mod merge;
+pub mod top;
+
+pub use external::ExternalSorter;
+pub use key::{Comparison, KeyField, KeyOptions, SortKey, SortSpec};
+pub use merge::{merge_sorted, SortedReader};
+pub use top::TopSelector;
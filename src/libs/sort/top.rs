@@ -0,0 +1,189 @@
+//! Top-N partial sort.
+//!
+//! Keeps the `n` smallest records (according to a [`SortSpec`]) in a bounded
+//! max-heap while streaming, optionally one heap per group. Memory is O(n) per
+//! group, and the output is identical to a full stable sort followed by `head`
+//! (per group).
+
+use crate::libs::sort::key::{SortKey, SortSpec};
+use crate::libs::tsv::key::{KeyBuffer, KeyExtractor};
+use crate::libs::tsv::record::{TsvRecord, TsvRow};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{self, Write};
+
+/// A kept record. Ordered by key, then by input position so ties keep input order.
+struct TopEntry {
+    key: SortKey,
+    seq: u64,
+    record: TsvRecord,
+}
+
+impl Ord for TopEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for TopEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for TopEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TopEntry {}
+
+/// Selects the first `n` records in sort order, overall or per group.
+pub struct TopSelector {
+    spec: SortSpec,
+    n: usize,
+    group_by: Option<KeyExtractor>,
+    groups: HashMap<KeyBuffer, BinaryHeap<TopEntry>>,
+    seq: u64,
+}
+
+impl TopSelector {
+    /// Creates a selector keeping `n` records, per group if `group_by` is set.
+    pub fn new(spec: SortSpec, n: usize, group_by: Option<KeyExtractor>) -> Self {
+        Self {
+            spec,
+            n,
+            group_by,
+            groups: HashMap::new(),
+            seq: 0,
+        }
+    }
+
+    /// Offers a row. The row is only copied if it makes it into the top `n`.
+    pub fn push_row(&mut self, row: &TsvRow, delimiter: u8) -> io::Result<()> {
+        let seq = self.seq;
+        self.seq += 1;
+        if self.n == 0 {
+            return Ok(());
+        }
+
+        let group = match self.group_by.as_mut() {
+            Some(extractor) => extractor
+                .extract_from_row(row, delimiter)
+                .map_err(|idx| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line has fewer than {} fields", idx),
+                    )
+                })?
+                .into_owned(),
+            None => KeyBuffer::new(),
+        };
+
+        let key = self.spec.key_from_row(row);
+        let heap = self.groups.entry(group).or_default();
+
+        if heap.len() >= self.n {
+            // Later rows lose ties, so only strictly smaller keys can enter
+            match heap.peek() {
+                Some(max) if key < max.key => {
+                    heap.pop();
+                }
+                _ => return Ok(()),
+            }
+        }
+
+        let record = if row.line.is_empty() {
+            TsvRecord::new()
+        } else {
+            TsvRecord::from_row(row)
+        };
+        heap.push(TopEntry { key, seq, record });
+        Ok(())
+    }
+
+    /// Writes all kept records in sort order, one per line.
+    pub fn finish<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        let mut entries: Vec<TopEntry> = self
+            .groups
+            .into_values()
+            .flat_map(|heap| heap.into_vec())
+            .collect();
+        entries.sort_unstable();
+
+        for entry in entries {
+            writer.write_all(entry.record.as_line())?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::sort::key::{Comparison, KeyField, KeyOptions};
+
+    fn run(lines: &[&str], spec: SortSpec, n: usize, group: Option<usize>) -> String {
+        let group_by = group.map(|idx| KeyExtractor::new(Some(vec![idx]), false, true));
+        let mut top = TopSelector::new(spec, n, group_by);
+        for line in lines {
+            let mut ends: Vec<usize> =
+                memchr::memchr_iter(b'\t', line.as_bytes()).collect();
+            ends.push(line.len());
+            let row = TsvRow {
+                line: line.as_bytes(),
+                ends: &ends,
+            };
+            top.push_row(&row, b'\t').unwrap();
+        }
+        let mut out = Vec::new();
+        top.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn numeric_desc(index: usize) -> SortSpec {
+        SortSpec {
+            keys: vec![KeyField {
+                index,
+                options: KeyOptions {
+                    comparison: Comparison::Numeric,
+                    reverse: true,
+                },
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_top_n() {
+        let lines = ["a\t3", "b\t9", "c\t1", "d\t7", "e\t5"];
+        assert_eq!(run(&lines, numeric_desc(2), 2, None), "b\t9\nd\t7\n");
+    }
+
+    #[test]
+    fn test_top_n_ties_keep_input_order() {
+        let lines = ["a\t5", "b\t5", "c\t5", "d\t1"];
+        assert_eq!(run(&lines, numeric_desc(2), 2, None), "a\t5\nb\t5\n");
+    }
+
+    #[test]
+    fn test_top_n_larger_than_input() {
+        let lines = ["a\t1", "b\t2"];
+        assert_eq!(run(&lines, numeric_desc(2), 10, None), "b\t2\na\t1\n");
+    }
+
+    #[test]
+    fn test_top_zero() {
+        assert_eq!(run(&["a\t1"], numeric_desc(2), 0, None), "");
+    }
+
+    #[test]
+    fn test_top_per_group() {
+        let lines = ["x\t1", "y\t5", "x\t3", "y\t2", "x\t2", "y\t9"];
+        assert_eq!(run(&lines, numeric_desc(2), 1, Some(1)), "y\t9\nx\t3\n");
+    }
+}
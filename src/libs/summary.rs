@@ -0,0 +1,333 @@
+//! Column profiles for `tva summary`.
+//!
+//! Counts, numeric min/max, mean and stdev come from the `tva stats`
+//! calculators, run over every profiled column in a single [`StatsProcessor`].
+//! [`Profiler`] adds what the calculators do not track: the inferred type,
+//! distinct values of non-missing fields, string min/max and length, and the
+//! most frequent values.
+
+use crate::libs::aggregation::frequent::FrequentItems;
+use crate::libs::aggregation::hll::{HyperLogLog, DEFAULT_PRECISION};
+use crate::libs::aggregation::{
+    Aggregator, OpKind, Operation, StatsConfig, StatsProcessor,
+};
+use crate::libs::number::{fast_parse_f64, parse_timestamp};
+use crate::libs::tsv::record::Row;
+
+/// Output columns of the report, one line per profiled column.
+pub const REPORT_COLUMNS: [&str; 11] = [
+    "field",
+    "type",
+    "count",
+    "missing",
+    "nunique",
+    "min",
+    "max",
+    "mean",
+    "stdev",
+    "max_length",
+    "top",
+];
+
+/// Calculators run for each column, in this order.
+const COLUMN_OPS: [OpKind; 6] = [
+    OpKind::NotMissingCount,
+    OpKind::MissingCount,
+    OpKind::Min,
+    OpKind::Max,
+    OpKind::Mean,
+    OpKind::Stdev,
+];
+
+/// Counters kept per reported top value. The counts are exact for columns
+/// with at most `100 * top` distinct values.
+const TOP_COUNTERS: usize = 100;
+
+/// Type of a column, the narrowest that fits all its non-missing values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    /// No non-missing values.
+    Empty,
+    Int,
+    Float,
+    /// ISO 8601 dates or timestamps.
+    Date,
+    String,
+}
+
+impl ValueType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueType::Empty => "empty",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Date => "date",
+            ValueType::String => "string",
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, ValueType::Int | ValueType::Float)
+    }
+}
+
+/// State of one column beyond what the calculators keep.
+struct ColumnState {
+    name: String,
+    field_idx: usize,
+    seen: bool,
+    is_int: bool,
+    is_float: bool,
+    is_date: bool,
+    min: Vec<u8>,
+    max: Vec<u8>,
+    max_length: usize,
+    hll: HyperLogLog,
+    /// Most frequent values, when reported.
+    top: Option<FrequentItems>,
+}
+
+impl ColumnState {
+    fn new(field_idx: usize, name: String, top: usize) -> Self {
+        Self {
+            name,
+            field_idx,
+            seen: false,
+            is_int: true,
+            is_float: true,
+            is_date: true,
+            min: Vec::new(),
+            max: Vec::new(),
+            max_length: 0,
+            hll: HyperLogLog::new(DEFAULT_PRECISION),
+            top: (top > 0).then(|| FrequentItems::new(TOP_COUNTERS * top)),
+        }
+    }
+
+    fn update(&mut self, value: &[u8]) {
+        if value.is_empty() {
+            return;
+        }
+        let text = std::str::from_utf8(value).ok();
+
+        if self.is_int {
+            self.is_int = text.is_some_and(|s| s.trim().parse::<i64>().is_ok());
+        }
+        let is_number = fast_parse_f64(value).is_some();
+        self.is_float &= is_number;
+        // Only tried while no value has ruled dates out, as parsing is costly
+        if self.is_date {
+            self.is_date =
+                !is_number && text.is_some_and(|s| parse_timestamp(s.trim()).is_some());
+        }
+
+        if !self.seen || value < self.min.as_slice() {
+            self.min = value.to_vec();
+        }
+        if !self.seen || value > self.max.as_slice() {
+            self.max = value.to_vec();
+        }
+        self.seen = true;
+
+        let length = text.map_or(value.len(), |s| s.chars().count());
+        self.max_length = self.max_length.max(length);
+        self.hll.add(value);
+
+        if let Some(top) = &mut self.top {
+            top.add(value);
+        }
+    }
+
+    fn value_type(&self) -> ValueType {
+        if !self.seen {
+            ValueType::Empty
+        } else if self.is_int {
+            ValueType::Int
+        } else if self.is_float {
+            ValueType::Float
+        } else if self.is_date {
+            ValueType::Date
+        } else {
+            ValueType::String
+        }
+    }
+}
+
+/// Profiles a set of columns in one pass.
+pub struct Profiler {
+    processor: StatsProcessor,
+    agg: Aggregator,
+    columns: Vec<ColumnState>,
+    top: usize,
+}
+
+impl Profiler {
+    /// `columns` holds the 0-based index and output name of each column;
+    /// `top` is the number of most frequent values reported (0 disables them).
+    pub fn new(
+        columns: Vec<(usize, String)>,
+        top: usize,
+        precision: Option<usize>,
+    ) -> Self {
+        let ops = columns
+            .iter()
+            .flat_map(|(idx, _)| {
+                COLUMN_OPS.iter().map(|kind| Operation {
+                    kind: *kind,
+                    field_idx: Some(*idx),
+                })
+            })
+            .collect();
+        let config = StatsConfig {
+            precision,
+            exclude_missing: true,
+            ..Default::default()
+        };
+        let processor = StatsProcessor::new(ops, config);
+        let agg = processor.create_aggregator();
+
+        Self {
+            processor,
+            agg,
+            columns: columns
+                .into_iter()
+                .map(|(idx, name)| ColumnState::new(idx, name, top))
+                .collect(),
+            top,
+        }
+    }
+
+    pub fn update(&mut self, row: &dyn Row) {
+        self.processor.update(&mut self.agg, row);
+        for column in &mut self.columns {
+            let value = row.get_bytes(column.field_idx + 1).unwrap_or(b"");
+            column.update(value);
+        }
+    }
+
+    /// One report line per column, matching [`REPORT_COLUMNS`].
+    pub fn report(&self) -> Vec<Vec<String>> {
+        let results = self.processor.format_results(&self.agg);
+
+        self.columns
+            .iter()
+            .zip(results.chunks(COLUMN_OPS.len()))
+            .map(|(column, stats)| {
+                let value_type = column.value_type();
+                let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+                let (min, max, mean, stdev) = if value_type.is_numeric() {
+                    (
+                        stats[2].clone(),
+                        stats[3].clone(),
+                        stats[4].clone(),
+                        stats[5].clone(),
+                    )
+                } else {
+                    (
+                        text(&column.min),
+                        text(&column.max),
+                        String::new(),
+                        String::new(),
+                    )
+                };
+                let nunique = if column.seen {
+                    column.hll.estimate().round() as u64
+                } else {
+                    0
+                };
+
+                vec![
+                    column.name.clone(),
+                    value_type.name().to_string(),
+                    stats[0].clone(),
+                    stats[1].clone(),
+                    nunique.to_string(),
+                    min,
+                    max,
+                    mean,
+                    stdev,
+                    column.max_length.to_string(),
+                    self.top_values(column),
+                ]
+            })
+            .collect()
+    }
+
+    /// Most frequent values as `value:count`, comma separated. Ties keep
+    /// the order in which values were first seen. Backslashes, commas and
+    /// colons in values are escaped with a backslash.
+    fn top_values(&self, column: &ColumnState) -> String {
+        let Some(top) = &column.top else {
+            return String::new();
+        };
+        top.top(self.top)
+            .iter()
+            .map(|(value, count)| {
+                let mut entry = String::new();
+                for c in String::from_utf8_lossy(value).chars() {
+                    if matches!(c, '\\' | ',' | ':') {
+                        entry.push('\\');
+                    }
+                    entry.push(c);
+                }
+                format!("{}:{}", entry, count)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::tsv::record::StrSliceRow;
+
+    fn profile(rows: &[&[&str]], top: usize) -> Vec<Vec<String>> {
+        let width = rows[0].len();
+        let columns = (0..width).map(|i| (i, format!("c{}", i + 1))).collect();
+        let mut profiler = Profiler::new(columns, top, Some(4));
+        for fields in rows {
+            profiler.update(&StrSliceRow { fields });
+        }
+        profiler.report()
+    }
+
+    #[test]
+    fn test_value_types() {
+        let report = profile(
+            &[
+                &["1", "1.5", "2024-01-02", "a", "", "0.5", "7"],
+                &["", "2", "2024-01-01T10:00:00", "1", "", "2", "2024-01-01"],
+                &["-3", "nope", "2023-12-31", "bb", "", "1e3", ""],
+            ],
+            0,
+        );
+        let types: Vec<&str> = report.iter().map(|r| r[1].as_str()).collect();
+        assert_eq!(
+            types,
+            ["int", "string", "date", "string", "empty", "float", "string"]
+        );
+
+        // count, missing, nunique, min, max, mean, stdev, max_length of the ints
+        assert_eq!(
+            report[0][2..10],
+            ["2", "1", "2", "-3", "1", "-1", "2.8284", "2"]
+        );
+        // Dates and strings compare as text
+        assert_eq!(report[2][5..9], ["2023-12-31", "2024-01-02", "", ""]);
+        assert_eq!(report[3][5..10], ["1", "bb", "", "", "2"]);
+        assert_eq!(report[4][2..6], ["0", "3", "0", ""]);
+        assert_eq!(report[5][5..7], ["0.5", "1000"]);
+    }
+
+    #[test]
+    fn test_top_values() {
+        let report = profile(&[&["x"], &["y"], &["y"], &["z"], &["x"], &["y"]], 2);
+        assert_eq!(report[0][1], "string");
+        assert_eq!(report[0][4], "3");
+        assert_eq!(report[0][10], "y:3,x:2");
+
+        let report = profile(&[&["a,b"], &["a:b"], &["a,b"], &["c\\"]], 3);
+        assert_eq!(report[0][10], "a\\,b:2,a\\:b:1,c\\\\:1");
+    }
+}
@@ -401,6 +401,21 @@ impl FieldResolver {
         }
     }
 
+    /// Resolves a field specification that must name exactly one field, such
+    /// as the argument of `option`, into its 1-based index.
+    pub fn resolve_single(&self, spec: &str, option: &str) -> Result<usize, String> {
+        let indices = self.resolve(spec.trim())?;
+        match indices[..] {
+            [idx] => Ok(idx),
+            _ => Err(format!(
+                "{} needs exactly one field, got {} in `{}`",
+                option,
+                indices.len(),
+                spec
+            )),
+        }
+    }
+
     /// Returns column names if header is available.
     ///
     /// # Returns
@@ -967,4 +982,17 @@ mod tests {
         let indices = resolver.resolve("*_time").unwrap();
         assert_eq!(indices, vec![2, 3]);
     }
+
+    #[test]
+    fn test_field_resolver_single() {
+        let resolver =
+            FieldResolver::new(Some(b"run\tuser_time\tsystem_time".to_vec()), '\t');
+        assert_eq!(resolver.resolve_single("user_time", "--value"), Ok(2));
+        assert_eq!(resolver.resolve_single(" 3 ", "--value"), Ok(3));
+        assert_eq!(
+            resolver.resolve_single("*_time", "--value"),
+            Err("--value needs exactly one field, got 2 in `*_time`".to_string())
+        );
+        assert!(resolver.resolve_single("nope", "--value").is_err());
+    }
 }
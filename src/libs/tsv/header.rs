@@ -143,6 +143,13 @@ pub struct HeaderInfo {
     pub(crate) column_names_line: Option<Vec<u8>>,
 }
 
+impl HeaderInfo {
+    /// Returns the number of input lines the header occupies.
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(self.column_names_line.is_some())
+    }
+}
+
 /// Header handler for streaming processing.
 ///
 /// This struct is designed for line-by-line TSV processing where you need to
@@ -862,6 +869,13 @@ impl Header {
     pub fn column_count(&self) -> Option<usize> {
         self.index_cache.as_ref().map(|cache| cache.len())
     }
+
+    /// Returns the number of input lines the header occupies.
+    ///
+    /// Empty lines skipped while reading hash headers are not counted.
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(self.column_names.is_some())
+    }
 }
 
 /// Writes header to output in standard format.
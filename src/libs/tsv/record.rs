@@ -205,6 +205,15 @@ impl TsvRecord {
         }
     }
 
+    /// Returns a borrowed row view of this record.
+    #[inline]
+    pub fn as_row(&self) -> TsvRow<'_, '_> {
+        TsvRow {
+            line: &self.line,
+            ends: &self.ends,
+        }
+    }
+
     /// Returns the number of fields.
     #[inline]
     pub fn len(&self) -> usize {
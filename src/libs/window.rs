@@ -0,0 +1,767 @@
+//! Window functions for `tva window`.
+//!
+//! Each [`WindowFunc`] appends one column, computed from the rows seen so far
+//! in the row's partition (the `--group-by` key). Everything streams, except
+//! `lead`, which holds a row back until the `k`-th next row of its partition
+//! arrives; [`Windower`] keeps the output in input order.
+
+use crate::libs::aggregation::{math, OpKind, Operation, StatsConfig, StatsProcessor};
+use crate::libs::number::{fast_parse_f64, format_float};
+use crate::libs::tsv::fields::FieldResolver;
+use crate::libs::tsv::record::Row;
+use std::collections::{HashMap, VecDeque};
+
+/// Running aggregates of `cumsum`, `cummean`, `cummin` and `cummax`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cumulative {
+    Sum,
+    Mean,
+    Min,
+    Max,
+}
+
+/// A window function, parsed from `op[:field][:param][:header]`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowKind {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Cumulative(Cumulative),
+    Lag(usize),
+    Lead(usize),
+    Diff(usize),
+    PctChange(usize),
+    /// Any single-field `tva stats` operation over the last `n` rows.
+    Rolling(OpKind, usize),
+}
+
+/// A parsed `tva window` spec whose field is not yet resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSpec {
+    pub kind: WindowKind,
+    pub field: Option<String>,
+    /// Parameter as written, used in the default column name.
+    param: Option<String>,
+    header: Option<String>,
+}
+
+impl WindowSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let op = parts[0];
+        let mut rest = parts[1..].iter().copied();
+        let invalid = || format!("invalid window spec `{}`", spec);
+
+        let (kind, needs_field) = match op {
+            "row_number" => (WindowKind::RowNumber, false),
+            "rank" => (WindowKind::Rank, true),
+            "dense_rank" => (WindowKind::DenseRank, true),
+            "cumsum" => (WindowKind::Cumulative(Cumulative::Sum), true),
+            "cummean" => (WindowKind::Cumulative(Cumulative::Mean), true),
+            "cummin" => (WindowKind::Cumulative(Cumulative::Min), true),
+            "cummax" => (WindowKind::Cumulative(Cumulative::Max), true),
+            "lag" => (WindowKind::Lag(1), true),
+            "lead" => (WindowKind::Lead(1), true),
+            "diff" => (WindowKind::Diff(1), true),
+            "pct_change" => (WindowKind::PctChange(1), true),
+            _ => {
+                let name = op.strip_prefix("rolling_").ok_or_else(|| {
+                    format!("unknown window function `{}` in `{}`", op, spec)
+                })?;
+                let kind = OpKind::from_name(name)
+                    .filter(|k| *k != OpKind::Count && !k.is_bivariate())
+                    .ok_or_else(|| {
+                        format!("unknown rolling operation `{}` in `{}`", name, spec)
+                    })?;
+                (WindowKind::Rolling(kind, 0), true)
+            }
+        };
+
+        let field = if needs_field {
+            Some(rest.next().filter(|f| !f.is_empty()).ok_or_else(|| {
+                format!("window function `{}` needs a field: `{}`", op, spec)
+            })?)
+        } else {
+            None
+        };
+
+        let mut param = None;
+        let kind = match kind {
+            WindowKind::Rolling(op_kind, _) => {
+                let n = rest.next().ok_or_else(|| {
+                    format!(
+                        "`{}` needs a window size, e.g. `{}:{}:7`",
+                        op,
+                        op,
+                        field.unwrap()
+                    )
+                })?;
+                let size =
+                    n.parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(|| {
+                        format!("window size must be a positive integer: `{}`", spec)
+                    })?;
+                param = Some(n);
+                WindowKind::Rolling(op_kind, size)
+            }
+            WindowKind::Lag(_)
+            | WindowKind::Lead(_)
+            | WindowKind::Diff(_)
+            | WindowKind::PctChange(_) => {
+                // An optional offset; anything else is the header
+                let k = match parts.get(2).and_then(|p| p.parse::<usize>().ok()) {
+                    Some(k) => {
+                        param = rest.next();
+                        if k == 0 {
+                            return Err(format!("offset must be positive: `{}`", spec));
+                        }
+                        k
+                    }
+                    None => 1,
+                };
+                match kind {
+                    WindowKind::Lag(_) => WindowKind::Lag(k),
+                    WindowKind::Lead(_) => WindowKind::Lead(k),
+                    WindowKind::Diff(_) => WindowKind::Diff(k),
+                    _ => WindowKind::PctChange(k),
+                }
+            }
+            kind => kind,
+        };
+
+        let header = rest.next().map(|h| h.to_string());
+        if header.as_deref() == Some("") || rest.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            kind,
+            field: field.map(|f| f.to_string()),
+            param: param.map(|p| p.to_string()),
+            header,
+        })
+    }
+
+    /// Name of the function as written, e.g. `rolling_mean`.
+    fn op_name(&self) -> String {
+        match &self.kind {
+            WindowKind::RowNumber => "row_number".to_string(),
+            WindowKind::Rank => "rank".to_string(),
+            WindowKind::DenseRank => "dense_rank".to_string(),
+            WindowKind::Cumulative(c) => match c {
+                Cumulative::Sum => "cumsum",
+                Cumulative::Mean => "cummean",
+                Cumulative::Min => "cummin",
+                Cumulative::Max => "cummax",
+            }
+            .to_string(),
+            WindowKind::Lag(_) => "lag".to_string(),
+            WindowKind::Lead(_) => "lead".to_string(),
+            WindowKind::Diff(_) => "diff".to_string(),
+            WindowKind::PctChange(_) => "pct_change".to_string(),
+            WindowKind::Rolling(kind, _) => format!("rolling_{}", kind.name()),
+        }
+    }
+
+    /// Resolves the field and the output column name, e.g. `price_lag_2`.
+    pub fn resolve(
+        &self,
+        resolver: &FieldResolver,
+        column_names: Option<&[String]>,
+    ) -> Result<(Option<usize>, String), String> {
+        let Some(field) = &self.field else {
+            return Ok((None, self.header.clone().unwrap_or_else(|| self.op_name())));
+        };
+        let indices = resolver.resolve(field)?;
+        let [idx] = indices[..] else {
+            return Err(format!(
+                "window function `{}` needs a single field, got {}",
+                self.op_name(),
+                indices.len()
+            ));
+        };
+
+        let name = match &self.header {
+            Some(header) => header.clone(),
+            None => {
+                let field_name = column_names
+                    .and_then(|names| names.get(idx - 1).cloned())
+                    .unwrap_or_else(|| format!("field{}", idx));
+                match &self.param {
+                    Some(param) => {
+                        format!("{}_{}_{}", field_name, self.op_name(), param)
+                    }
+                    None => format!("{}_{}", field_name, self.op_name()),
+                }
+            }
+        };
+        Ok((Some(idx - 1), name))
+    }
+}
+
+/// A window function bound to its 0-based field.
+pub struct WindowFunc {
+    kind: WindowKind,
+    field: usize,
+    /// Processor of a `rolling_*` function, with one operation on field 0.
+    /// Not needed by the operations of [`RollingSums`].
+    rolling: Option<StatsProcessor>,
+}
+
+impl WindowFunc {
+    pub fn new(
+        kind: WindowKind,
+        field: Option<usize>,
+        precision: Option<usize>,
+    ) -> Self {
+        let rolling = match kind {
+            WindowKind::Rolling(op, _) if !RollingSums::supports(op) => {
+                let config = StatsConfig {
+                    precision,
+                    ..StatsConfig::default()
+                };
+                let ops = vec![Operation {
+                    kind: op,
+                    field_idx: Some(0),
+                }];
+                Some(StatsProcessor::new(ops, config))
+            }
+            _ => None,
+        };
+        Self {
+            kind,
+            field: field.unwrap_or(0),
+            rolling,
+        }
+    }
+
+    fn new_state(&self) -> FuncState {
+        match self.kind {
+            WindowKind::Cumulative(Cumulative::Min) => FuncState::Acc(f64::INFINITY, 0),
+            WindowKind::Cumulative(Cumulative::Max) => {
+                FuncState::Acc(f64::NEG_INFINITY, 0)
+            }
+            WindowKind::Cumulative(_) => FuncState::Acc(0.0, 0),
+            WindowKind::Rank | WindowKind::DenseRank => FuncState::Rank(0, None),
+            WindowKind::Lead(_) => FuncState::Seqs(VecDeque::new()),
+            WindowKind::Rolling(op, _) if RollingSums::supports(op) => {
+                FuncState::Sums(RollingSums::default())
+            }
+            _ => FuncState::Values(VecDeque::new()),
+        }
+    }
+}
+
+/// Per-partition state of one window function.
+enum FuncState {
+    /// Running total or extreme, and the number of values in it.
+    Acc(f64, usize),
+    /// Current rank and the previous value.
+    Rank(usize, Option<Vec<u8>>),
+    /// Recent raw values, for lag, diff, pct_change and rolling windows.
+    Values(VecDeque<Vec<u8>>),
+    /// Sequence numbers of recent rows still waiting for their lead value.
+    Seqs(VecDeque<u64>),
+    /// Rolling window kept up to date value by value.
+    Sums(RollingSums),
+}
+
+/// Rolling window of `rolling_sum`, `rolling_mean`, `rolling_variance` and
+/// `rolling_stdev`. The value entering the window is added to the running
+/// sums and the one leaving it is taken out, so a row costs O(1) instead of
+/// aggregating the whole window again.
+#[derive(Default)]
+struct RollingSums {
+    /// Values in the window, `None` when empty or not a number.
+    values: VecDeque<Option<f64>>,
+    /// Count of the finite values.
+    count: usize,
+    /// Sum of the finite values, with the rounding error of a compensated
+    /// (Neumaier) summation, so that removals do not lose precision.
+    sum: f64,
+    compensation: f64,
+    /// Sum of squared deviations from the mean of the finite values.
+    m2: f64,
+    /// Number of infinite and NaN values.
+    non_finite: usize,
+    /// Values added since the sums were last computed from scratch.
+    since_reset: usize,
+}
+
+impl RollingSums {
+    fn supports(op: OpKind) -> bool {
+        matches!(
+            op,
+            OpKind::Sum | OpKind::Mean | OpKind::Variance | OpKind::Stdev
+        )
+    }
+
+    fn push(&mut self, value: Option<f64>, len: usize) {
+        let mut exact = true;
+        if self.values.len() == len {
+            if let Some(old) = self.values.pop_front().unwrap() {
+                exact = self.remove(old);
+            }
+        }
+        self.values.push_back(value);
+        if let Some(x) = value {
+            self.add(x);
+        }
+
+        // Rounding errors of the removals pile up, so start afresh once per
+        // window length, which keeps the cost amortized O(1)
+        self.since_reset += 1;
+        if !exact || self.since_reset >= len || !self.m2.is_finite() {
+            self.reset();
+        }
+    }
+
+    fn add(&mut self, x: f64) {
+        if !x.is_finite() {
+            self.non_finite += 1;
+            return;
+        }
+        let old_mean = self.mean();
+        self.count += 1;
+        self.accumulate(x);
+        self.m2 += (x - old_mean) * (x - self.mean());
+    }
+
+    /// Takes `x` out of the sums. Returns false when the sum of squared
+    /// deviations loses most of its value, and with it most of its precision.
+    fn remove(&mut self, x: f64) -> bool {
+        if !x.is_finite() {
+            self.non_finite -= 1;
+            return true;
+        }
+        let old_mean = self.mean();
+        self.count -= 1;
+        self.accumulate(-x);
+        if self.count <= 1 {
+            self.m2 = 0.0;
+            return true;
+        }
+        let delta = (x - old_mean) * (x - self.mean());
+        self.m2 -= delta;
+        delta <= self.m2
+    }
+
+    fn accumulate(&mut self, x: f64) {
+        let total = self.sum + x;
+        if self.sum.abs() >= x.abs() {
+            self.compensation += (self.sum - total) + x;
+        } else {
+            self.compensation += (x - total) + self.sum;
+        }
+        self.sum = total;
+    }
+
+    /// Recomputes the sums from the values, in two passes.
+    fn reset(&mut self) {
+        let values = std::mem::take(&mut self.values);
+        let finite = || values.iter().flatten().filter(|x| x.is_finite());
+        (self.count, self.sum, self.compensation) = (0, 0.0, 0.0);
+        for &x in finite() {
+            self.count += 1;
+            self.accumulate(x);
+        }
+        let mean = self.mean();
+        self.m2 = finite().map(|x| (x - mean) * (x - mean)).sum();
+        self.non_finite = values.iter().flatten().count() - self.count;
+        self.since_reset = 0;
+        self.values = values;
+    }
+
+    /// Sum of the finite values.
+    fn total(&self) -> f64 {
+        self.sum + self.compensation
+    }
+
+    /// Mean of the finite values, 0 when there are none.
+    fn mean(&self) -> f64 {
+        if self.count > 0 {
+            self.total() / self.count as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Result of `op`, as `tva stats` would compute it over the window.
+    fn result(&self, op: OpKind) -> f64 {
+        let count = self.count + self.non_finite;
+        let sum = if self.non_finite > 0 {
+            let rest: f64 = self
+                .values
+                .iter()
+                .flatten()
+                .filter(|x| !x.is_finite())
+                .sum();
+            self.total() + rest
+        } else {
+            self.total()
+        };
+        match op {
+            OpKind::Sum => sum,
+            OpKind::Mean => math::mean(sum, count),
+            _ => {
+                let variance = if count > 1 && self.non_finite == 0 {
+                    self.m2.max(0.0) / (count - 1) as f64
+                } else {
+                    f64::NAN
+                };
+                if op == OpKind::Stdev {
+                    variance.sqrt()
+                } else {
+                    variance
+                }
+            }
+        }
+    }
+}
+
+struct Partition {
+    rows: usize,
+    states: Vec<FuncState>,
+}
+
+/// A row whose appended values are not all known yet.
+pub struct PendingRow {
+    pub line: Vec<u8>,
+    pub values: Vec<String>,
+    missing: usize,
+}
+
+/// Computes the window functions of each row and releases rows, in input
+/// order, once their values are complete.
+pub struct Windower {
+    funcs: Vec<WindowFunc>,
+    precision: Option<usize>,
+    partitions: HashMap<Vec<u8>, Partition>,
+    pending: VecDeque<PendingRow>,
+    /// Sequence number of the first pending row.
+    front_seq: u64,
+}
+
+impl Windower {
+    pub fn new(funcs: Vec<WindowFunc>, precision: Option<usize>) -> Self {
+        Self {
+            funcs,
+            precision,
+            partitions: HashMap::new(),
+            pending: VecDeque::new(),
+            front_seq: 0,
+        }
+    }
+
+    /// Adds a row of the partition `key`. `line` is written out unchanged,
+    /// followed by the values of the window functions.
+    pub fn push(&mut self, key: &[u8], row: &dyn Row, line: &[u8]) {
+        let seq = self.front_seq + self.pending.len() as u64;
+        let partition = match self.partitions.get_mut(key) {
+            Some(p) => p,
+            None => {
+                let states = self.funcs.iter().map(|f| f.new_state()).collect();
+                self.partitions
+                    .entry(key.to_vec())
+                    .or_insert(Partition { rows: 0, states })
+            }
+        };
+        partition.rows += 1;
+
+        let mut values = Vec::with_capacity(self.funcs.len());
+        let mut missing = 0;
+        for (j, (func, state)) in
+            self.funcs.iter().zip(&mut partition.states).enumerate()
+        {
+            let raw = row.get_bytes(func.field + 1).unwrap_or(b"");
+            let value = match (&func.kind, state) {
+                (WindowKind::RowNumber, _) => partition.rows.to_string(),
+                (
+                    WindowKind::Rank | WindowKind::DenseRank,
+                    FuncState::Rank(rank, prev),
+                ) => {
+                    if prev.as_deref() != Some(raw) {
+                        *rank = if func.kind == WindowKind::Rank {
+                            partition.rows
+                        } else {
+                            *rank + 1
+                        };
+                        *prev = Some(raw.to_vec());
+                    }
+                    rank.to_string()
+                }
+                (WindowKind::Cumulative(c), FuncState::Acc(acc, n)) => {
+                    match fast_parse_f64(raw) {
+                        Some(x) => {
+                            *n += 1;
+                            *acc = match c {
+                                Cumulative::Sum | Cumulative::Mean => *acc + x,
+                                Cumulative::Min => acc.min(x),
+                                Cumulative::Max => acc.max(x),
+                            };
+                            let v = if *c == Cumulative::Mean {
+                                *acc / *n as f64
+                            } else {
+                                *acc
+                            };
+                            format_float(v, self.precision)
+                        }
+                        None => String::new(),
+                    }
+                }
+                (WindowKind::Lag(k), FuncState::Values(history)) => {
+                    let value = if history.len() == *k {
+                        String::from_utf8_lossy(&history[0]).into_owned()
+                    } else {
+                        String::new()
+                    };
+                    push_bounded(history, raw, *k);
+                    value
+                }
+                (
+                    WindowKind::Diff(k) | WindowKind::PctChange(k),
+                    FuncState::Values(history),
+                ) => {
+                    let prev = if history.len() == *k {
+                        fast_parse_f64(&history[0])
+                    } else {
+                        None
+                    };
+                    push_bounded(history, raw, *k);
+                    match (fast_parse_f64(raw), prev) {
+                        (Some(x), Some(p)) => {
+                            let v = if matches!(func.kind, WindowKind::Diff(_)) {
+                                x - p
+                            } else {
+                                (x - p) / p
+                            };
+                            format_float(v, self.precision)
+                        }
+                        _ => String::new(),
+                    }
+                }
+                (WindowKind::Rolling(_, n), FuncState::Values(window)) => {
+                    push_bounded(window, raw, *n);
+                    let proc = func.rolling.as_ref().unwrap();
+                    let mut agg = proc.create_aggregator();
+                    for v in window.iter() {
+                        proc.update(&mut agg, &ValueRow(v));
+                    }
+                    proc.format_results(&agg).swap_remove(0)
+                }
+                (WindowKind::Rolling(op, n), FuncState::Sums(sums)) => {
+                    sums.push(fast_parse_f64(raw), *n);
+                    format_float(sums.result(*op), self.precision)
+                }
+                (WindowKind::Lead(k), FuncState::Seqs(waiting)) => {
+                    // This row is the lead of the row `k` rows back
+                    if waiting.len() == *k {
+                        let target = waiting.pop_front().unwrap();
+                        let row = &mut self.pending[(target - self.front_seq) as usize];
+                        row.values[j] = String::from_utf8_lossy(raw).into_owned();
+                        row.missing -= 1;
+                    }
+                    waiting.push_back(seq);
+                    missing += 1;
+                    String::new()
+                }
+                _ => unreachable!("window function state mismatch"),
+            };
+            values.push(value);
+        }
+
+        self.pending.push_back(PendingRow {
+            line: line.to_vec(),
+            values,
+            missing,
+        });
+    }
+
+    /// Returns the next row in input order whose values are complete.
+    pub fn pop_ready(&mut self) -> Option<PendingRow> {
+        if self.pending.front()?.missing > 0 {
+            return None;
+        }
+        self.front_seq += 1;
+        self.pending.pop_front()
+    }
+
+    /// At the end of the input, leads past the end of a partition stay empty.
+    pub fn finish(&mut self) {
+        for row in &mut self.pending {
+            row.missing = 0;
+        }
+    }
+}
+
+fn push_bounded(queue: &mut VecDeque<Vec<u8>>, value: &[u8], len: usize) {
+    if queue.len() == len {
+        queue.pop_front();
+    }
+    queue.push_back(value.to_vec());
+}
+
+/// A single value, seen as field 1 of a row.
+struct ValueRow<'a>(&'a [u8]);
+
+impl Row for ValueRow<'_> {
+    fn get_bytes(&self, idx: usize) -> Option<&[u8]> {
+        (idx == 1).then_some(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::libs::tsv::record::StrSliceRow;
+
+    fn run(specs: &[(&str, Option<usize>)], rows: &[[&str; 2]]) -> Vec<String> {
+        let funcs = specs
+            .iter()
+            .map(|(s, field)| {
+                WindowFunc::new(WindowSpec::parse(s).unwrap().kind, *field, None)
+            })
+            .collect();
+        let mut windower = Windower::new(funcs, None);
+        let mut out = Vec::new();
+        for r in rows {
+            windower.push(
+                r[0].as_bytes(),
+                &StrSliceRow { fields: r },
+                r.join("\t").as_bytes(),
+            );
+            while let Some(row) = windower.pop_ready() {
+                out.push(row.values.join(","));
+            }
+        }
+        windower.finish();
+        while let Some(row) = windower.pop_ready() {
+            out.push(row.values.join(","));
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse() {
+        let spec = WindowSpec::parse("rolling_mean:price:7").unwrap();
+        assert_eq!(spec.kind, WindowKind::Rolling(OpKind::Mean, 7));
+        assert_eq!(spec.field.as_deref(), Some("price"));
+
+        let spec = WindowSpec::parse("lag:price:2:prev").unwrap();
+        assert_eq!(spec.kind, WindowKind::Lag(2));
+        assert_eq!(spec.header.as_deref(), Some("prev"));
+
+        let spec = WindowSpec::parse("lag:price:prev").unwrap();
+        assert_eq!(spec.kind, WindowKind::Lag(1));
+        assert_eq!(spec.header.as_deref(), Some("prev"));
+
+        assert_eq!(
+            WindowSpec::parse("row_number").unwrap().kind,
+            WindowKind::RowNumber
+        );
+        assert!(WindowSpec::parse("cumsum").is_err());
+        assert!(WindowSpec::parse("rolling_mean:price").is_err());
+        assert!(WindowSpec::parse("rolling_mean:price:0").is_err());
+        assert!(WindowSpec::parse("rolling_count:price:3").is_err());
+        assert!(WindowSpec::parse("bogus:price").is_err());
+        assert!(WindowSpec::parse("lag:price:1:a:b").is_err());
+    }
+
+    #[test]
+    fn test_functions() {
+        let rows = [["a", "1"], ["a", "3"], ["a", "3"], ["a", ""], ["a", "8"]];
+        let out = run(
+            &[
+                ("row_number", None),
+                ("rank:x", Some(1)),
+                ("dense_rank:x", Some(1)),
+                ("cumsum:x", Some(1)),
+                ("lag:x", Some(1)),
+                ("diff:x", Some(1)),
+                ("rolling_max:x:2", Some(1)),
+            ],
+            &rows,
+        );
+        assert_eq!(
+            out,
+            vec![
+                "1,1,1,1,,,1",
+                "2,2,2,4,1,2,3",
+                "3,2,2,7,3,0,3",
+                "4,4,3,,3,,3",
+                "5,5,4,15,,,8",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partitions_and_lead() {
+        let rows = [["a", "1"], ["b", "10"], ["a", "2"], ["a", "3"], ["b", "20"]];
+        let out = run(
+            &[
+                ("cumsum:x", Some(1)),
+                ("lead:x", Some(1)),
+                ("pct_change:x", Some(1)),
+            ],
+            &rows,
+        );
+        assert_eq!(out, vec!["1,2,", "10,20,", "3,3,1", "6,,0.5", "30,,1"]);
+    }
+
+    #[test]
+    fn test_rolling_sums() {
+        // Gaps, text, non-finite values and a spike entering and leaving
+        let values = [
+            "1", "2", "", "4", "x", "3", "inf", "2.5", "-1", "-1", "nan", "7", "1e15",
+            "3", "0.1", "0.2", "0.3",
+        ];
+        for op in ["sum", "mean", "variance", "stdev"] {
+            for n in 1..5 {
+                let spec = format!("rolling_{}:x:{}", op, n);
+                let rows: Vec<[&str; 2]> = values.iter().map(|v| ["a", v]).collect();
+                let out = run(&[(&spec, Some(1))], &rows);
+
+                // Aggregates each window from scratch, as `tva stats` would
+                let kind = OpKind::from_name(op).unwrap();
+                let ops = vec![Operation {
+                    kind,
+                    field_idx: Some(0),
+                }];
+                let proc = StatsProcessor::new(ops, StatsConfig::default());
+                for (i, got) in out.iter().enumerate() {
+                    let mut agg = proc.create_aggregator();
+                    for v in &values[(i + 1).saturating_sub(n)..=i] {
+                        proc.update(&mut agg, &ValueRow(v.as_bytes()));
+                    }
+                    let expected: f64 = proc.format_results(&agg)[0].parse().unwrap();
+                    let got: f64 = got.parse().unwrap();
+                    assert!(
+                        got == expected
+                            || (got.is_nan() && expected.is_nan())
+                            || (got - expected).abs() <= 1e-9 * expected.abs().max(1.0),
+                        "{} row {}: {} != {}",
+                        spec,
+                        i,
+                        got,
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rolling_variance_offset() {
+        let rows = [
+            ["a", "1000000001"],
+            ["a", "1000000002"],
+            ["a", "1000000004"],
+            ["a", "1000000003"],
+        ];
+        let out = run(&[("rolling_variance:x:3", Some(1))], &rows);
+        assert_eq!(out[0], "nan");
+        for (got, expected) in out[1..].iter().zip([0.5, 7.0 / 3.0, 1.0]) {
+            let got: f64 = got.parse().unwrap();
+            assert!((got - expected).abs() < 1e-12, "{} != {}", got, expected);
+        }
+    }
+}
@@ -35,14 +35,18 @@ fn main() -> anyhow::Result<()> {
         .subcommand(cmd_tva::bin::make_subcommand())
         .subcommand(cmd_tva::blank::make_subcommand())
         .subcommand(cmd_tva::check::make_subcommand())
+        .subcommand(cmd_tva::crosstab::make_subcommand())
         .subcommand(cmd_tva::expr::make_subcommand())
         .subcommand(cmd_tva::fill::make_subcommand())
         .subcommand(cmd_tva::filter::make_subcommand())
+        .subcommand(cmd_tva::freq::make_subcommand())
         .subcommand(cmd_tva::r#from::make_subcommand())
         .subcommand(cmd_tva::header::make_subcommand())
+        .subcommand(cmd_tva::hist::make_subcommand())
         .subcommand(cmd_tva::join::make_subcommand())
         .subcommand(cmd_tva::keep_header::make_subcommand())
         .subcommand(cmd_tva::longer::make_subcommand())
+        .subcommand(cmd_tva::merge::make_subcommand())
         .subcommand(cmd_tva::mutate::make_subcommand())
         .subcommand(cmd_tva::nl::make_subcommand())
         .subcommand(cmd_tva::plot::make_subcommand())
@@ -53,19 +57,22 @@ fn main() -> anyhow::Result<()> {
         .subcommand(cmd_tva::sort::make_subcommand())
         .subcommand(cmd_tva::split::make_subcommand())
         .subcommand(cmd_tva::stats::make_subcommand())
+        .subcommand(cmd_tva::summary::make_subcommand())
+        .subcommand(cmd_tva::test::make_subcommand())
         .subcommand(cmd_tva::to::make_subcommand())
         .subcommand(cmd_tva::transpose::make_subcommand())
         .subcommand(cmd_tva::uniq::make_subcommand())
         .subcommand(cmd_tva::wider::make_subcommand())
+        .subcommand(cmd_tva::window::make_subcommand())
         .after_help(
             r###"Subcommands groups:
 
 * Subset Selection: select, filter, slice, sample
 * Data Transformation: longer, wider, fill, blank, transpose
 * Expr Language: expr, extend, mutate
-* Data Organization: sort, reverse, join, append, split
-* Statistics & Summary: stats, bin, uniq
-* Visualization: plot (point, box, bin2d)
+* Data Organization: sort, merge, reverse, join, append, split
+* Statistics & Summary: stats, summary, window, freq, crosstab, test, bin, uniq
+* Visualization: plot (point, box, bin2d), hist
 * Formatting & Utilities: check, nl, keep-header
 * Import & Export: from (csv, xlsx, html), to (csv, xlsx, md)
 "###,
@@ -96,15 +103,19 @@ fn main() -> anyhow::Result<()> {
         Some(("bin", sub_matches)) => cmd_tva::bin::execute(sub_matches),
         Some(("blank", sub_matches)) => cmd_tva::blank::execute(sub_matches),
         Some(("check", sub_matches)) => cmd_tva::check::execute(sub_matches),
+        Some(("crosstab", sub_matches)) => cmd_tva::crosstab::execute(sub_matches),
         Some(("extend", sub_matches)) => cmd_tva::extend::execute(sub_matches),
         Some(("expr", sub_matches)) => cmd_tva::expr::execute(sub_matches),
         Some(("fill", sub_matches)) => cmd_tva::fill::execute(sub_matches),
         Some(("filter", sub_matches)) => cmd_tva::filter::execute(sub_matches),
+        Some(("freq", sub_matches)) => cmd_tva::freq::execute(sub_matches),
         Some(("from", sub_matches)) => cmd_tva::r#from::execute(sub_matches),
         Some(("header", sub_matches)) => cmd_tva::header::execute(sub_matches),
+        Some(("hist", sub_matches)) => cmd_tva::hist::execute(sub_matches),
         Some(("join", sub_matches)) => cmd_tva::join::execute(sub_matches),
         Some(("keep-header", sub_matches)) => cmd_tva::keep_header::execute(sub_matches),
         Some(("longer", sub_matches)) => cmd_tva::longer::execute(sub_matches),
+        Some(("merge", sub_matches)) => cmd_tva::merge::execute(sub_matches),
         Some(("mutate", sub_matches)) => cmd_tva::mutate::execute(sub_matches),
         Some(("nl", sub_matches)) => cmd_tva::nl::execute(sub_matches),
         Some(("plot", sub_matches)) => cmd_tva::plot::execute(sub_matches),
@@ -115,10 +126,13 @@ fn main() -> anyhow::Result<()> {
         Some(("sort", sub_matches)) => cmd_tva::sort::execute(sub_matches),
         Some(("split", sub_matches)) => cmd_tva::split::execute(sub_matches),
         Some(("stats", sub_matches)) => cmd_tva::stats::execute(sub_matches),
+        Some(("summary", sub_matches)) => cmd_tva::summary::execute(sub_matches),
+        Some(("test", sub_matches)) => cmd_tva::test::execute(sub_matches),
         Some(("to", sub_matches)) => cmd_tva::to::execute(sub_matches),
         Some(("transpose", sub_matches)) => cmd_tva::transpose::execute(sub_matches),
         Some(("uniq", sub_matches)) => cmd_tva::uniq::execute(sub_matches),
         Some(("wider", sub_matches)) => cmd_tva::wider::execute(sub_matches),
+        Some(("window", sub_matches)) => cmd_tva::window::execute(sub_matches),
         _ => unreachable!(),
     }?;
 
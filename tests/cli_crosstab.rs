@@ -0,0 +1,92 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+use test_case::test_case;
+
+const INPUT: &str = "sp\tisl\na\tx\nb\tx\na\ty\nc\tx\nb\ty\na\tx\n";
+
+#[test_case(
+    &[],
+    "sp\tx\ty\ttotal\na\t2\t1\t3\nb\t1\t1\t2\nc\t1\t0\t1\ntotal\t4\t2\t6\n"
+    ; "counts"
+)]
+#[test_case(
+    &["--percent", "row"],
+    "sp\tx\ty\ttotal\na\t66.67\t33.33\t100\nb\t50\t50\t100\nc\t100\t0\t100\n\
+     total\t66.67\t33.33\t100\n"
+    ; "row_percent"
+)]
+#[test_case(
+    &["--percent", "col", "-p", "1"],
+    "sp\tx\ty\ttotal\na\t50\t50\t50\nb\t25\t50\t33.3\nc\t25\t0\t16.7\n\
+     total\t100\t100\t100\n"
+    ; "col_percent"
+)]
+#[test_case(
+    &["--percent", "total"],
+    "sp\tx\ty\ttotal\na\t33.33\t16.67\t50\nb\t16.67\t16.67\t33.33\nc\t16.67\t0\t16.67\n\
+     total\t66.67\t33.33\t100\n"
+    ; "total_percent"
+)]
+fn crosstab_tables(args: &[&str], expected: &str) {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["crosstab", "-H", "-r", "sp", "-c", "isl"])
+        .args(args)
+        .stdin(INPUT)
+        .run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn crosstab_chi2() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["crosstab", "-H", "-r", "sp", "-c", "isl", "--chi2"])
+        .stdin(INPUT)
+        .run();
+    // Expected counts are (2, 1), (4/3, 2/3) and (2/3, 1/3), so the
+    // statistic is 0.75 and, with 2 degrees of freedom, p = exp(-0.375)
+    assert_eq!(
+        stdout,
+        "sp\tx\ty\ttotal\na\t2\t1\t3\nb\t1\t1\t2\nc\t1\t0\t1\ntotal\t4\t2\t6\n\
+         \n\
+         chi2\tdf\tp_value\n0.75\t2\t0.6873\n"
+    );
+}
+
+#[test]
+fn crosstab_sort_no_header() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["crosstab", "-r", "2", "-c", "1", "--sort"])
+        .stdin("b\t2\na\t1\nb\t1\n")
+        .run();
+    assert_eq!(
+        stdout,
+        "field2\ta\tb\ttotal\n1\t1\t1\t2\n2\t0\t1\t1\ntotal\t1\t2\t3\n"
+    );
+}
+
+#[test]
+fn crosstab_errors() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["crosstab", "-H", "-r", "sp", "-c", "sp,isl"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("--cols needs exactly one field, got 2"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "crosstab",
+            "-H",
+            "-r",
+            "sp",
+            "-c",
+            "isl",
+            "--percent",
+            "cell",
+        ])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("invalid value"));
+}
@@ -0,0 +1,129 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+
+const INPUT: &str = "sp\tisl\na\tx\nb\tx\na\ty\nc\tx\n\tz\na\tx\n";
+
+#[test]
+fn freq_single_field() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "sp"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        "field\tvalue\tcount\tpercent\tcum_percent\n\
+         sp\ta\t3\t50\t50\n\
+         sp\tb\t1\t16.67\t66.67\n\
+         sp\tc\t1\t16.67\t83.33\n\
+         sp\t\t1\t16.67\t100\n"
+    );
+}
+
+#[test]
+fn freq_delimiter() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "sp", "-d", ",", "--limit", "1"])
+        .stdin(INPUT.replace('\t', ",").as_str())
+        .run();
+    assert_eq!(
+        stdout,
+        "field,value,count,percent,cum_percent\n\
+         sp,a,3,50,50\n"
+    );
+}
+
+#[test]
+fn freq_multiple_fields() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "isl,sp", "-p", "1"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        "field\tvalue\tcount\tpercent\tcum_percent\n\
+         isl\tx\t4\t66.7\t66.7\n\
+         isl\ty\t1\t16.7\t83.3\n\
+         isl\tz\t1\t16.7\t100\n\
+         sp\ta\t3\t50\t50\n\
+         sp\tb\t1\t16.7\t66.7\n\
+         sp\tc\t1\t16.7\t83.3\n\
+         sp\t\t1\t16.7\t100\n"
+    );
+}
+
+#[test]
+fn freq_limit_and_other() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "1,2", "--limit", "1"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        "field\tvalue\tcount\tpercent\tcum_percent\n\
+         sp\ta\t3\t50\t50\n\
+         isl\tx\t4\t66.67\t66.67\n"
+    );
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "sp", "-n", "2", "--other"])
+        .args(&["--other-label", "rest"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        "field\tvalue\tcount\tpercent\tcum_percent\n\
+         sp\ta\t3\t50\t50\n\
+         sp\tb\t1\t16.67\t66.67\n\
+         sp\trest\t2\t33.33\t100\n"
+    );
+
+    // Nothing beyond the limit, so no other row
+    let (stdout, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "isl", "-n", "5", "--other"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(stdout.lines().count(), 4);
+}
+
+#[test]
+fn freq_no_header_multiple_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let first = dir.path().join("a.tsv");
+    let second = dir.path().join("b.tsv");
+    std::fs::write(&first, "x\t1\ny\t2\n").unwrap();
+    std::fs::write(&second, "y\t3\n").unwrap();
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "freq",
+            "-f",
+            "1",
+            first.to_str().unwrap(),
+            second.to_str().unwrap(),
+        ])
+        .run();
+    assert_eq!(
+        stdout,
+        "field\tvalue\tcount\tpercent\tcum_percent\n\
+         field1\ty\t2\t66.67\t66.67\n\
+         field1\tx\t1\t33.33\t100\n"
+    );
+}
+
+#[test]
+fn freq_errors() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "sp", "--other"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("--limit"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "nope"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(!stderr.is_empty());
+}
@@ -0,0 +1,137 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+use test_case::test_case;
+
+const INPUT: &str = "sp\tmass\na\t3\nb\t4\na\t5\nc\t\na\t7\nb\t3\n";
+
+#[test_case(
+    &["-l", "sp", "-w", "6"],
+    "a  3     50% │██████\nb  2  33.33% │████\nc  1  16.67% │██\n"
+    ; "count_rows"
+)]
+#[test_case(
+    &["-l", "sp", "-c", "mass", "-w", "4", "--sort", "count"],
+    "a  15  68.18% │████\nb   7  31.82% │█▉\nc   0      0% │\n"
+    ; "sum_counts_sorted"
+)]
+#[test_case(
+    &["-b", "mass", "-n", "2", "-w", "3"],
+    "[3, 5)  3  60% │███\n[5, 7]  2  40% │██\n"
+    ; "bins"
+)]
+fn hist_charts(args: &[&str], expected: &str) {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-H"])
+        .args(args)
+        .stdin(INPUT)
+        .run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn hist_bins_skip_non_finite() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-H", "-b", "v", "-n", "2", "-w", "2"])
+        .stdin("v\nnan\n1\ninf\n2\n-inf\n3\n")
+        .run();
+    assert_eq!(stdout, "[1, 2)  1  33.33% │█\n[2, 3]  2  66.67% │██\n");
+
+    // Only non-finite values leave nothing to bin
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-H", "-b", "v"])
+        .stdin("v\ninf\n")
+        .run();
+    assert_eq!(stdout, "");
+}
+
+#[test]
+fn hist_log_scale() {
+    let input = "x\t9\ny\t99\n";
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-l", "1", "-c", "2", "-w", "2", "--log"])
+        .stdin(input)
+        .run();
+    assert_eq!(stdout, "x   9   8.33% │█\ny  99  91.67% │██\n");
+}
+
+#[test]
+fn hist_dates_and_gaps() {
+    let input = "day\tn\n2024-01-05\t2\n2024-01-01\t1\n2024-01-02\t1\n";
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-H", "-l", "day", "-c", "n", "-w", "2", "--dates"])
+        .stdin(input)
+        .run();
+    assert_eq!(
+        stdout,
+        "2024-01-01  1  25% │█\n2024-01-02  1  25% │█\n2024-01-03  0   0% │\n\
+         2024-01-04  0   0% │\n2024-01-05  2  50% │██\n"
+    );
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-H", "-l", "day", "-c", "n", "-w", "2", "--dates"])
+        .args(&["--compress-gaps"])
+        .stdin(input)
+        .run();
+    assert_eq!(
+        stdout,
+        "2024-01-01  1  25% │█\n2024-01-02  1  25% │█\n…                  │(2 empty)\n\
+         2024-01-05  2  50% │██\n"
+    );
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&["hist", "-l", "1", "--dates", "-w", "1"])
+        .stdin("2023\n2025\n")
+        .run();
+    assert_eq!(stdout, "2023  1  50% │█\n2024  0   0% │\n2025  1  50% │█\n");
+}
+
+#[test]
+fn hist_groups_from_freq() {
+    let (freq, _) = TvaCmd::new()
+        .args(&["freq", "-H", "-f", "sp"])
+        .stdin(INPUT)
+        .run();
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "hist", "-H", "-l", "value", "-c", "count", "-g", "field", "-w", "3",
+        ])
+        .stdin(&freq)
+        .run();
+    assert_eq!(
+        stdout,
+        "sp\na  3     50% │███\nb  2  33.33% │██\nc  1  16.67% │█\n"
+    );
+}
+
+#[test]
+fn hist_errors() {
+    let (_, stderr) = TvaCmd::new().args(&["hist", "-H"]).stdin(INPUT).run_fail();
+    assert!(stderr.contains("--label"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["hist", "-H", "-l", "sp", "-c", "sp"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("invalid count `a`"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["hist", "-H", "-l", "sp", "-c", "mass"])
+        .stdin("sp\tmass\na\t3\nb\t-2\n")
+        .run_fail();
+    assert!(stderr.contains("invalid count `-2`"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["hist", "-H", "-l", "sp", "--dates"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("`a` is not a date"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["hist", "-H", "-b", "mass", "-l", "sp"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("cannot be used with"));
+}
@@ -1852,3 +1852,1274 @@ fn join_error_no_such_data_file() {
         stderr
     );
 }
+
+#[test]
+fn join_how_inner_all_filter_columns() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--how",
+            "inner",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "id",
+            "tests/data/join/how_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "id\tname\tscore.x\tscore.y\tcity\n\
+         1\tann\t5\t50\tparis\n\
+         2\tbob\t7\t20\toslo\n"
+    );
+}
+
+#[test]
+fn join_how_left_fill() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--how",
+            "left",
+            "--fill",
+            "NA",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "id",
+            "tests/data/join/how_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "id\tname\tscore.x\tscore.y\tcity\n\
+         1\tann\t5\t50\tparis\n\
+         2\tbob\t7\t20\toslo\n\
+         4\tdan\t1\tNA\tNA\n"
+    );
+}
+
+#[test]
+fn join_how_right_keeps_unmatched_filter() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--how",
+            "right",
+            "--fill",
+            "NA",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "id",
+            "tests/data/join/how_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "id\tname\tscore.x\tscore.y\tcity\n\
+         1\tann\t5\t50\tparis\n\
+         2\tbob\t7\t20\toslo\n\
+         3\tNA\tNA\t30\trome\n"
+    );
+}
+
+#[test]
+fn join_how_full_noheader() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--how",
+            "full",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "1",
+        ])
+        .stdin("1\tann\t5\n4\tdan\t1\n")
+        .run();
+
+    // Without a header, the header line of the filter file is just another record
+    assert_eq!(
+        stdout,
+        "1\tann\t5\t50\tparis\n\
+         4\tdan\t1\t\t\n\
+         id\t\t\tscore\tcity\n\
+         3\t\t\t30\trome\n\
+         2\t\t\t20\toslo\n"
+    );
+}
+
+#[test]
+fn join_how_full_key_position_differs() {
+    // The data key is the second column; unmatched filter keys are placed there
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--how",
+            "full",
+            "--fill",
+            "-",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "id",
+            "-d",
+            "uid",
+        ])
+        .stdin("name\tuid\nann\t1\nzed\t9\n")
+        .run();
+
+    assert_eq!(
+        stdout,
+        "name\tuid\tscore\tcity\n\
+         ann\t1\t50\tparis\n\
+         zed\t9\t-\t-\n\
+         -\t3\t30\trome\n\
+         -\t2\t20\toslo\n"
+    );
+}
+
+#[test]
+fn join_how_append_fields_and_suffixes() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--how",
+            "inner",
+            "--suffixes",
+            "_d,_f",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "id",
+            "-a",
+            "score",
+            "tests/data/join/how_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "id\tname\tscore_d\tscore_f\n1\tann\t5\t50\n2\tbob\t7\t20\n"
+    );
+}
+
+#[test]
+fn join_how_prefix_avoids_collision() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--how",
+            "inner",
+            "--prefix",
+            "f_",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+            "-k",
+            "id",
+            "tests/data/join/how_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "id\tname\tscore\tf_score\tf_city\n\
+         1\tann\t5\t50\tparis\n\
+         2\tbob\t7\t20\toslo\n"
+    );
+}
+
+#[test]
+fn join_how_conflicts_with_exclude() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--how",
+            "left",
+            "--exclude",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+        ])
+        .stdin("1\n")
+        .run_fail();
+
+    assert!(stderr.contains("cannot be used with"));
+}
+
+#[test]
+fn join_how_invalid_suffixes() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--how",
+            "left",
+            "--suffixes",
+            "x",
+            "-f",
+            "tests/data/join/how_filter.tsv",
+        ])
+        .stdin("1\n")
+        .run_fail();
+
+    assert!(stderr.contains("--suffixes must be two comma-separated values"));
+}
+
+#[test]
+fn join_all_matches_one_to_many() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--all-matches",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "tx",
+            "tests/data/join/many_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "gene\tlen\ttx\n\
+         G1\t100\tT1a\n\
+         G1\t100\tT1b\n\
+         G1\t100\tT1c\n\
+         G2\t200\tT2a\n"
+    );
+}
+
+#[test]
+fn join_all_matches_without_append_fields() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "-m",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "gene",
+            "tests/data/join/many_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(stdout, "gene\tlen\nG1\t100\nG1\t100\nG1\t100\nG2\t200\n");
+}
+
+#[test]
+fn join_all_matches_write_all() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-m",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "1",
+            "-a",
+            "2",
+            "--write-all",
+            "?",
+        ])
+        .stdin("G4\nG3\nG1\n")
+        .run();
+
+    assert_eq!(stdout, "G4\t?\nG3\tT3a\nG1\tT1a\nG1\tT1b\nG1\tT1c\n");
+}
+
+#[test]
+fn join_all_matches_how_full() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "-m",
+            "--how",
+            "full",
+            "--fill",
+            "NA",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "gene",
+            "tests/data/join/many_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "gene\tlen\ttx\n\
+         G1\t100\tT1a\n\
+         G1\t100\tT1b\n\
+         G1\t100\tT1c\n\
+         G2\t200\tT2a\n\
+         G4\t400\tNA\n\
+         G3\tNA\tT3a\n"
+    );
+}
+
+#[test]
+fn join_all_matches_how_right_unmatched_duplicates() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-m",
+            "--how",
+            "right",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "1",
+        ])
+        .stdin("G2\t200\n")
+        .run();
+
+    assert_eq!(
+        stdout,
+        "G2\t200\tT2a\n\
+         gene\t\ttx\n\
+         G1\t\tT1a\n\
+         G1\t\tT1b\n\
+         G1\t\tT1c\n\
+         G3\t\tT3a\n"
+    );
+}
+
+#[test]
+fn join_all_matches_conflicts_with_allow_duplicate_keys() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["join", "-m", "-z", "-f", "tests/data/join/many_filter.tsv"])
+        .stdin("G1\n")
+        .run_fail();
+
+    assert!(stderr.contains("cannot be used with"));
+}
+
+#[test]
+fn join_relationship_one_to_many_ok() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--relationship",
+            "one-to-many",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "tx",
+            "tests/data/join/many_data.tsv",
+        ])
+        .run();
+
+    // Implies --all-matches
+    assert_eq!(
+        stdout,
+        "gene\tlen\ttx\n\
+         G1\t100\tT1a\n\
+         G1\t100\tT1b\n\
+         G1\t100\tT1c\n\
+         G2\t200\tT2a\n"
+    );
+}
+
+#[test]
+fn join_relationship_many_to_one_filter_duplicates() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--relationship",
+            "many-to-one",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "tx",
+            "tests/data/join/many_data.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains(
+        "--relationship many-to-one violated by the filter file: key `G1` at \
+         tests/data/join/many_data.tsv line 2 matches 3 filter records"
+    ));
+}
+
+#[test]
+fn join_relationship_one_to_one_data_duplicates() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--relationship",
+            "one-to-one",
+            "-f",
+            "tests/data/join/many_data.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "len",
+            "tests/data/join/many_data_dup.tsv",
+        ])
+        .run_fail();
+
+    // Fails fast at the offending record
+    assert_eq!(stdout, "gene\tlen\tlen\nG2\t1\t200\n");
+    assert!(stderr.contains(
+        "--relationship one-to-one violated by the data: key `G2` at \
+         tests/data/join/many_data_dup.tsv line 3 was already matched"
+    ));
+}
+
+#[test]
+fn join_relationship_many_to_one_data_duplicates_ok() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--relationship",
+            "many-to-one",
+            "-f",
+            "tests/data/join/many_data.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "len",
+            "tests/data/join/many_data_dup.tsv",
+        ])
+        .run();
+
+    assert_eq!(stdout, "gene\tlen\tlen\nG2\t1\t200\nG2\t2\t200\n");
+}
+
+#[test]
+fn join_relationship_ignores_unmatched_duplicates() {
+    // G3 occurs twice in the data but never matches
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--relationship",
+            "one-to-one",
+            "--how",
+            "left",
+            "-f",
+            "tests/data/join/many_data.tsv",
+            "-k",
+            "1",
+        ])
+        .stdin("G3\nG2\nG3\n")
+        .run();
+
+    assert_eq!(stdout, "G3\t\nG2\t200\nG3\t\n");
+}
+
+#[test]
+fn join_require_match() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--require-match",
+            "-f",
+            "tests/data/join/many_data.tsv",
+            "-k",
+            "1",
+            "-a",
+            "2",
+        ])
+        .stdin("G1\nG2\nG9\nG1\n")
+        .run_fail();
+
+    assert_eq!(stdout, "G1\t100\nG2\t200\n");
+    assert!(stderr.contains(
+        "--require-match: key `G9` at stdin line 3 has no match in the filter file"
+    ));
+}
+
+#[test]
+fn join_require_match_conflicts_with_exclude() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--require-match",
+            "--exclude",
+            "-f",
+            "tests/data/join/many_data.tsv",
+        ])
+        .stdin("G1\n")
+        .run_fail();
+
+    assert!(stderr.contains("cannot be used with"));
+}
+
+#[test]
+fn join_sorted_inner_append() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "-m",
+            "-f",
+            "tests/data/join/sorted_filter.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "tx",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "gene\tlen\ttx\n\
+         G1\t100\tT1a\n\
+         G1\t100\tT1b\n\
+         G1\t100\tT1c\n\
+         G2\t200\tT2a\n"
+    );
+}
+
+#[test]
+fn join_sorted_full_merge_order() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "-m",
+            "--how",
+            "full",
+            "--fill",
+            "NA",
+            "-f",
+            "tests/data/join/sorted_filter.tsv",
+            "-k",
+            "gene",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run();
+
+    // Unmatched filter records are written in key order
+    assert_eq!(
+        stdout,
+        "gene\tlen\ttx\n\
+         G1\t100\tT1a\n\
+         G1\t100\tT1b\n\
+         G1\t100\tT1c\n\
+         G2\t200\tT2a\n\
+         G3\tNA\tT3a\n\
+         G4\t400\tNA\n"
+    );
+}
+
+#[test]
+fn join_sorted_exclude() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "--exclude",
+            "-f",
+            "tests/data/join/sorted_filter.tsv",
+            "-k",
+            "gene",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(stdout, "gene\tlen\nG4\t400\n");
+}
+
+#[test]
+fn join_sorted_right_empty_data() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "--how",
+            "right",
+            "-f",
+            "tests/data/join/sorted_data.tsv",
+            "-k",
+            "1",
+        ])
+        .stdin("")
+        .run();
+
+    assert_eq!(stdout, "G1\t100\nG2\t200\nG4\t400\n");
+}
+
+#[test]
+fn join_sorted_duplicate_keys_error() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "-f",
+            "tests/data/join/sorted_filter.tsv",
+            "-k",
+            "gene",
+            "-a",
+            "tx",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains("duplicate key with different append values"));
+}
+
+#[test]
+fn join_sorted_unsorted_filter() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "-m",
+            "-f",
+            "tests/data/join/many_filter.tsv",
+            "-k",
+            "gene",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains(
+        "tests/data/join/many_filter.tsv: input is not sorted: line 4 sorts before line 3"
+    ));
+}
+
+#[test]
+fn join_sorted_unsorted_data() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "-f",
+            "tests/data/join/sorted_data.tsv",
+            "-k",
+            "1",
+        ])
+        .stdin("gene\nG1\nG4\nG2\n")
+        .run_fail();
+
+    assert!(stderr.contains("stdin: input is not sorted: line 4 sorts before line 3"));
+}
+
+#[test]
+fn join_sorted_relationship() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--sorted",
+            "--relationship",
+            "many-to-one",
+            "-f",
+            "tests/data/join/sorted_filter.tsv",
+            "-k",
+            "gene",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains("key `G1` at tests/data/join/sorted_data.tsv line 2"));
+}
+
+#[test]
+fn join_sorted_single_data_input() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--sorted",
+            "-f",
+            "tests/data/join/sorted_data.tsv",
+            "tests/data/join/sorted_data.tsv",
+            "tests/data/join/sorted_data.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains("--sorted takes a single data input"));
+}
+
+#[test]
+fn join_interval_closed() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "-a",
+            "gene",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+            "tests/data/join/interval_data.tsv",
+        ])
+        .run();
+
+    // Closed intervals touching at a single position overlap
+    assert_eq!(
+        stdout,
+        "chrom\tstart\tend\tname\tgene\n\
+         chr1\t100\t200\tr1\tgA\n\
+         chr1\t100\t200\tr1\tgB\n\
+         chr1\t250\t260\tr2\tgA\n\
+         chr2\t50\t80\tr3\tgD\n"
+    );
+}
+
+#[test]
+fn join_interval_half_open() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "1-3",
+            "--half-open",
+            "-a",
+            "gene",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+            "tests/data/join/interval_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "chrom\tstart\tend\tname\tgene\n\
+         chr1\t100\t200\tr1\tgA\n\
+         chr1\t250\t260\tr2\tgA\n"
+    );
+}
+
+#[test]
+fn join_interval_zero_length() {
+    // Half-open insertions are the point at their start
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "1-3",
+            "--half-open",
+            "-a",
+            "gene",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+        ])
+        .stdin("chrom\tstart\tend\tname\nchr1\t150\t150\ti1\nchr1\t100\t100\ti2\n")
+        .run();
+    assert_eq!(
+        stdout,
+        "chrom\tstart\tend\tname\tgene\nchr1\t150\t150\ti1\tgA\n"
+    );
+
+    // Closed intervals always hold their start
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "1-3",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+        ])
+        .stdin("chrom\tstart\tend\nchr1\t150\t149\n")
+        .run_fail();
+    assert!(stderr.contains("interval end is before its start at stdin line 2"));
+}
+
+#[test]
+fn join_interval_write_all_and_exclude() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "-a",
+            "gene",
+            "-w",
+            "NA",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+            "tests/data/join/interval_data.tsv",
+        ])
+        .run();
+    assert!(stdout.ends_with("chr2\t50\t80\tr3\tgD\nchr3\t1\t10\tr4\tNA\n"));
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "-e",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+            "tests/data/join/interval_data.tsv",
+        ])
+        .run();
+    assert_eq!(stdout, "chrom\tstart\tend\tname\nchr3\t1\t10\tr4\n");
+}
+
+#[test]
+fn join_interval_how_full() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "--how",
+            "full",
+            "--fill",
+            "NA",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+            "tests/data/join/interval_data.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "chrom.x\tstart.x\tend.x\tname\tchrom.y\tstart.y\tend.y\tgene\n\
+         chr1\t100\t200\tr1\tchr1\t150\t300\tgA\n\
+         chr1\t100\t200\tr1\tchr1\t1\t100\tgB\n\
+         chr1\t250\t260\tr2\tchr1\t150\t300\tgA\n\
+         chr2\t50\t80\tr3\tchr2\t80\t90\tgD\n\
+         chr3\t1\t10\tr4\tNA\tNA\tNA\tNA\n\
+         NA\tNA\tNA\tNA\tchr1\t201\t249\tgC\n\
+         NA\tNA\tNA\tNA\tchr2\t500\t600\tgE\n"
+    );
+}
+
+#[test]
+fn join_interval_data_fields() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "--data-interval",
+            "seq,from,to",
+            "-a",
+            "gene",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+        ])
+        .stdin("id\tseq\tfrom\tto\nx\tchr2\t85\t85\ny\tchr9\t1\t5\nz\tchr1\t90\t160\n")
+        .run();
+
+    assert_eq!(
+        stdout,
+        "id\tseq\tfrom\tto\tgene\n\
+         x\tchr2\t85\t85\tgD\n\
+         z\tchr1\t90\t160\tgA\n\
+         z\tchr1\t90\t160\tgB\n"
+    );
+}
+
+#[test]
+fn join_interval_require_match() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "--require-match",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+            "tests/data/join/interval_data.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains(
+        "key `chr3:1-10` at tests/data/join/interval_data.tsv line 5 has no match"
+    ));
+}
+
+#[test]
+fn join_interval_invalid() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--interval",
+            "1,2",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+        ])
+        .stdin("")
+        .run_fail();
+    assert!(stderr.contains("interval fields must be three fields"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--interval",
+            "1,2,3",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+        ])
+        .stdin("")
+        .run_fail();
+    assert!(stderr.contains(
+        "invalid interval start `start` at tests/data/join/interval_filter.tsv line 1"
+    ));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--interval",
+            "chrom,start,end",
+            "-f",
+            "tests/data/join/interval_filter.tsv",
+        ])
+        .stdin("chrom\tstart\tend\nchr1\t20\t10\n")
+        .run_fail();
+    assert!(stderr.contains("interval end is before its start at stdin line 2"));
+}
+
+#[test]
+fn join_asof_partitioned() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--asof",
+            "time",
+            "-k",
+            "sym",
+            "-a",
+            "bid",
+            "-f",
+            "tests/data/join/asof_quotes.tsv",
+            "tests/data/join/asof_trades.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "time\tsym\tqty\tbid\n\
+         2024-05-01T09:30:00\tAAPL\t20\t100.0\n\
+         2024-05-01T09:32:30\tAAPL\t30\t100.5\n\
+         2024-05-01T09:33:00\tMSFT\t40\t300.0\n\
+         2024-05-01T09:40:00\tAAPL\t50\t101.0\n"
+    );
+}
+
+#[test]
+fn join_asof_tolerance_write_all() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--asof",
+            "time",
+            "-k",
+            "sym",
+            "-a",
+            "bid",
+            "-w",
+            "NA",
+            "--tolerance",
+            "90s",
+            "-f",
+            "tests/data/join/asof_quotes.tsv",
+            "tests/data/join/asof_trades.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "time\tsym\tqty\tbid\n\
+         2024-05-01T09:29:59\tAAPL\t10\tNA\n\
+         2024-05-01T09:30:00\tAAPL\t20\t100.0\n\
+         2024-05-01T09:32:30\tAAPL\t30\t100.5\n\
+         2024-05-01T09:33:00\tMSFT\t40\tNA\n\
+         2024-05-01T09:40:00\tAAPL\t50\tNA\n"
+    );
+}
+
+#[test]
+fn join_asof_numeric_ties_take_last() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "--data-asof",
+            "2",
+            "-a",
+            "2",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("x\t5\ny\t1\nz\t3.5\nw\t-1\n")
+        .run();
+
+    assert_eq!(stdout, "x\t5\tc\ny\t1\tz\nz\t3.5\ta\n");
+}
+
+#[test]
+fn join_asof_exclude() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "-e",
+            "--tolerance",
+            "1",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("-1\n2.5\n3.5\n6\n")
+        .run();
+
+    assert_eq!(stdout, "-1\n3.5\n6\n");
+}
+
+#[test]
+fn join_asof_invalid_values() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("2024-01-01\n")
+        .run_fail();
+    assert!(stderr.contains("as-of values mix numbers and timestamps at stdin line 1"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("1\nabc\n")
+        .run_fail();
+    assert!(stderr.contains("invalid as-of value `abc` at stdin line 2"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "--tolerance",
+            "5x",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("1\n")
+        .run_fail();
+    assert!(stderr.contains("invalid --tolerance `5x`"));
+
+    // Time units are for timestamps only
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "--tolerance",
+            "5m",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("1\n")
+        .run_fail();
+    assert!(stderr
+        .contains("--tolerance `5m` has a time unit, but the as-of values are numbers"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--asof",
+            "1",
+            "--data-fields",
+            "2",
+            "-f",
+            "tests/data/join/asof_numeric.tsv",
+        ])
+        .stdin("1\tx\n")
+        .run_fail();
+    assert!(stderr.contains("--data-fields with --asof requires --key-fields"));
+}
+
+#[test]
+fn join_fuzzy_normalized() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--fuzzy",
+            "normalized",
+            "-k",
+            "vendor",
+            "-d",
+            "name",
+            "-a",
+            "id",
+            "-f",
+            "tests/data/join/fuzzy_vendors.tsv",
+            "tests/data/join/fuzzy_orders.tsv",
+        ])
+        .run();
+
+    assert_eq!(stdout, "name\tamount\tid\tscore\nglobex, inc\t200\tV2\t1\n");
+}
+
+#[test]
+fn join_fuzzy_jaro_winkler() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--fuzzy",
+            "jaro-winkler",
+            "--threshold",
+            "0.9",
+            "-k",
+            "vendor",
+            "-d",
+            "name",
+            "-a",
+            "id",
+            "-w",
+            "NA",
+            "-f",
+            "tests/data/join/fuzzy_vendors.tsv",
+            "tests/data/join/fuzzy_orders.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "name\tamount\tid\tscore\n\
+         ACME Corp.\t100\tV1\t0.9125\n\
+         globex, inc\t200\tV2\t1\n\
+         Initrode\t300\tNA\tNA\n\
+         Soylent\t400\tNA\tNA\n\
+         Umbrela Corp\t500\tV4\t0.9846\n"
+    );
+}
+
+#[test]
+fn join_fuzzy_levenshtein() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--fuzzy",
+            "levenshtein",
+            "-k",
+            "vendor",
+            "-d",
+            "name",
+            "-f",
+            "tests/data/join/fuzzy_vendors.tsv",
+            "tests/data/join/fuzzy_orders.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "name\tamount\tscore\nglobex, inc\t200\t1\nUmbrela Corp\t500\t0.9231\n"
+    );
+}
+
+#[test]
+fn join_fuzzy_exclude() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--fuzzy",
+            "jaro-winkler",
+            "-e",
+            "-k",
+            "vendor",
+            "-d",
+            "name",
+            "-f",
+            "tests/data/join/fuzzy_vendors.tsv",
+            "tests/data/join/fuzzy_orders.tsv",
+        ])
+        .run();
+
+    assert_eq!(stdout, "name\tamount\nSoylent\t400\n");
+}
+
+#[test]
+fn join_fuzzy_normalized_collision() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "-H",
+            "--fuzzy",
+            "levenshtein",
+            "-k",
+            "vendor",
+            "-d",
+            "name",
+            "-a",
+            "id",
+            "-f",
+            "tests/data/join/fuzzy_vendors_dup.tsv",
+            "tests/data/join/fuzzy_orders.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains(
+        "filter keys `Acme Corp.` and `acme corp` both normalize to `acme corp`"
+    ));
+}
+
+#[test]
+fn join_fuzzy_invalid_threshold() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "join",
+            "--fuzzy",
+            "levenshtein",
+            "--threshold",
+            "1.5",
+            "-f",
+            "tests/data/join/fuzzy_vendors.tsv",
+        ])
+        .stdin("")
+        .run_fail();
+
+    assert!(stderr.contains("--threshold must be between 0 and 1"));
+}
@@ -0,0 +1,163 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+use test_case::test_case;
+
+// ============================================================================
+// Error Handling Tests
+// ============================================================================
+
+#[test]
+fn merge_invalid_key() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["merge", "--key", "0"])
+        .stdin("a\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("field index must be >= 1"));
+}
+
+#[test]
+fn merge_unknown_key_name() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["merge", "-H", "-k", "nope", "tests/data/merge/part1.tsv"])
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("invalid key specification"));
+}
+
+#[test]
+fn merge_unsorted_input() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "merge",
+            "-H",
+            "-k",
+            "chrom:V",
+            "-k",
+            "pos:n",
+            "tests/data/merge/part1.tsv",
+            "tests/data/merge/unsorted.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains("tests/data/merge/unsorted.tsv"));
+    assert!(stderr.contains("line 3 sorts before line 2"));
+}
+
+#[test]
+fn merge_unsorted_stdin() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["merge", "-k", "1"])
+        .stdin("a\nc\nb\n")
+        .run_fail();
+
+    assert!(stderr.contains("input is not sorted: line 3 sorts before line 2"));
+}
+
+// ============================================================================
+// Merge Tests
+// ============================================================================
+
+#[test]
+fn merge_header_and_keys() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "merge",
+            "-H",
+            "-k",
+            "chrom:V",
+            "-k",
+            "pos:n",
+            "tests/data/merge/part1.tsv",
+            "tests/data/merge/part2.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "chrom\tpos\tname\n\
+         chr1\t5\ta1\n\
+         chr1\t20\tb1\n\
+         chr1\t100\ta2\n\
+         chr2\t7\ta3\n\
+         chr2\t7\tb2\n\
+         chr2\t300\tb3\n\
+         chr10\t1\ta4\n"
+    );
+}
+
+#[test]
+fn merge_ties_keep_file_order() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "merge",
+            "-H",
+            "-k",
+            "chrom:V",
+            "tests/data/merge/part2.tsv",
+            "tests/data/merge/part1.tsv",
+        ])
+        .run();
+
+    assert_eq!(
+        stdout,
+        "chrom\tpos\tname\n\
+         chr1\t20\tb1\n\
+         chr1\t5\ta1\n\
+         chr1\t100\ta2\n\
+         chr2\t7\tb2\n\
+         chr2\t300\tb3\n\
+         chr2\t7\ta3\n\
+         chr10\t1\ta4\n"
+    );
+}
+
+#[test]
+fn merge_matches_sort() {
+    let files = ["tests/data/merge/part1.tsv", "tests/data/merge/part2.tsv"];
+    let keys = ["-k", "chrom:V", "-k", "pos:n"];
+
+    let mut merge_args = vec!["merge", "-H"];
+    merge_args.extend(keys);
+    merge_args.extend(files);
+    let (merged, _) = TvaCmd::new().args(&merge_args).run();
+
+    let mut sort_args = vec!["sort", "-H"];
+    sort_args.extend(keys);
+    sort_args.extend(files);
+    let (sorted, _) = TvaCmd::new().args(&sort_args).run();
+
+    assert_eq!(merged, sorted);
+}
+
+#[test]
+fn merge_single_input_stdin() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["merge", "-k", "1"])
+        .stdin("a\nb\nb\nc\n")
+        .run();
+
+    assert_eq!(stdout, "a\nb\nb\nc\n");
+}
+
+#[test]
+fn merge_empty_input() {
+    let (stdout, _) = TvaCmd::new().args(&["merge", "-k", "1"]).stdin("").run();
+
+    assert!(stdout.is_empty());
+}
+
+#[test_case(&["-r"], "chr2\nchr10\nchr1\n" ; "reverse lexical")]
+#[test_case(&["--natural", "-r"], "chr10\nchr2\nchr1\n" ; "reverse natural")]
+fn merge_global_options(opts: &[&str], input: &str) {
+    let mut args = vec!["merge", "-k", "1"];
+    args.extend(opts);
+    let (stdout, _) = TvaCmd::new().args(&args).stdin(input).run();
+
+    assert_eq!(stdout, input);
+}
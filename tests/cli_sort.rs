@@ -143,6 +143,111 @@ fn sort_keys(input: &str, args: &[&str], expected: &str) {
     assert_eq!(stdout, expected);
 }
 
+// ============================================================================
+// Per-Key Modifier Tests
+// ============================================================================
+
+#[test_case(
+    "chr2\t5\nchr1\t5\nchr1\t10\nchr2\t50\n",
+    &["sort", "-k", "1", "-k", "2:nr"],
+    "chr1\t10\nchr1\t5\nchr2\t50\nchr2\t5\n" ; "text_then_numeric_reverse"
+)]
+#[test_case(
+    "chr10\nchr2\nchr1\n",
+    &["sort", "-k", "1:V"],
+    "chr1\nchr2\nchr10\n" ; "natural"
+)]
+#[test_case(
+    "chr10\nchr2\nchr1\n",
+    &["sort", "--natural"],
+    "chr1\nchr2\nchr10\n" ; "natural_global"
+)]
+#[test_case(
+    "3G\n10K\n512\n2M\n",
+    &["sort", "-k", "1:h"],
+    "512\n10K\n2M\n3G\n" ; "human_size"
+)]
+#[test_case(
+    "b\nA\na\nB\n",
+    &["sort", "-k", "1:f"],
+    "A\na\nb\nB\n" ; "ignore_case"
+)]
+#[test_case(
+    "b\nA\na\nB\n",
+    &["sort", "-i"],
+    "A\na\nb\nB\n" ; "ignore_case_global"
+)]
+#[test_case(
+    "1e3\nx\n-inf\n5\n",
+    &["sort", "-k", "1:g"],
+    "x\n-inf\n5\n1e3\n" ; "general_numeric"
+)]
+#[test_case(
+    "a\t2\nb\t10\nc\t1\n",
+    &["sort", "-r", "-k", "2:n"],
+    "c\t1\na\t2\nb\t10\n" ; "modifiers_override_global"
+)]
+#[test_case(
+    "a\t2\nb\t10\nc\t1\n",
+    &["sort", "-n", "-r", "-k", "2"],
+    "b\t10\na\t2\nc\t1\n" ; "global_applies_without_modifiers"
+)]
+fn sort_key_modifiers(input: &str, args: &[&str], expected: &str) {
+    let (stdout, _) = TvaCmd::new().args(args).stdin(input).run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn sort_key_by_header_name() {
+    let input =
+        "chrom\tscore\tsample\nchr2\t5\ts10\nchr1\t5\ts2\nchr1\t5\ts1\nchr1\t9\ts3\n";
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "sort", "-H", "-k", "chrom", "-k", "score:nr", "-k", "sample:V",
+        ])
+        .stdin(input)
+        .run();
+
+    assert_eq!(
+        stdout,
+        "chrom\tscore\tsample\nchr1\t9\ts3\nchr1\t5\ts1\nchr1\t5\ts2\nchr2\t5\ts10\n"
+    );
+}
+
+#[test]
+fn sort_key_name_requires_header() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "-k", "score:n"])
+        .stdin("a\t1\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("requires header"));
+}
+
+#[test]
+fn sort_key_conflicting_modifiers() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "-k", "1:nh"])
+        .stdin("a\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("conflicting sort modifiers"));
+}
+
+#[test]
+fn sort_global_comparisons_mutually_exclusive() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "-n", "--natural"])
+        .stdin("a\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("cannot be used with"));
+}
+
 // ============================================================================
 // Header Mode Tests
 // ============================================================================
@@ -325,3 +430,212 @@ fn sort_with_header_lines_n_multi_file() {
 
     assert_eq!(stdout, "# Comment 1\na\t2\nb\t3\nc\t1\nd\t4\n");
 }
+
+// ============================================================================
+// External Sort Tests
+// ============================================================================
+
+#[test_case(&["sort", "-k", "1", "-S", "1"] ; "lexicographic")]
+#[test_case(&["sort", "-k", "1", "--buffer-size", "1K"] ; "long_option")]
+fn sort_buffer_size_matches_in_memory(args: &[&str]) {
+    let input: String = (0..500)
+        .map(|i| format!("k{:03}\t{}\n", (i * 7919) % 500, i))
+        .collect();
+
+    let (expected, _) = TvaCmd::new()
+        .args(&["sort", "-k", "1"])
+        .stdin(input.clone())
+        .run();
+    let (stdout, _) = TvaCmd::new().args(args).stdin(input).run();
+
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn sort_buffer_size_numeric_reverse_stable() {
+    let input = "a\t2\nb\t10\nc\t2\nd\t1\ne\t10\n";
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&["sort", "-k", "2", "-n", "-r", "-S", "1"])
+        .stdin(input)
+        .run();
+
+    assert_eq!(stdout, "b\t10\ne\t10\na\t2\nc\t2\nd\t1\n");
+}
+
+#[test]
+fn sort_buffer_size_with_header_and_temp_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = "name\tval\nc\t1\na\t2\nb\t3\n";
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "sort",
+            "--header",
+            "-k",
+            "1",
+            "-S",
+            "1",
+            "-T",
+            dir.path().to_str().unwrap(),
+        ])
+        .stdin(input)
+        .run();
+
+    assert_eq!(stdout, "name\tval\na\t2\nb\t3\nc\t1\n");
+}
+
+#[test]
+fn sort_invalid_buffer_size() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "-S", "lots"])
+        .stdin("a\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("invalid size `lots`"));
+}
+
+// ============================================================================
+// Top-N Tests
+// ============================================================================
+
+#[test_case(&["-k", "2:nr"], 3 ; "numeric_desc")]
+#[test_case(&["-k", "1"], 5 ; "lexical")]
+#[test_case(&["-k", "2:n"], 1 ; "single")]
+#[test_case(&["-k", "3"], 4 ; "ties")]
+fn sort_top_matches_sort_head(key_args: &[&str], n: usize) {
+    let input: String = (0..200)
+        .map(|i| format!("r{}\t{}\t{}\n", (i * 37) % 101, (i * 7919) % 97, i % 3))
+        .collect();
+
+    let mut full_args = vec!["sort"];
+    full_args.extend_from_slice(key_args);
+    let (full, _) = TvaCmd::new().args(&full_args).stdin(input.clone()).run();
+    let expected: String = full.lines().take(n).map(|l| format!("{}\n", l)).collect();
+
+    let n_str = n.to_string();
+    let mut top_args = full_args.clone();
+    top_args.extend_from_slice(&["--top", &n_str]);
+    let (stdout, _) = TvaCmd::new().args(&top_args).stdin(input).run();
+
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn sort_top_group_by_with_header() {
+    let input = "region\tsales\nwest\t5\neast\t7\nwest\t9\neast\t1\nwest\t2\neast\t8\n";
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "sort",
+            "-H",
+            "-k",
+            "sales:nr",
+            "--top",
+            "2",
+            "--group-by",
+            "region",
+        ])
+        .stdin(input)
+        .run();
+
+    assert_eq!(
+        stdout,
+        "region\tsales\nwest\t9\neast\t8\neast\t7\nwest\t5\n"
+    );
+}
+
+#[test]
+fn sort_top_zero_keeps_header() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["sort", "-H", "-k", "1", "--top", "0"])
+        .stdin("h\nb\na\n")
+        .run();
+
+    assert_eq!(stdout, "h\n");
+}
+
+#[test]
+fn sort_group_by_requires_top() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "-k", "1", "--group-by", "1"])
+        .stdin("a\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("--top"));
+}
+
+// ============================================================================
+// Check Mode Tests
+// ============================================================================
+
+#[test]
+fn sort_check_sorted_input() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "--check", "-k", "2:n"])
+        .stdin("a\t1\nb\t2\nc\t2\nd\t10\n")
+        .run();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn sort_check_unsorted_line_number() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "-c", "-H", "-k", "score:nr"])
+        .stdin("id\tscore\na\t9\nb\t7\nc\t8\nd\t1\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("input is not sorted: line 4 sorts before line 3"));
+}
+
+#[test]
+fn sort_check_uses_sort_comparator() {
+    // Lexically unsorted, but sorted in natural order
+    let input = "chr1\nchr2\nchr10\n";
+    TvaCmd::new()
+        .args(&["sort", "--check", "--natural", "-k", "1"])
+        .stdin(input)
+        .run();
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["sort", "--check", "-k", "1"])
+        .stdin(input)
+        .run_fail();
+    assert!(stderr.contains("line 3 sorts before line 2"));
+}
+
+#[test]
+fn sort_check_per_file() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "sort",
+            "--check",
+            "-H",
+            "-k",
+            "chrom:V",
+            "-k",
+            "pos:n",
+            "tests/data/merge/part1.tsv",
+            "tests/data/merge/unsorted.tsv",
+        ])
+        .run_fail();
+
+    assert!(stderr.contains("tests/data/merge/unsorted.tsv"));
+    assert!(stderr.contains("line 3 sorts before line 2"));
+}
+
+#[test]
+fn sort_check_conflicts_with_top() {
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["sort", "--check", "--top", "1"])
+        .stdin("a\n")
+        .run_fail();
+
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("cannot be used with"));
+}
@@ -2491,3 +2491,581 @@ fn stats_header_hash1_quantile_custom_header() {
 
     assert!(stdout.contains("MedianValue"));
 }
+
+#[test]
+fn stats_approx_quantiles_small_groups_exact() {
+    let args = [
+        "--median 2",
+        "--q1 2",
+        "--q3 2",
+        "--iqr 2",
+        "--quantile 2:0.1,0.9",
+    ];
+    for arg in args {
+        let exact: Vec<&str> = std::iter::once("stats")
+            .chain("-g 1".split_whitespace())
+            .chain(arg.split_whitespace())
+            .collect();
+        let approx: Vec<&str> = exact
+            .iter()
+            .copied()
+            .chain(std::iter::once("--approx-quantiles"))
+            .collect();
+        let (expected, _) = TvaCmd::new().args(&exact).stdin(INPUT_NEW).run();
+        let (stdout, _) = TvaCmd::new().args(&approx).stdin(INPUT_NEW).run();
+        assert_eq!(stdout, expected, "{}", arg);
+    }
+}
+
+#[test]
+fn stats_approx_quantiles_large_input() {
+    // 0..20000 in a scrambled order
+    let input: String = (0..20_000u64)
+        .map(|i| format!("{}\n", (i * 7919) % 20_000))
+        .collect();
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--approx-quantiles",
+            "--compression",
+            "50",
+            "--median",
+            "1",
+            "--quantile",
+            "1:0,1",
+        ])
+        .stdin(&input)
+        .run();
+
+    let fields: Vec<f64> = stdout
+        .trim()
+        .split('\t')
+        .map(|s| s.parse().unwrap())
+        .collect();
+    // Within the documented bound of ±π/50 of the rank at the median
+    assert!((fields[0] - 9_999.5).abs() < 20_000.0 * 0.063, "{}", stdout);
+    assert_eq!(fields[1], 0.0);
+    assert_eq!(fields[2], 19_999.0);
+}
+
+#[test]
+fn stats_compression_requires_approx() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "--compression", "50", "--median", "1"])
+        .stdin("1\n")
+        .run_fail();
+    assert!(stderr.contains("--approx-quantiles"));
+}
+
+#[test]
+fn stats_approx_nunique() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-H",
+            "-g",
+            "header1",
+            "--nunique",
+            "value",
+            "--approx-nunique",
+            "header2",
+        ])
+        .stdin(INPUT_BASIC)
+        .run();
+    assert_eq!(
+        stdout,
+        "header1\tvalue_nunique\theader2_approx_nunique\nA\t3\t2\nB\t3\t2\n"
+    );
+}
+
+#[test]
+fn stats_approx_nunique_large_input() {
+    // 5000 distinct values, each seen twice
+    let input: String = (0..10_000).map(|i| format!("id{}\n", i % 5_000)).collect();
+    let (stdout, _) = TvaCmd::new()
+        .args(&["stats", "--approx-nunique", "1", "--hll-precision", "12"])
+        .stdin(input)
+        .run();
+    let estimate: f64 = stdout.trim().parse().unwrap();
+    // Three standard errors at precision 12
+    assert!((estimate - 5_000.0).abs() < 5_000.0 * 0.049, "{}", estimate);
+}
+
+#[test]
+fn stats_hll_precision_out_of_range() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "--approx-nunique", "1", "--hll-precision", "20"])
+        .stdin("a\n")
+        .run_fail();
+    assert!(stderr.contains("--hll-precision must be between 4 and 18"));
+}
+
+fn run_partial(args: &[&str], input: &str) -> NamedTempFile {
+    let args: Vec<&str> = ["stats", "--partial"].iter().chain(args).copied().collect();
+    let (stdout, _) = TvaCmd::new().args(&args).stdin(input).run();
+    create_file(&stdout)
+}
+
+#[test]
+fn stats_merge_partials_matches_single_pass() {
+    let ops = [
+        "-H",
+        "-g",
+        "header1",
+        "--count",
+        "--mean",
+        "value",
+        "--stdev",
+        "value",
+        "--median",
+        "value",
+        "--first",
+        "header2",
+        "--last",
+        "header2",
+        "--unique-values",
+        "header2",
+        "--values",
+        "value",
+        "--approx-nunique",
+        "value",
+    ];
+    let (expected, _) = TvaCmd::new()
+        .args(&[&["stats"], &ops[..]].concat())
+        .stdin(INPUT_BASIC)
+        .run();
+
+    // Shards split group A, and one of them is empty
+    let shard_a = run_partial(&ops, "header1\theader2\tvalue\nA\tX\t10\nA\tX\t20\n");
+    let shard_b = run_partial(&ops, "header1\theader2\tvalue\n");
+    let shard_c = run_partial(
+        &ops,
+        "header1\theader2\tvalue\nA\tY\t30\nB\tX\t40\nB\tY\t50\nB\tY\t60\n",
+    );
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--merge-partials",
+            shard_a.path().to_str().unwrap(),
+            shard_b.path().to_str().unwrap(),
+            shard_c.path().to_str().unwrap(),
+        ])
+        .run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn stats_merge_partials_ungrouped() {
+    let ops = ["--sum", "2", "--last", "1", "--quantile", "2:0.25,0.75"];
+    let shard_a = run_partial(&ops, INPUT_NEW);
+    let shard_b = run_partial(&ops, "C\t100\n");
+    let shard_empty = run_partial(&ops, "");
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--merge-partials",
+            shard_a.path().to_str().unwrap(),
+            shard_b.path().to_str().unwrap(),
+            shard_empty.path().to_str().unwrap(),
+        ])
+        .run();
+    // 10, 20, 30, 40, 50, 100
+    assert_eq!(stdout, "250\tC\t22.5\t47.5\n");
+}
+
+#[test]
+fn stats_merge_partials_errors() {
+    let shard_a = run_partial(&["--sum", "2"], INPUT_NEW);
+    let shard_b = run_partial(&["--mean", "2"], INPUT_NEW);
+    let plain = create_file(INPUT_NEW);
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--merge-partials",
+            shard_a.path().to_str().unwrap(),
+            shard_b.path().to_str().unwrap(),
+        ])
+        .run_fail();
+    assert!(stderr.contains("different operations or settings"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "--merge-partials", plain.path().to_str().unwrap()])
+        .run_fail();
+    assert!(stderr.contains("not a tva stats partial file"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--merge-partials",
+            "--sum",
+            "2",
+            shard_a.path().to_str().unwrap(),
+        ])
+        .run_fail();
+    assert!(stderr.contains("takes its operations from the partial files"));
+}
+
+const INPUT_PAIRS: &str = "g\tx\ty
+A\t1\t2
+A\t2\t4
+A\t3\t5
+A\t4\t4
+A\t5\t5
+B\t1\t10
+B\t2\t20
+B\t3\t30
+B\t10\t40
+";
+
+#[test]
+fn stats_moments_and_correlation() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-H",
+            "-g",
+            "g",
+            "--skewness",
+            "x",
+            "--kurtosis",
+            "x",
+            "--cov",
+            "x,y",
+            "--corr",
+            "x,y",
+            "--spearman",
+            "x,y:rho",
+        ])
+        .stdin(INPUT_PAIRS)
+        .run();
+    assert_eq!(
+        stdout,
+        "g\tx_skewness\tx_kurtosis\tx_y_covariance\tx_y_pearson\trho\n\
+         A\t0\t-1.2\t1.5\t0.7746\t0.7379\n\
+         B\t1.7636\t3.228\t46.6667\t0.8854\t1\n"
+    );
+}
+
+#[test]
+fn stats_correlation_skips_missing_pairs() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["stats", "--pearson", "1,2", "--skewness", "1"])
+        .stdin("1\t2\n2\t\n3\t6\n\t7\n")
+        .run();
+    // Only the complete pairs (1,2) and (3,6) count for --pearson, while
+    // --skewness sees 1, 2 and 3
+    assert_eq!(stdout, "1\t0\n");
+}
+
+#[test]
+fn stats_correlation_needs_two_fields() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "-H", "--pearson", "x"])
+        .stdin(INPUT_PAIRS)
+        .run_fail();
+    assert!(stderr.contains("--pearson needs exactly two fields, got 1"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "-H", "--spearman", "g,x,y"])
+        .stdin(INPUT_PAIRS)
+        .run_fail();
+    assert!(stderr.contains("--spearman needs exactly two fields, got 3"));
+}
+
+#[test]
+fn stats_merge_partials_moments() {
+    let ops = [
+        "-H",
+        "-g",
+        "g",
+        "--skewness",
+        "x",
+        "--kurtosis",
+        "x",
+        "--cov",
+        "x,y",
+        "--corr",
+        "x,y",
+        "--spearman",
+        "x,y",
+    ];
+    let (expected, _) = TvaCmd::new()
+        .args(&[&["stats"], &ops[..]].concat())
+        .stdin(INPUT_PAIRS)
+        .run();
+
+    let lines: Vec<&str> = INPUT_PAIRS.lines().collect();
+    let first = format!("{}\n", lines[..4].join("\n"));
+    let second = format!("{}\n{}\n", lines[0], lines[4..].join("\n"));
+    let shard_a = run_partial(&ops, &first);
+    let shard_b = run_partial(&ops, &second);
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--merge-partials",
+            shard_a.path().to_str().unwrap(),
+            shard_b.path().to_str().unwrap(),
+        ])
+        .run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn stats_sorted_matches_hash_grouping() {
+    let ops = ["-H", "-g", "header1,header2", "--count", "--mean", "value"];
+    let (expected, _) = TvaCmd::new()
+        .args(&[&["stats"], &ops[..]].concat())
+        .stdin(INPUT_BASIC)
+        .run();
+    let (stdout, _) = TvaCmd::new()
+        .args(&[&["stats", "--sorted"], &ops[..]].concat())
+        .stdin(INPUT_BASIC)
+        .run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn stats_sorted_group_spans_files() {
+    let first = create_file("A\t1\nB\t2\n");
+    let second = create_file("B\t3\nC\t4\n");
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-g",
+            "1",
+            "--sorted",
+            "--sum",
+            "2",
+            first.path().to_str().unwrap(),
+            second.path().to_str().unwrap(),
+        ])
+        .run();
+    assert_eq!(stdout, "A\t1\nB\t5\nC\t4\n");
+}
+
+#[test]
+fn stats_sorted_not_sorted() {
+    // A key that comes back after its group was written
+    let (stdout, stderr) = TvaCmd::new()
+        .args(&["stats", "-g", "1", "--sorted", "--sum", "2"])
+        .stdin("A\t1\nB\t2\nA\t3\n")
+        .run_fail();
+    // Groups already closed were written before the error
+    assert_eq!(stdout, "A\t1\nB\t2\n");
+    assert!(stderr.contains("group `A` sorts before group `B`"));
+
+    // Grouped, but not sorted
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "-g", "1", "--sorted", "--sum", "2"])
+        .stdin("B\t1\nB\t2\nA\t3\n")
+        .run_fail();
+    assert!(stderr.contains("input is not sorted by the --group-by fields"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "--sorted", "--sum", "2"])
+        .stdin("A\t1\n")
+        .run_fail();
+    assert!(stderr.contains("--group-by"));
+}
+
+#[test]
+fn stats_sorted_partial() {
+    let ops = ["-g", "1", "--sorted", "--sum", "2", "--last", "2"];
+    let shard_a = run_partial(&ops, "A\t1\nB\t2\n");
+    let shard_b = run_partial(&ops, "B\t3\nC\t4\n");
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--merge-partials",
+            shard_a.path().to_str().unwrap(),
+            shard_b.path().to_str().unwrap(),
+        ])
+        .run();
+    assert_eq!(stdout, "A\t1\t1\nB\t5\t3\nC\t4\t4\n");
+}
+
+#[test]
+fn stats_rollup() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-H",
+            "-g",
+            "header1,header2",
+            "--rollup",
+            "--count",
+            "--sum",
+            "value",
+        ])
+        .stdin(INPUT_BASIC)
+        .run();
+    assert_eq!(
+        stdout,
+        "header1\theader2\tcount\tvalue_sum\n\
+         A\tX\t2\t30\n\
+         A\tY\t1\t30\n\
+         A\t(all)\t3\t60\n\
+         B\tX\t1\t40\n\
+         B\tY\t2\t110\n\
+         B\t(all)\t3\t150\n\
+         (all)\t(all)\t6\t210\n"
+    );
+}
+
+#[test]
+fn stats_cube_with_placeholder() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-H",
+            "-g",
+            "header1,header2",
+            "--cube",
+            "--placeholder",
+            "*",
+            "--mean",
+            "value",
+        ])
+        .stdin(INPUT_BASIC)
+        .run();
+    assert_eq!(
+        stdout,
+        "header1\theader2\tvalue_mean\n\
+         A\tX\t15\n\
+         A\tY\t30\n\
+         A\t*\t20\n\
+         B\tX\t40\n\
+         B\tY\t55\n\
+         B\t*\t50\n\
+         *\tX\t23.3333\n\
+         *\tY\t46.6667\n\
+         *\t*\t35\n"
+    );
+}
+
+#[test]
+fn stats_rollup_placeholder_value_in_data() {
+    // A real "(all)" value stays a separate group
+    let (stdout, _) = TvaCmd::new()
+        .args(&["stats", "-g", "1", "--rollup", "--sum", "2"])
+        .stdin("(all)\t1\nA\t2\n")
+        .run();
+    assert_eq!(stdout, "(all)\t1\nA\t2\n(all)\t3\n");
+}
+
+#[test]
+fn stats_rollup_errors() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "--sum", "2", "--placeholder", "x"])
+        .stdin(INPUT_NEW)
+        .run_fail();
+    assert!(stderr.contains("--placeholder requires --rollup or --cube"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "-g", "1", "--rollup", "--cube", "--sum", "2"])
+        .stdin(INPUT_NEW)
+        .run_fail();
+    assert!(stderr.contains("cannot be used with"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "--rollup", "--sum", "2"])
+        .stdin(INPUT_NEW)
+        .run_fail();
+    assert!(stderr.contains("--group-by"));
+}
+
+const INPUT_SCORES: &str = "class\tscore
+A\t2
+B\t5
+A\t4
+A\t
+B\t5
+A\t6
+";
+
+#[test]
+fn stats_transform_grouped() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-H",
+            "-g",
+            "class",
+            "--transform",
+            "--mean",
+            "score",
+            "--zscore",
+            "score",
+            "--dense-rank",
+            "score",
+            "--ordinal-rank",
+            "score",
+            "--percent-rank",
+            "score",
+            "--share",
+            "score:frac",
+        ])
+        .stdin(INPUT_SCORES)
+        .run();
+    assert_eq!(
+        stdout,
+        "class\tscore\tscore_mean\tscore_zscore\tscore_dense_rank\t\
+         score_ordinal_rank\tscore_percent_rank\tfrac\n\
+         A\t2\t4\t-1\t1\t1\t0\t0.1667\n\
+         B\t5\t5\tnan\t1\t1\t0\t0.5\n\
+         A\t4\t4\t0\t2\t2\t0.5\t0.3333\n\
+         A\t\t4\t\t\t\t\t\n\
+         B\t5\t5\tnan\t1\t2\t0\t0.5\n\
+         A\t6\t4\t1\t3\t3\t1\t0.5\n"
+    );
+}
+
+#[test]
+fn stats_transform_ungrouped_no_header() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "--transform",
+            "--count",
+            "--sum",
+            "2",
+            "--share",
+            "2",
+        ])
+        .stdin("a\t1\nb\t3\n")
+        .run();
+    assert_eq!(stdout, "a\t1\t2\t4\t0.25\nb\t3\t2\t4\t0.75\n");
+}
+
+#[test]
+fn stats_transform_errors() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "-H", "--zscore", "score"])
+        .stdin(INPUT_SCORES)
+        .run_fail();
+    assert!(stderr.contains("--transform"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&[
+            "stats",
+            "-H",
+            "-g",
+            "1",
+            "--transform",
+            "--sorted",
+            "--count",
+        ])
+        .stdin(INPUT_SCORES)
+        .run_fail();
+    assert!(stderr.contains("cannot be used with"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["stats", "-H", "--transform", "--zscore", "1,2:z"])
+        .stdin(INPUT_SCORES)
+        .run_fail();
+    assert!(stderr.contains("custom header is not allowed with multiple fields"));
+}
@@ -0,0 +1,84 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+
+const INPUT: &str = "id\tsp\tmass\tday\tnote
+1\ta\t3.5\t2024-01-01\t
+2\tb\t4\t2024-01-03\t
+3\ta\t\t2023-12-30\t
+";
+
+const REPORT_HEADER: &str =
+    "field\ttype\tcount\tmissing\tnunique\tmin\tmax\tmean\tstdev\tmax_length\ttop\n";
+
+#[test]
+fn summary_all_columns() {
+    let (stdout, _) = TvaCmd::new().args(&["summary", "-H"]).stdin(INPUT).run();
+    assert_eq!(
+        stdout,
+        format!(
+            "{}{}{}{}{}{}",
+            REPORT_HEADER,
+            "id\tint\t3\t0\t3\t1\t3\t2\t1\t1\t1:1,2:1,3:1\n",
+            "sp\tstring\t3\t0\t2\ta\tb\t\t\t1\ta:2,b:1\n",
+            "mass\tfloat\t2\t1\t2\t3.5\t4\t3.75\t0.3536\t3\t3.5:1,4:1\n",
+            "day\tdate\t3\t0\t3\t2023-12-30\t2024-01-03\t\t\t10\t\
+             2024-01-01:1,2024-01-03:1,2023-12-30:1\n",
+            "note\tempty\t0\t3\t0\t\t\t\t\t0\t\n",
+        )
+    );
+}
+
+#[test]
+fn summary_fields_and_top() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["summary", "-H", "-f", "mass,sp", "--top", "1", "-p", "1"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        format!(
+            "{}{}{}",
+            REPORT_HEADER,
+            "mass\tfloat\t2\t1\t2\t3.5\t4\t3.8\t0.4\t3\t3.5:1\n",
+            "sp\tstring\t3\t0\t2\ta\tb\t\t\t1\ta:2\n",
+        )
+    );
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&["summary", "-H", "-f", "sp", "--top", "0"])
+        .stdin(INPUT)
+        .run();
+    assert!(stdout.ends_with("\t1\t\n"));
+}
+
+#[test]
+fn summary_no_header() {
+    let (stdout, _) = TvaCmd::new().args(&["summary"]).stdin("x\t1\ny\t2\n").run();
+    assert_eq!(
+        stdout,
+        format!(
+            "{}{}{}",
+            REPORT_HEADER,
+            "field1\tstring\t2\t0\t2\tx\ty\t\t\t1\tx:1,y:1\n",
+            "field2\tint\t2\t0\t2\t1\t2\t1.5\t0.7071\t1\t1:1,2:1\n",
+        )
+    );
+}
+
+#[test]
+fn summary_empty_input() {
+    let (stdout, _) = TvaCmd::new().args(&["summary", "-H"]).stdin("").run();
+    assert_eq!(stdout, REPORT_HEADER);
+}
+
+#[test]
+fn summary_unknown_field() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["summary", "-H", "-f", "nope"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(!stderr.is_empty());
+}
@@ -0,0 +1,104 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+
+const INPUT: &str = "arm\tscore\nA\t1.1\nA\t2.3\nA\t2.3\nA\t4.0\nA\t5.2\n\
+                     B\t3.4\nB\t4.4\nB\t5.0\nB\t6.1\nB\t6.1\nB\t7.3\nB\tNA\n";
+
+#[test]
+fn test_all_tests() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        "test\tgroup1\tgroup2\tn1\tn2\tstatistic\tdf\tp_value\n\
+         student\tA\tB\t5\t6\t-2.6508\t9\t0.0264\n\
+         welch\tA\tB\t5\t6\t-2.6124\t8.0409\t0.0309\n\
+         mann-whitney\tA\tB\t5\t6\t4\t\t0.0541\n\
+         ks\tA\tB\t5\t6\t0.6333\t\t0.133\n"
+    );
+}
+
+#[test]
+fn test_levels_and_selected_tests() {
+    let input = format!("{}C\t9\n", INPUT);
+    let (stdout, _) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm", "--levels", "B,A"])
+        .args(&["--tests", "welch,ks", "-p", "2"])
+        .stdin(input.as_str())
+        .run();
+    assert_eq!(
+        stdout,
+        "test\tgroup1\tgroup2\tn1\tn2\tstatistic\tdf\tp_value\n\
+         welch\tB\tA\t6\t5\t2.61\t8.04\t0.0309\n\
+         ks\tB\tA\t6\t5\t0.63\t\t0.133\n"
+    );
+}
+
+#[test]
+fn test_skips_non_finite() {
+    let input = format!("{}A\tnan\nB\tinf\nB\t-inf\n", INPUT);
+    let (stdout, _) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm"])
+        .stdin(input.as_str())
+        .run();
+    let (expected, _) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm"])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn test_small_p_value() {
+    let mut input = String::new();
+    for i in 0..50 {
+        input.push_str(&format!("x\t{}\ny\t{}\n", i, i + 100));
+    }
+    let (stdout, _) = TvaCmd::new()
+        .args(&["test", "-v", "2", "-g", "1", "-t", "student"])
+        .stdin(input.as_str())
+        .run();
+    let p_value = stdout.lines().nth(1).unwrap().rsplit('\t').next().unwrap();
+    assert!(p_value.contains("e-"), "{}", p_value);
+}
+
+#[test]
+fn test_errors() {
+    let input = format!("{}C\t9\n", INPUT);
+    let (_, stderr) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm"])
+        .stdin(input.as_str())
+        .run_fail();
+    assert!(stderr.contains("exactly two groups with numeric values, found 3"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm", "--levels", "A,D"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("group `D` has no numeric values"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["test", "-H", "-v", "score", "-g", "arm", "-t", "anova"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("unknown test `anova`"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["test", "-v", "2", "-g", "1", "-t", "ks,welch"])
+        .stdin("x\t1\ny\t2\ny\t3\n")
+        .run_fail();
+    assert!(
+        stderr.contains("welch needs at least two values per group, group `x` has 1")
+    );
+
+    // Rank and distribution tests take single values
+    TvaCmd::new()
+        .args(&["test", "-v", "2", "-g", "1", "-t", "mann-whitney,ks"])
+        .stdin("x\t1\ny\t2\ny\t3\n")
+        .run();
+}
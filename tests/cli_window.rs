@@ -0,0 +1,137 @@
+#[macro_use]
+#[path = "common/mod.rs"]
+mod common;
+
+use common::TvaCmd;
+use test_case::test_case;
+
+const INPUT: &str = "store\tday\tsales
+A\t1\t10
+B\t1\t5
+A\t2\t14
+A\t3\t
+B\t2\t7
+A\t4\t20
+";
+
+#[test_case(
+    &["-w", "row_number"],
+    "row_number\n1\n2\n3\n4\n5\n6\n"
+    ; "row_number"
+)]
+#[test_case(
+    &["-w", "cumsum:sales", "-w", "cummax:sales"],
+    "sales_cumsum\tsales_cummax\n10\t10\n15\t10\n29\t14\n\t\n36\t14\n56\t20\n"
+    ; "cumulative"
+)]
+#[test_case(
+    &["-w", "lag:sales", "-w", "lead:sales:2"],
+    "sales_lag\tsales_lead_2\n\t14\n10\t\n5\t7\n14\t20\n\t\n7\t\n"
+    ; "lag_lead"
+)]
+#[test_case(
+    &["-w", "rolling_mean:sales:3", "-w", "rolling_sum:sales:2:sum2"],
+    "sales_rolling_mean_3\tsum2\n10\t10\n7.5\t15\n9.6667\t19\n9.5\t14\n10.5\t7\n13.5\t27\n"
+    ; "rolling"
+)]
+fn window_functions(args: &[&str], expected_appended: &str) {
+    let args: Vec<&str> = ["window", "-H"].iter().chain(args).copied().collect();
+    let (stdout, _) = TvaCmd::new().args(&args).stdin(INPUT).run();
+
+    let appended: Vec<String> = stdout
+        .lines()
+        .map(|line| line.splitn(4, '\t').nth(3).unwrap_or("").to_string())
+        .collect();
+    assert_eq!(appended.join("\n") + "\n", expected_appended);
+}
+
+#[test]
+fn window_group_by() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "window",
+            "-H",
+            "-g",
+            "store",
+            "-w",
+            "row_number",
+            "-w",
+            "diff:sales",
+            "-w",
+            "pct_change:sales",
+            "-w",
+            "lead:day",
+        ])
+        .stdin(INPUT)
+        .run();
+    assert_eq!(
+        stdout,
+        "store\tday\tsales\trow_number\tsales_diff\tsales_pct_change\tday_lead\n\
+         A\t1\t10\t1\t\t\t2\n\
+         B\t1\t5\t1\t\t\t2\n\
+         A\t2\t14\t2\t4\t0.4\t3\n\
+         A\t3\t\t3\t\t\t4\n\
+         B\t2\t7\t2\t2\t0.4\t\n\
+         A\t4\t20\t4\t\t\t\n"
+    );
+}
+
+#[test]
+fn window_rank() {
+    let (stdout, _) = TvaCmd::new()
+        .args(&["window", "-g", "1", "-w", "rank:2", "-w", "dense_rank:2"])
+        .stdin("x\t9\nx\t7\nx\t7\nx\t5\ny\t1\n")
+        .run();
+    assert_eq!(
+        stdout,
+        "x\t9\t1\t1\nx\t7\t2\t2\nx\t7\t2\t2\nx\t5\t4\t3\ny\t1\t1\t1\n"
+    );
+}
+
+#[test]
+fn window_continues_across_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let first = dir.path().join("a.tsv");
+    let second = dir.path().join("b.tsv");
+    std::fs::write(&first, "n\n1\n2\n").unwrap();
+    std::fs::write(&second, "n\n3\n").unwrap();
+
+    let (stdout, _) = TvaCmd::new()
+        .args(&[
+            "window",
+            "-H",
+            "-w",
+            "cumsum:n",
+            first.to_str().unwrap(),
+            second.to_str().unwrap(),
+        ])
+        .run();
+    assert_eq!(stdout, "n\tn_cumsum\n1\t1\n2\t3\n3\t6\n");
+}
+
+#[test]
+fn window_errors() {
+    let (_, stderr) = TvaCmd::new()
+        .args(&["window", "-H", "-w", "median:sales"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("unknown window function `median`"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["window", "-H", "-w", "rolling_mean:sales"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("needs a window size"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["window", "-H", "-w", "lag:day,sales"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(stderr.contains("needs a single field, got 2"));
+
+    let (_, stderr) = TvaCmd::new()
+        .args(&["window", "-w", "cumsum:sales"])
+        .stdin(INPUT)
+        .run_fail();
+    assert!(!stderr.is_empty());
+}